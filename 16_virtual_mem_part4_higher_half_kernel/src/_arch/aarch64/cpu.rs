@@ -19,6 +19,21 @@ use cortex_a::asm;
 
 pub use asm::nop;
 
+/// Pause execution on the core until an interrupt is pending.
+#[inline(always)]
+pub fn wait_for_interrupt() {
+    asm::wfi()
+}
+
+/// Pause execution on the core until an event is signaled, e.g. with
+/// [`smp::send_event()`](crate::cpu::smp::send_event).
+///
+/// Might return early, e.g. for an interrupt or a leftover event.
+#[inline(always)]
+pub fn wait_for_event() {
+    asm::wfe()
+}
+
 /// Pause execution on the core.
 #[inline(always)]
 pub fn wait_forever() -> ! {
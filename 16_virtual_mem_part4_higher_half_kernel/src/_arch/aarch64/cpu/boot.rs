@@ -30,7 +30,7 @@ global_asm!(include_str!("boot.s"));
 /// - The HW state of EL1 must be prepared in a sound way.
 #[inline(always)]
 unsafe fn prepare_el2_to_el1_transition(
-    virt_boot_core_stack_end_exclusive_addr: u64,
+    virt_stack_end_exclusive_addr: u64,
     virt_runtime_init_addr: u64,
 ) {
     // Enable timer counter registers for EL1.
@@ -45,7 +45,8 @@ unsafe fn prepare_el2_to_el1_transition(
     // Set up a simulated exception return.
     //
     // First, fake a saved program status where all interrupts were masked and SP_EL1 was used as a
-    // stack pointer.
+    // stack pointer. SErrors stay masked until the exception vectors are installed, see
+    // `exception::handling_init()`.
     SPSR_EL2.write(
         SPSR_EL2::D::Masked
             + SPSR_EL2::A::Masked
@@ -59,7 +60,7 @@ unsafe fn prepare_el2_to_el1_transition(
 
     // Set up SP_EL1 (stack pointer), which will be used by EL1 once we "return" to it. Since there
     // are no plans to ever return to EL2, just re-use the same stack.
-    SP_EL1.set(virt_boot_core_stack_end_exclusive_addr);
+    SP_EL1.set(virt_stack_end_exclusive_addr);
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -68,22 +69,21 @@ unsafe fn prepare_el2_to_el1_transition(
 
 /// The Rust entry of the `kernel` binary.
 ///
-/// The function is called from the assembly `_start` function.
+/// The function is called from the assembly `_start` function on the boot core, and from
+/// `_start_secondary` on the secondary cores.
 ///
 /// # Safety
 ///
 /// - The `bss` section is not initialized yet. The code must not use or reference it in any way.
-/// - Exception return from EL2 must must continue execution in EL1 with `runtime_init()`.
+/// - Exception return from EL2 must must continue execution in EL1 with `runtime_init()` or
+///   `secondary_runtime_init()`.
 #[no_mangle]
 pub unsafe extern "C" fn _start_rust(
     phys_kernel_tables_base_addr: u64,
-    virt_boot_core_stack_end_exclusive_addr: u64,
+    virt_stack_end_exclusive_addr: u64,
     virt_runtime_init_addr: u64,
 ) -> ! {
-    prepare_el2_to_el1_transition(
-        virt_boot_core_stack_end_exclusive_addr,
-        virt_runtime_init_addr,
-    );
+    prepare_el2_to_el1_transition(virt_stack_end_exclusive_addr, virt_runtime_init_addr);
 
     // Turn on the MMU for EL1.
     let addr = Address::new(phys_kernel_tables_base_addr as usize);
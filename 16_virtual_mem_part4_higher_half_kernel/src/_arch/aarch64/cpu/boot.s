@@ -85,3 +85,43 @@ _start:
 .size	_start, . - _start
 .type	_start, function
 .global	_start
+
+//------------------------------------------------------------------------------
+// fn _start_secondary()
+//------------------------------------------------------------------------------
+_start_secondary:
+	// Only proceed if the core executes in EL2. Park it otherwise.
+	mrs	x0, CurrentEL
+	cmp	x0, _EL2
+	b.ne	1f
+
+	// Offset of this core's stack end from the start of the secondary core stacks. Core N uses
+	// the N-th stack slot, counting from one.
+	mrs	x3, MPIDR_EL1
+	and	x3, x3, _core_id_mask
+	ADR_ABS	x4, __secondary_core_stack_stride // provided by the linker script
+	mul	x3, x3, x4
+
+	// Load the base address of the kernel's translation tables.
+	ldr	x0, PHYS_KERNEL_TABLES_BASE_ADDR // provided by bsp/__board_name__/memory/mmu.rs
+
+	// Load the _absolute_ addresses of this core's stack end and of the Rust entry.
+	ADR_ABS	x1, __secondary_core_stacks_start
+	add	x1, x1, x3
+	ADR_ABS	x2, secondary_runtime_init
+
+	// Set the stack pointer to the PC-relative address of the stack, same as in _start().
+	ADR_REL	x4, __secondary_core_stacks_start
+	add	x4, x4, x3
+	mov	sp, x4
+
+	// Jump to Rust code. x0, x1 and x2 hold the function arguments provided to _start_rust().
+	b	_start_rust
+
+	// Infinitely wait for events (aka "park the core").
+1:	wfe
+	b	1b
+
+.size	_start_secondary, . - _start_secondary
+.type	_start_secondary, function
+.global	_start_secondary
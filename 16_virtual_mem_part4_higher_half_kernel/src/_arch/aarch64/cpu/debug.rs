@@ -0,0 +1,535 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Architectural self-hosted debug.
+//!
+//! Breakpoints and watchpoints are programmed through the `DBGBVR<n>_EL1/DBGBCR<n>_EL1` and
+//! `DBGWVR<n>_EL1/DBGWCR<n>_EL1` register pairs. Debug exceptions are taken before the triggering
+//! instruction executes. To make progress, the triggered slot is disabled and the instruction is
+//! executed with software step, after which the slot is enabled again.
+//!
+//! The debug registers are banked per core. The slots are kept in one global table and programmed
+//! on every online core, and secondary cores replay them during their init.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::cpu::debug::arch_debug
+
+use crate::{
+    cpu::{
+        debug::{DebugCallback, DebugEvent, DebugEventKind, WatchpointKind},
+        percpu::PerCpu,
+    },
+    exception::asynchronous::ipi,
+    memory::{Address, Virtual},
+    synchronization,
+    synchronization::IRQSafeSpinLock,
+};
+use cortex_a::{barrier, regs::*};
+use register::{register_bitfields, InMemoryRegister};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u64,
+
+    /// Debug Breakpoint Control Register.
+    DBGBCR [
+        /// Breakpoint Type.
+        BT OFFSET(20) NUMBITS(4) [
+            UnlinkedAddressMatch = 0b0000
+        ],
+
+        /// Byte address select. All bytes for A64 instructions.
+        BAS OFFSET(5) NUMBITS(4) [
+            A64 = 0b1111
+        ],
+
+        /// Privilege mode control.
+        PMC OFFSET(1) NUMBITS(2) [
+            EL1 = 0b01
+        ],
+
+        /// Enable.
+        E OFFSET(0) NUMBITS(1) []
+    ],
+
+    /// Debug Watchpoint Control Register.
+    DBGWCR [
+        /// Address mask. Number of low address bits that are masked out, 0 for no mask.
+        MASK OFFSET(24) NUMBITS(5) [],
+
+        /// Byte address select, one bit per byte of the watched doubleword.
+        BAS OFFSET(5) NUMBITS(8) [],
+
+        /// Load/store control.
+        LSC OFFSET(3) NUMBITS(2) [
+            Load = 0b01,
+            Store = 0b10,
+            LoadStore = 0b11
+        ],
+
+        /// Privilege access control.
+        PAC OFFSET(1) NUMBITS(2) [
+            EL1 = 0b01
+        ],
+
+        /// Enable.
+        E OFFSET(0) NUMBITS(1) []
+    ]
+}
+
+/// The architectural maximum of breakpoint and watchpoint register pairs.
+const MAX_SLOTS: usize = 16;
+
+/// MDSCR_EL1 bits.
+mod mdscr_bits {
+    pub const SS: u64 = 1 << 0;
+    pub const KDE: u64 = 1 << 13;
+    pub const MDE: u64 = 1 << 15;
+}
+
+/// SPSR bits that are manipulated while stepping over a triggered instruction.
+mod spsr_bits {
+    pub const SS: u64 = 1 << 21;
+    pub const I: u64 = 1 << 7;
+    pub const F: u64 = 1 << 6;
+}
+
+#[derive(Copy, Clone)]
+struct Slot {
+    addr: usize,
+    len: usize,
+    value: usize,
+    ctrl: u64,
+    callback: Option<DebugCallback>,
+}
+
+#[derive(Copy, Clone)]
+struct PendingStep {
+    kind: DebugEventKind,
+    slot: usize,
+    saved_spsr_bits: u64,
+}
+
+struct DebugState {
+    breakpoints: [Option<Slot>; MAX_SLOTS],
+    watchpoints: [Option<Slot>; MAX_SLOTS],
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static DEBUG_STATE: IRQSafeSpinLock<DebugState> = IRQSafeSpinLock::new(DebugState {
+    breakpoints: [None; MAX_SLOTS],
+    watchpoints: [None; MAX_SLOTS],
+});
+
+/// The step over a triggering instruction that is in progress on each core.
+static PENDING_STEP: PerCpu<Option<PendingStep>> = PerCpu::new(None);
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+/// Write to a banked debug register.
+///
+/// The register number is part of the instruction encoding, hence the dispatch.
+macro_rules! write_banked {
+    (@arms $reg:literal, $n:expr, $value:expr, [$($i:literal),*]) => {
+        match $n {
+            $(
+                $i => asm!(
+                    concat!("msr ", $reg, $i, "_el1, {v}"),
+                    v = in(reg) $value,
+                    options(nomem, nostack)
+                ),
+            )*
+            _ => unreachable!(),
+        }
+    };
+    ($reg:literal, $n:expr, $value:expr) => {
+        write_banked!(@arms $reg, $n, $value, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15])
+    };
+}
+
+fn mdscr_el1() -> u64 {
+    let value: u64;
+
+    unsafe { asm!("mrs {v}, mdscr_el1", v = out(reg) value, options(nomem, nostack)) };
+
+    value
+}
+
+unsafe fn set_mdscr_el1(value: u64) {
+    asm!("msr mdscr_el1, {v}", v = in(reg) value, options(nomem, nostack));
+    barrier::isb(barrier::SY);
+}
+
+fn id_aa64dfr0_el1() -> u64 {
+    let value: u64;
+
+    unsafe { asm!("mrs {v}, id_aa64dfr0_el1", v = out(reg) value, options(nomem, nostack)) };
+
+    value
+}
+
+/// Enable debug exceptions from EL1 on the executing core.
+///
+/// Unlocks the OS lock, enables kernel debug and clears `PSTATE.D`.
+unsafe fn enable_self_hosted_debug() {
+    asm!("msr oslar_el1, xzr", options(nomem, nostack));
+    set_mdscr_el1(mdscr_el1() | mdscr_bits::KDE | mdscr_bits::MDE);
+    asm!("msr DAIFClr, #8", options(nomem, nostack, preserves_flags));
+}
+
+unsafe fn program_breakpoint(n: usize, addr: usize, ctrl: u64) {
+    write_banked!("dbgbvr", n, addr as u64);
+    write_banked!("dbgbcr", n, ctrl);
+    barrier::isb(barrier::SY);
+}
+
+unsafe fn program_watchpoint(n: usize, addr: usize, ctrl: u64) {
+    write_banked!("dbgwvr", n, addr as u64);
+    write_banked!("dbgwcr", n, ctrl);
+    barrier::isb(barrier::SY);
+}
+
+/// Compute the watchpoint value and control register contents for the given region.
+///
+/// Regions of up to 8 bytes must not cross a doubleword boundary. Larger regions must be a
+/// naturally aligned power of two.
+fn watchpoint_config(
+    addr: usize,
+    len: usize,
+    kind: WatchpointKind,
+) -> Result<(usize, u64), &'static str> {
+    let ctrl = InMemoryRegister::<u64, DBGWCR::Register>::new(0);
+
+    let lsc = match kind {
+        WatchpointKind::Read => DBGWCR::LSC::Load,
+        WatchpointKind::Write => DBGWCR::LSC::Store,
+        WatchpointKind::ReadWrite => DBGWCR::LSC::LoadStore,
+    };
+    ctrl.write(lsc + DBGWCR::PAC::EL1 + DBGWCR::E::SET);
+
+    if len == 0 {
+        return Err("Watchpoint length must not be zero");
+    }
+
+    let offset = addr & 0b111;
+    if offset + len <= 8 {
+        ctrl.modify(DBGWCR::BAS.val(((1 << len) - 1) << offset as u64));
+
+        return Ok((addr & !0b111, ctrl.get()));
+    }
+
+    if !len.is_power_of_two() || (addr & (len - 1)) != 0 {
+        return Err("Watchpoint region must be naturally aligned and a power of two");
+    }
+
+    ctrl.modify(DBGWCR::BAS.val(0xff) + DBGWCR::MASK.val(len.trailing_zeros() as u64));
+
+    Ok((addr, ctrl.get()))
+}
+
+/// The size of the blocks that `DC ZVA` zeroes, in bytes.
+fn dc_zva_block_size() -> usize {
+    let value: u64;
+
+    unsafe { asm!("mrs {v}, dczid_el0", v = out(reg) value, options(nomem, nostack)) };
+
+    4 << (value & 0xf)
+}
+
+impl Slot {
+    /// Check if the slot caused a hit at the reported address.
+    ///
+    /// A watchpoint also triggers on an access that only partially overlaps the watched bytes. The
+    /// reported address then lies between the lowest address of the access and the highest
+    /// watched address it touched, within a naturally aligned block that is no larger than the
+    /// `DC ZVA` block size. So watchpoints match from the start of that block on.
+    fn is_hit(&self, kind: DebugEventKind, addr: usize) -> bool {
+        let start = match kind {
+            DebugEventKind::Breakpoint => self.addr,
+            DebugEventKind::Watchpoint => self.addr & !(dc_zva_block_size() - 1),
+        };
+
+        (addr >= start) && (addr < (self.addr + self.len))
+    }
+}
+
+/// Program all slots on the executing core, disabling the ones that are not in use.
+///
+/// Has the signature of a cross-core call function. The argument is unused.
+fn load_slots(_: usize) {
+    DEBUG_STATE.lock(|state| unsafe {
+        for (n, slot) in state.breakpoints[..num_breakpoints()].iter().enumerate() {
+            match slot {
+                Some(slot) => program_breakpoint(n, slot.value, slot.ctrl),
+                None => program_breakpoint(n, 0, 0),
+            }
+        }
+
+        for (n, slot) in state.watchpoints[..num_watchpoints()].iter().enumerate() {
+            match slot {
+                Some(slot) => program_watchpoint(n, slot.value, slot.ctrl),
+                None => program_watchpoint(n, 0, 0),
+            }
+        }
+    })
+}
+
+/// Program all slots on every online core.
+///
+/// Must not be called with `DEBUG_STATE` locked, because the other cores take the lock as well.
+fn load_slots_on_all_cores() -> Result<(), &'static str> {
+    load_slots(0);
+    ipi::call_on_others(load_slots, 0)
+}
+
+/// Find a free slot, limited to the number of slots the hardware implements.
+fn free_slot(slots: &[Option<Slot>], num_implemented: usize) -> Result<usize, &'static str> {
+    slots[..num_implemented]
+        .iter()
+        .position(|x| x.is_none())
+        .ok_or("All hardware debug slots are in use")
+}
+
+/// Called when a breakpoint or watchpoint triggered.
+///
+/// Disables the triggered slot and prepares stepping over the triggering instruction.
+fn handle_hit(
+    spsr: &InMemoryRegister<u64, SPSR_EL1::Register>,
+    kind: DebugEventKind,
+    addr: usize,
+    instruction_addr: Address<Virtual>,
+) -> Option<(DebugEvent, Option<DebugCallback>)> {
+    DEBUG_STATE.lock(|state| {
+        let slots = match kind {
+            DebugEventKind::Breakpoint => &state.breakpoints,
+            DebugEventKind::Watchpoint => &state.watchpoints,
+        };
+        let n = slots
+            .iter()
+            .position(|x| x.map_or(false, |slot| slot.is_hit(kind, addr)))?;
+        let slot = slots[n].unwrap();
+
+        unsafe {
+            match kind {
+                DebugEventKind::Breakpoint => program_breakpoint(n, slot.value, 0),
+                DebugEventKind::Watchpoint => program_watchpoint(n, slot.value, 0),
+            }
+
+            // Step over the instruction with interrupts masked, so that the step exception is
+            // taken right after it.
+            set_mdscr_el1(mdscr_el1() | mdscr_bits::SS);
+        }
+
+        let saved = spsr.get();
+        PENDING_STEP.with_mut(|pending_step| {
+            *pending_step = Some(PendingStep {
+                kind,
+                slot: n,
+                saved_spsr_bits: saved & (spsr_bits::I | spsr_bits::F),
+            })
+        });
+        spsr.set(saved | spsr_bits::SS | spsr_bits::I | spsr_bits::F);
+
+        let event = DebugEvent {
+            kind,
+            slot: n,
+            addr: Address::new(addr),
+            instruction_addr,
+        };
+
+        Some((event, slot.callback))
+    })
+}
+
+/// Called when the triggering instruction was stepped over. Enables the triggered slot again.
+fn handle_step_done(spsr: &InMemoryRegister<u64, SPSR_EL1::Register>) {
+    let pending = match PENDING_STEP.with_mut(|pending_step| pending_step.take()) {
+        None => return,
+        Some(x) => x,
+    };
+
+    DEBUG_STATE.lock(|state| {
+        unsafe {
+            set_mdscr_el1(mdscr_el1() & !mdscr_bits::SS);
+
+            // The slot might have been cleared by the callback in the meantime.
+            match pending.kind {
+                DebugEventKind::Breakpoint => {
+                    if let Some(slot) = state.breakpoints[pending.slot] {
+                        program_breakpoint(pending.slot, slot.value, slot.ctrl);
+                    }
+                }
+                DebugEventKind::Watchpoint => {
+                    if let Some(slot) = state.watchpoints[pending.slot] {
+                        program_watchpoint(pending.slot, slot.value, slot.ctrl);
+                    }
+                }
+            }
+        }
+
+        let value = spsr.get() & !(spsr_bits::SS | spsr_bits::I | spsr_bits::F);
+        spsr.set(value | pending.saved_spsr_bits);
+    })
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// The number of hardware breakpoints the executing core implements.
+pub fn num_breakpoints() -> usize {
+    (((id_aa64dfr0_el1() >> 12) & 0xf) + 1) as usize
+}
+
+/// The number of hardware watchpoints the executing core implements.
+pub fn num_watchpoints() -> usize {
+    (((id_aa64dfr0_el1() >> 20) & 0xf) + 1) as usize
+}
+
+/// Set a breakpoint on the instruction at `addr` on all online cores.
+///
+/// Returns the hardware slot that was used.
+pub fn set_breakpoint(
+    addr: Address<Virtual>,
+    callback: Option<DebugCallback>,
+) -> Result<usize, &'static str> {
+    if (addr.into_usize() & 0b11) != 0 {
+        return Err("Breakpoint address must be 4 byte aligned");
+    }
+
+    let ctrl = InMemoryRegister::<u64, DBGBCR::Register>::new(0);
+    ctrl.write(
+        DBGBCR::BT::UnlinkedAddressMatch + DBGBCR::BAS::A64 + DBGBCR::PMC::EL1 + DBGBCR::E::SET,
+    );
+
+    let n = DEBUG_STATE.lock(|state| {
+        let n = free_slot(&state.breakpoints, num_breakpoints())?;
+        state.breakpoints[n] = Some(Slot {
+            addr: addr.into_usize(),
+            len: 4,
+            value: addr.into_usize(),
+            ctrl: ctrl.get(),
+            callback,
+        });
+
+        Ok(n)
+    })?;
+
+    unsafe { enable_self_hosted_debug() };
+    load_slots_on_all_cores()?;
+
+    Ok(n)
+}
+
+/// Set a watchpoint on the `len` bytes starting at `addr` on all online cores.
+///
+/// Returns the hardware slot that was used.
+pub fn set_watchpoint(
+    addr: Address<Virtual>,
+    len: usize,
+    kind: WatchpointKind,
+    callback: Option<DebugCallback>,
+) -> Result<usize, &'static str> {
+    let (value, ctrl) = watchpoint_config(addr.into_usize(), len, kind)?;
+
+    let n = DEBUG_STATE.lock(|state| {
+        let n = free_slot(&state.watchpoints, num_watchpoints())?;
+        state.watchpoints[n] = Some(Slot {
+            addr: addr.into_usize(),
+            len,
+            value,
+            ctrl,
+            callback,
+        });
+
+        Ok(n)
+    })?;
+
+    unsafe { enable_self_hosted_debug() };
+    load_slots_on_all_cores()?;
+
+    Ok(n)
+}
+
+/// Clear the breakpoint in the given slot.
+pub fn clear_breakpoint(slot: usize) -> Result<(), &'static str> {
+    DEBUG_STATE.lock(|state| {
+        match state.breakpoints.get_mut(slot) {
+            Some(x @ Some(_)) => *x = None,
+            _ => return Err("Breakpoint slot not in use"),
+        }
+
+        Ok(())
+    })?;
+
+    load_slots_on_all_cores()
+}
+
+/// Clear the watchpoint in the given slot.
+pub fn clear_watchpoint(slot: usize) -> Result<(), &'static str> {
+    DEBUG_STATE.lock(|state| {
+        match state.watchpoints.get_mut(slot) {
+            Some(x @ Some(_)) => *x = None,
+            _ => return Err("Watchpoint slot not in use"),
+        }
+
+        Ok(())
+    })?;
+
+    load_slots_on_all_cores()
+}
+
+/// Enable self-hosted debug on the executing secondary core and program the slots that were set so
+/// far.
+///
+/// # Safety
+///
+/// - Must only be called from the secondary core's init, before its IRQs are unmasked.
+pub unsafe fn secondary_core_init() {
+    enable_self_hosted_debug();
+    load_slots(0);
+}
+
+/// Handle a debug exception taken from the current EL.
+///
+/// Called by the exception handler. Returns `None` if the exception completed the step over a
+/// triggering instruction, or if it is a hit that matches no breakpoint or watchpoint. Otherwise,
+/// returns the event and the callback that must be invoked for it.
+pub fn handle_debug_exception(
+    spsr: &InMemoryRegister<u64, SPSR_EL1::Register>,
+    instruction_addr: Address<Virtual>,
+) -> Option<(DebugEvent, Option<DebugCallback>)> {
+    match ESR_EL1.read_as_enum(ESR_EL1::EC) {
+        Some(ESR_EL1::EC::Value::BreakpointCurrentEL) => handle_hit(
+            spsr,
+            DebugEventKind::Breakpoint,
+            instruction_addr.into_usize(),
+            instruction_addr,
+        ),
+        Some(ESR_EL1::EC::Value::WatchpointCurrentEL) => handle_hit(
+            spsr,
+            DebugEventKind::Watchpoint,
+            FAR_EL1.get() as usize,
+            instruction_addr,
+        ),
+        Some(ESR_EL1::EC::Value::SoftwareStepCurrentEL) => {
+            handle_step_done(spsr);
+            None
+        }
+        _ => None,
+    }
+}
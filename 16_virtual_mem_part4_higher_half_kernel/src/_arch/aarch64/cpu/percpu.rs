@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Architectural per-core data.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::cpu::percpu::arch_percpu
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Store the address of the executing core's per-core block.
+///
+/// The address is kept in `TPIDR_EL1`, which is reserved for the OS and not accessible from EL0.
+///
+/// # Safety
+///
+/// - The address must point to the executing core's block.
+#[inline(always)]
+pub unsafe fn set_block_addr(addr: usize) {
+    asm!("msr tpidr_el1, {}", in(reg) addr as u64, options(nomem, nostack));
+}
+
+/// Load the address of the executing core's per-core block.
+#[inline(always)]
+pub fn block_addr() -> usize {
+    let addr: u64;
+
+    unsafe { asm!("mrs {}, tpidr_el1", out(reg) addr, options(nomem, nostack)) };
+
+    addr as usize
+}
@@ -11,7 +11,8 @@
 //!
 //! crate::cpu::smp::arch_smp
 
-use cortex_a::regs::*;
+use crate::memory::{Address, Virtual};
+use cortex_a::{barrier, regs::*};
 
 //--------------------------------------------------------------------------------------------------
 // Public Code
@@ -27,3 +28,24 @@ where
 
     T::from((MPIDR_EL1.get() & CORE_MASK) as u8)
 }
+
+/// The virtual address of the secondary cores' assembly entry.
+pub fn secondary_core_entry_addr() -> Address<Virtual> {
+    // Provided by boot.s.
+    extern "C" {
+        fn _start_secondary();
+    }
+
+    Address::new(_start_secondary as usize)
+}
+
+/// Wake up all cores that wait for an event.
+///
+/// Memory writes issued before are made visible first.
+#[inline(always)]
+pub fn send_event() {
+    unsafe {
+        barrier::dsb(barrier::SY);
+        asm!("sev", options(nomem, nostack));
+    }
+}
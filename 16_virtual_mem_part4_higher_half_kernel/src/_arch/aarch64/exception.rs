@@ -13,12 +13,19 @@
 
 use crate::{
     bsp::{self},
-    exception,
+    cpu, exception,
+    exception::synchronous::{ExceptionClass, FaultInfo, FaultResolution},
+    info,
     memory::Address,
+    thread, warn,
+};
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
 };
-use core::{cell::UnsafeCell, fmt};
 use cortex_a::{barrier, regs::*};
-use register::InMemoryRegister;
+use register::{register_bitfields, InMemoryRegister};
 
 // Assembly counterpart to this file.
 global_asm!(include_str!("exception.s"));
@@ -50,6 +57,45 @@ struct ExceptionContext {
 /// Wrapper struct for pretty printing ESR_EL1.
 struct EsrEL1;
 
+register_bitfields! {
+    u64,
+
+    /// Instruction Specific Syndrome of an SError interrupt.
+    SERROR_ISS [
+        /// Implementation defined syndrome. If set, the rest of the ISS is implementation defined.
+        IDS OFFSET(24) NUMBITS(1) [],
+
+        /// Asynchronous Error Type. Only valid if DFSC is `AsynchronousSError`.
+        AET OFFSET(10) NUMBITS(3) [
+            Uncontainable = 0b000,
+            UnrecoverableState = 0b001,
+            Restartable = 0b010,
+            Recoverable = 0b011,
+            Corrected = 0b110
+        ],
+
+        /// External abort type.
+        EA OFFSET(9) NUMBITS(1) [],
+
+        /// Data Fault Status Code.
+        DFSC OFFSET(0) NUMBITS(6) [
+            Uncategorized = 0b00_0000,
+            AsynchronousSError = 0b01_0001
+        ]
+    ]
+}
+
+/// Wrapper struct for memory copy of the SError syndrome in ESR_EL1.
+#[repr(transparent)]
+struct SErrorSyndrome(InMemoryRegister<u64, SERROR_ISS::Register>);
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// The number of SErrors the kernel recovered from.
+static NUM_RECOVERED_SERRORS: AtomicUsize = AtomicUsize::new(0);
+
 //--------------------------------------------------------------------------------------------------
 // Private Code
 //--------------------------------------------------------------------------------------------------
@@ -68,6 +114,44 @@ fn inspect_data_abort(f: &mut fmt::Formatter) -> fmt::Result {
     Ok(())
 }
 
+/// Derive the fault information for synchronous exceptions that can be offered to fault handlers.
+fn fault_info(e: &ExceptionContext) -> Option<FaultInfo> {
+    let class = match ESR_EL1.read_as_enum(ESR_EL1::EC) {
+        Some(ESR_EL1::EC::Value::DataAbortCurrentEL) => ExceptionClass::DataAbort,
+        Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => ExceptionClass::InstructionAbort,
+        _ => return None,
+    };
+
+    Some(FaultInfo {
+        class,
+        fault_addr: Address::new(FAR_EL1.get() as usize),
+        instruction_addr: Address::new(e.elr_el1 as usize),
+    })
+}
+
+/// Check if the exception is a debug exception and handle it if so.
+///
+/// Triggered breakpoints and watchpoints invoke their callback, or print the exception context if
+/// no callback was provided. A hit that matches no breakpoint or watchpoint is not handled, because
+/// returning would trigger it again right away.
+fn debug_exception_handler(e: &mut ExceptionContext) -> bool {
+    let is_step = match ESR_EL1.read_as_enum(ESR_EL1::EC) {
+        Some(ESR_EL1::EC::Value::BreakpointCurrentEL)
+        | Some(ESR_EL1::EC::Value::WatchpointCurrentEL) => false,
+        Some(ESR_EL1::EC::Value::SoftwareStepCurrentEL) => true,
+        _ => return false,
+    };
+
+    let instruction_addr = Address::new(e.elr_el1 as usize);
+    match cpu::debug::handle_debug_exception(&e.spsr_el1.0, instruction_addr) {
+        None => return is_step,
+        Some((event, Some(callback))) => callback(&event),
+        Some((event, None)) => info!("{}\n{}", event, e),
+    }
+
+    true
+}
+
 /// Prints verbose information about the exception and then panics.
 fn default_exception_handler(e: &ExceptionContext) {
     panic!(
@@ -81,6 +165,55 @@ fn default_exception_handler(e: &ExceptionContext) {
     );
 }
 
+impl SErrorSyndrome {
+    /// Take a snapshot of the syndrome of the SError that is currently being handled.
+    fn from_esr_el1() -> Self {
+        Self(InMemoryRegister::new(ESR_EL1.read(ESR_EL1::ISS)))
+    }
+
+    /// Check if the error was contained, so that execution can safely continue.
+    ///
+    /// Errors that are not described by the architected syndrome are treated as uncontainable.
+    fn is_recoverable(&self) -> bool {
+        if self.0.is_set(SERROR_ISS::IDS) {
+            return false;
+        }
+
+        if self.0.read_as_enum(SERROR_ISS::DFSC)
+            != Some(SERROR_ISS::DFSC::Value::AsynchronousSError)
+        {
+            return false;
+        }
+
+        matches!(
+            self.0.read_as_enum(SERROR_ISS::AET),
+            Some(SERROR_ISS::AET::Value::Restartable)
+                | Some(SERROR_ISS::AET::Value::Recoverable)
+                | Some(SERROR_ISS::AET::Value::Corrected)
+        )
+    }
+}
+
+/// Decodes the SError syndrome. Recoverable errors are counted and reported, all others panic.
+fn serror_handler(e: &ExceptionContext) {
+    let syndrome = SErrorSyndrome::from_esr_el1();
+
+    if !syndrome.is_recoverable() {
+        panic!(
+            "\n\nUncontainable SError!\n\
+             {}\n\
+             {}",
+            syndrome, e
+        );
+    }
+
+    let count = NUM_RECOVERED_SERRORS.fetch_add(1, Ordering::Relaxed) + 1;
+    warn!(
+        "Recovered from SError #{} at ELR_EL1 {:#018x}. {}",
+        count, e.elr_el1, syndrome
+    );
+}
+
 //------------------------------------------------------------------------------
 // Current, EL0
 //------------------------------------------------------------------------------
@@ -95,6 +228,11 @@ unsafe extern "C" fn current_el0_irq(_e: &mut ExceptionContext) {
     panic!("Should not be here. Use of SP_EL0 in EL1 is not supported.")
 }
 
+#[no_mangle]
+unsafe extern "C" fn current_el0_fiq(_e: &mut ExceptionContext) {
+    panic!("Should not be here. Use of SP_EL0 in EL1 is not supported.")
+}
+
 #[no_mangle]
 unsafe extern "C" fn current_el0_serror(_e: &mut ExceptionContext) {
     panic!("Should not be here. Use of SP_EL0 in EL1 is not supported.")
@@ -106,6 +244,29 @@ unsafe extern "C" fn current_el0_serror(_e: &mut ExceptionContext) {
 
 #[no_mangle]
 unsafe extern "C" fn current_elx_synchronous(e: &mut ExceptionContext) {
+    if debug_exception_handler(e) {
+        return;
+    }
+
+    if let Some(info) = fault_info(e) {
+        match exception::synchronous::handle_fault(&info) {
+            // Return to the faulting instruction, which is still stored in ELR_EL1.
+            FaultResolution::Resume => return,
+            // All AArch64 instructions are 4 bytes wide.
+            FaultResolution::SkipInstruction => {
+                e.elr_el1 += 4;
+                return;
+            }
+            FaultResolution::Unhandled => (),
+        }
+    }
+
+    // Instructions that are allowed to fault continue at their recovery address.
+    if let Some(fixup_addr) = exception::fixup::search(Address::new(e.elr_el1 as usize)) {
+        e.elr_el1 = fixup_addr.into_usize() as u64;
+        return;
+    }
+
     default_exception_handler(e);
 }
 
@@ -115,11 +276,23 @@ unsafe extern "C" fn current_elx_irq(_e: &mut ExceptionContext) {
 
     let token = &exception::asynchronous::IRQContext::new();
     bsp::exception::asynchronous::irq_manager().handle_pending_irqs(token);
+
+    // Might switch to another thread. The interrupted one continues from here once it is switched
+    // back in.
+    thread::preempt_on_irq_exit(token);
+}
+
+#[no_mangle]
+unsafe extern "C" fn current_elx_fiq(_e: &mut ExceptionContext) {
+    use exception::asynchronous::interface::IRQManager;
+
+    let token = &exception::asynchronous::IRQContext::new();
+    bsp::exception::asynchronous::irq_manager().handle_pending_fiq(token);
 }
 
 #[no_mangle]
 unsafe extern "C" fn current_elx_serror(e: &mut ExceptionContext) {
-    default_exception_handler(e);
+    serror_handler(e);
 }
 
 //------------------------------------------------------------------------------
@@ -137,10 +310,15 @@ unsafe extern "C" fn lower_aarch64_irq(e: &mut ExceptionContext) {
 }
 
 #[no_mangle]
-unsafe extern "C" fn lower_aarch64_serror(e: &mut ExceptionContext) {
+unsafe extern "C" fn lower_aarch64_fiq(e: &mut ExceptionContext) {
     default_exception_handler(e);
 }
 
+#[no_mangle]
+unsafe extern "C" fn lower_aarch64_serror(e: &mut ExceptionContext) {
+    serror_handler(e);
+}
+
 //------------------------------------------------------------------------------
 // Lower, AArch32
 //------------------------------------------------------------------------------
@@ -155,6 +333,11 @@ unsafe extern "C" fn lower_aarch32_irq(e: &mut ExceptionContext) {
     default_exception_handler(e);
 }
 
+#[no_mangle]
+unsafe extern "C" fn lower_aarch32_fiq(e: &mut ExceptionContext) {
+    default_exception_handler(e);
+}
+
 #[no_mangle]
 unsafe extern "C" fn lower_aarch32_serror(e: &mut ExceptionContext) {
     default_exception_handler(e);
@@ -179,6 +362,11 @@ impl fmt::Display for EsrEL1 {
         // Exception class, translation.
         let ec_translation = match esr_el1.read_as_enum(ESR_EL1::EC) {
             Some(ESR_EL1::EC::Value::DataAbortCurrentEL) => "Data Abort, current EL",
+            Some(ESR_EL1::EC::Value::InstrAbortCurrentEL) => "Instruction Abort, current EL",
+            Some(ESR_EL1::EC::Value::SError) => "SError interrupt",
+            Some(ESR_EL1::EC::Value::BreakpointCurrentEL) => "Breakpoint, current EL",
+            Some(ESR_EL1::EC::Value::SoftwareStepCurrentEL) => "Software Step, current EL",
+            Some(ESR_EL1::EC::Value::WatchpointCurrentEL) => "Watchpoint, current EL",
             _ => "N/A",
         };
         writeln!(f, " - {}", ec_translation)?;
@@ -190,6 +378,38 @@ impl fmt::Display for EsrEL1 {
     }
 }
 
+/// Human readable SError syndrome.
+#[rustfmt::skip]
+impl fmt::Display for SErrorSyndrome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Raw value.
+        writeln!(f, "SError ISS: {:#010x}", self.0.get())?;
+
+        if self.0.is_set(SERROR_ISS::IDS) {
+            return write!(f, "      Implementation defined syndrome");
+        }
+
+        let dfsc_translation = match self.0.read_as_enum(SERROR_ISS::DFSC) {
+            Some(SERROR_ISS::DFSC::Value::Uncategorized) => "Uncategorized",
+            Some(SERROR_ISS::DFSC::Value::AsynchronousSError) => "Asynchronous SError",
+            _ => "N/A",
+        };
+        writeln!(f, "      Data Fault Status Code  (DFSC): {:#x} - {}",
+            self.0.read(SERROR_ISS::DFSC), dfsc_translation)?;
+
+        let aet_translation = match self.0.read_as_enum(SERROR_ISS::AET) {
+            Some(SERROR_ISS::AET::Value::Uncontainable) => "Uncontainable",
+            Some(SERROR_ISS::AET::Value::UnrecoverableState) => "Unrecoverable state",
+            Some(SERROR_ISS::AET::Value::Restartable) => "Restartable",
+            Some(SERROR_ISS::AET::Value::Recoverable) => "Recoverable",
+            Some(SERROR_ISS::AET::Value::Corrected) => "Corrected",
+            _ => "N/A",
+        };
+        write!(f, "      Asynchronous Error Type  (AET): {:#x} - {}",
+            self.0.read(SERROR_ISS::AET), aet_translation)
+    }
+}
+
 /// Human readable SPSR_EL1.
 #[rustfmt::skip]
 impl fmt::Display for SpsrEL1 {
@@ -260,8 +480,16 @@ pub fn current_privilege_level() -> (PrivilegeLevel, &'static str) {
     }
 }
 
+/// Return the number of SErrors the kernel recovered from.
+pub fn num_recovered_serrors() -> usize {
+    NUM_RECOVERED_SERRORS.load(Ordering::Relaxed)
+}
+
 /// Init exception handling by setting the exception vector base address register.
 ///
+/// SErrors are unmasked afterwards, since they can only be handled once the vector table is in
+/// place.
+///
 /// # Safety
 ///
 /// - Changes the HW state of the executing core.
@@ -278,4 +506,6 @@ pub unsafe fn handling_init() {
 
     // Force VBAR update to complete before next instruction.
     barrier::isb(barrier::SY);
+
+    exception::asynchronous::local_serror_unmask();
 }
@@ -18,7 +18,9 @@ use cortex_a::regs::*;
 //--------------------------------------------------------------------------------------------------
 
 mod daif_bits {
+    pub const SERROR: u8 = 0b0100;
     pub const IRQ: u8 = 0b0010;
+    pub const FIQ: u8 = 0b0001;
 }
 
 trait DaifField {
@@ -110,6 +112,51 @@ pub unsafe fn local_irq_mask() {
     );
 }
 
+/// Unmask FIQs on the executing core.
+///
+/// # Safety
+///
+/// - Changes the HW state of the executing core.
+#[inline(always)]
+pub unsafe fn local_fiq_unmask() {
+    #[rustfmt::skip]
+    asm!(
+        "msr DAIFClr, {arg}",
+        arg = const daif_bits::FIQ,
+        options(nomem, nostack, preserves_flags)
+    );
+}
+
+/// Mask FIQs on the executing core.
+///
+/// # Safety
+///
+/// - Changes the HW state of the executing core.
+#[inline(always)]
+pub unsafe fn local_fiq_mask() {
+    #[rustfmt::skip]
+    asm!(
+        "msr DAIFSet, {arg}",
+        arg = const daif_bits::FIQ,
+        options(nomem, nostack, preserves_flags)
+    );
+}
+
+/// Unmask SErrors on the executing core.
+///
+/// # Safety
+///
+/// - Changes the HW state of the executing core.
+#[inline(always)]
+pub unsafe fn local_serror_unmask() {
+    #[rustfmt::skip]
+    asm!(
+        "msr DAIFClr, {arg}",
+        arg = const daif_bits::SERROR,
+        options(nomem, nostack, preserves_flags)
+    );
+}
+
 /// Mask IRQs on the executing core and return the previously saved interrupt mask bits (DAIF).
 ///
 /// # Safety
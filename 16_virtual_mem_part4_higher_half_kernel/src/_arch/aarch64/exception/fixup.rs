@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Architectural fault-tolerant memory accessors.
+//!
+//! Each accessor places its load or store instruction, together with a recovery label, into the
+//! `.exception_fixup` linker section. The recovery code flags the fault and skips the rest of the
+//! accessor.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::exception::fixup::arch_fixup
+
+use crate::{
+    exception::fixup::Fault,
+    memory::{Address, Virtual},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Read a `u32` from the given address, returning an error instead of panicking if the access
+/// faults.
+#[inline(always)]
+pub fn probe_read_u32(addr: Address<Virtual>) -> Result<u32, Fault> {
+    let value: u32;
+    let faulted: u64;
+
+    unsafe {
+        asm!(
+            "2: ldr {value:w}, [{addr}]",
+            "   mov {faulted}, #0",
+            "   b   4f",
+            "3: mov {value:w}, wzr",
+            "   mov {faulted}, #1",
+            "4:",
+            ".pushsection .exception_fixup, \"a\"",
+            ".balign 8",
+            ".quad 2b, 3b",
+            ".popsection",
+            addr = in(reg) addr.into_usize(),
+            value = out(reg) value,
+            faulted = out(reg) faulted,
+            options(nostack, readonly)
+        );
+    }
+
+    if faulted != 0 {
+        return Err(Fault::new(addr));
+    }
+
+    Ok(value)
+}
+
+/// Write a `u32` to the given address, returning an error instead of panicking if the access
+/// faults.
+///
+/// # Safety
+///
+/// - The write must not break assumptions other code makes about the memory at `addr`.
+#[inline(always)]
+pub unsafe fn probe_write_u32(addr: Address<Virtual>, value: u32) -> Result<(), Fault> {
+    let faulted: u64;
+
+    asm!(
+        "2: str {value:w}, [{addr}]",
+        "   mov {faulted}, #0",
+        "   b   4f",
+        "3: mov {faulted}, #1",
+        "4:",
+        ".pushsection .exception_fixup, \"a\"",
+        ".balign 8",
+        ".quad 2b, 3b",
+        ".popsection",
+        addr = in(reg) addr.into_usize(),
+        value = in(reg) value,
+        faulted = out(reg) faulted,
+        options(nostack)
+    );
+
+    if faulted != 0 {
+        return Err(Fault::new(addr));
+    }
+
+    Ok(())
+}
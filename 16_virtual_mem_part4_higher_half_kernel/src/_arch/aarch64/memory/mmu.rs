@@ -181,4 +181,17 @@ impl memory::mmu::interface::MMU for MemoryManagementUnit {
 
         Ok(Address::new(phys_addr as usize))
     }
+
+    fn invalidate_local_tlb(&self) {
+        unsafe {
+            // Make previous translation table updates visible to the table walk first.
+            barrier::dsb(barrier::SY);
+
+            asm!("TLBI VMALLE1", options(nostack, preserves_flags));
+
+            // Wait for the invalidation to complete and discard prefetched instructions.
+            barrier::dsb(barrier::SY);
+            barrier::isb(barrier::SY);
+        }
+    }
 }
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Architectural kernel thread context switching.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::thread::arch_thread
+
+// Assembly counterpart to this file.
+global_asm!(include_str!("thread.s"));
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The registers of a thread that is switched out.
+///
+/// Only the callee-saved registers need to be kept, because switching happens through a function
+/// call. The layout is shared with `thread.s`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Context {
+    /// x19 to x28.
+    gpr: [u64; 10],
+
+    /// The frame pointer, aka x29.
+    fp: u64,
+
+    /// The link register, aka x30. The address at which the thread continues.
+    lr: u64,
+
+    /// The stack pointer.
+    sp: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Context {
+    /// Create an empty instance, to be filled by the first switch away from the executing thread.
+    pub const fn new() -> Self {
+        Self {
+            gpr: [0; 10],
+            fp: 0,
+            lr: 0,
+            sp: 0,
+        }
+    }
+
+    /// Create an instance that starts executing `entry` on the stack ending at
+    /// `stack_end_exclusive`.
+    ///
+    /// The frame pointer is zero, which terminates frame record chains.
+    pub fn new_thread(stack_end_exclusive: usize, entry: extern "C" fn() -> !) -> Self {
+        Self {
+            gpr: [0; 10],
+            fp: 0,
+            lr: entry as usize as u64,
+            sp: stack_end_exclusive as u64,
+        }
+    }
+}
+
+/// Save the executing thread's registers to `prev` and continue the thread saved in `next`.
+///
+/// Returns once another thread switches back to `prev`.
+///
+/// # Safety
+///
+/// - IRQs must be masked.
+/// - `next` must have been saved by an earlier switch or created by `Context::new_thread()`.
+/// - The stack referenced by `next` must stay valid while the thread runs.
+#[inline(always)]
+pub unsafe fn switch(prev: *mut Context, next: *const Context) {
+    // Provided by thread.s.
+    extern "C" {
+        fn __switch_context(prev: *mut Context, next: *const Context);
+    }
+
+    __switch_context(prev, next)
+}
@@ -0,0 +1,43 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+// A section of its own, because the exception vector table relies on `.org` offsets relative to
+// the start of `.text`.
+.section .text.__switch_context
+
+//------------------------------------------------------------------------------
+// fn __switch_context(prev: *mut Context, next: *const Context)
+//------------------------------------------------------------------------------
+__switch_context:
+	// Save the callee-saved registers of the outgoing thread. The caller-saved registers are
+	// already saved by the compiler-generated code that called this function.
+	mov	x9,       sp
+	stp	x19, x20, [x0, #16 * 0]
+	stp	x21, x22, [x0, #16 * 1]
+	stp	x23, x24, [x0, #16 * 2]
+	stp	x25, x26, [x0, #16 * 3]
+	stp	x27, x28, [x0, #16 * 4]
+	stp	x29, lr,  [x0, #16 * 5]
+	str	x9,       [x0, #16 * 6]
+
+	// Restore the callee-saved registers of the incoming thread.
+	ldp	x19, x20, [x1, #16 * 0]
+	ldp	x21, x22, [x1, #16 * 1]
+	ldp	x23, x24, [x1, #16 * 2]
+	ldp	x25, x26, [x1, #16 * 3]
+	ldp	x27, x28, [x1, #16 * 4]
+	ldp	x29, lr,  [x1, #16 * 5]
+	ldr	x9,       [x1, #16 * 6]
+	mov	sp,  x9
+
+	// Continue where the incoming thread switched away, or at its entry if it is new.
+	ret
+
+.size	__switch_context, . - __switch_context
+.type	__switch_context, function
+.global	__switch_context
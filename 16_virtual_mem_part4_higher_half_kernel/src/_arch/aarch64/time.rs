@@ -4,6 +4,14 @@
 
 //! Architectural timer primitives.
 //!
+//! All timekeeping is based on the 64-bit physical counter (`CNTPCT_EL0`). Deadlines are absolute
+//! counter values, like the ones in `CNTP_CVAL_EL0`, so they cannot overflow within the lifetime of
+//! the system.
+//!
+//! The EL1 physical timer raises an IRQ at the nearest deadline of the software timer queue.
+//! Waiting leaves the timer untouched: `spin_for()` busy-checks the counter and `wait_for()` sleeps
+//! in `WFE` and is woken up by the counter's event stream.
+//!
 //! # Orientation
 //!
 //! Since arch modules are imported into generic modules using the path attribute, the path of this
@@ -11,9 +19,9 @@
 //!
 //! crate::time::arch_time
 
-use crate::{time, warn};
+use crate::{bsp, exception, time, time::Instant};
 use core::time::Duration;
-use cortex_a::{barrier, regs::*};
+use cortex_a::{asm, barrier, regs::*};
 
 //--------------------------------------------------------------------------------------------------
 // Private Definitions
@@ -21,6 +29,18 @@ use cortex_a::{barrier, regs::*};
 
 const NS_PER_S: u64 = 1_000_000_000;
 
+/// The event stream triggers on transitions of this counter bit, i.e. every 2^(N+1) ticks. That is
+/// roughly every 107 us at 19.2 MHz.
+const EVENT_STREAM_COUNTER_BIT: u64 = 10;
+
+/// Bits of CNTKCTL_EL1.
+mod cntkctl_bits {
+    pub const EVNTI_SHIFT: u64 = 4;
+    pub const EVNTI_MASK: u64 = 0b1111 << EVNTI_SHIFT;
+    pub const EVNTDIR: u64 = 1 << 3;
+    pub const EVNTEN: u64 = 1 << 2;
+}
+
 /// ARMv8 Generic Timer.
 struct GenericTimer;
 
@@ -41,6 +61,27 @@ impl GenericTimer {
         unsafe { barrier::isb(barrier::SY) };
         CNTPCT_EL0.get()
     }
+
+    #[inline(always)]
+    fn frequency(&self) -> u64 {
+        CNTFRQ_EL0.get() as u64
+    }
+
+    /// Make sure the counter's event stream is running, so that `WFE` wakes up periodically.
+    fn enable_event_stream(&self) {
+        let mut cntkctl: u64;
+
+        unsafe {
+            asm!("mrs {v}, cntkctl_el1", v = out(reg) cntkctl, options(nomem, nostack));
+
+            cntkctl &= !(cntkctl_bits::EVNTI_MASK | cntkctl_bits::EVNTDIR);
+            cntkctl |=
+                (EVENT_STREAM_COUNTER_BIT << cntkctl_bits::EVNTI_SHIFT) | cntkctl_bits::EVNTEN;
+
+            asm!("msr cntkctl_el1, {v}", v = in(reg) cntkctl, options(nomem, nostack));
+            barrier::isb(barrier::SY);
+        }
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -58,61 +99,98 @@ pub fn time_manager() -> &'static impl time::interface::TimeManager {
 
 impl time::interface::TimeManager for GenericTimer {
     fn resolution(&self) -> Duration {
-        Duration::from_nanos(NS_PER_S / (CNTFRQ_EL0.get() as u64))
+        Duration::from_nanos(NS_PER_S / self.frequency())
     }
 
     fn uptime(&self) -> Duration {
-        let current_count: u64 = self.read_cntpct() * NS_PER_S;
-        let frq: u64 = CNTFRQ_EL0.get() as u64;
+        self.now().as_duration_since_boot()
+    }
 
-        Duration::from_nanos(current_count / frq)
+    fn now(&self) -> Instant {
+        Instant::from_counter_ticks(self.read_cntpct())
+    }
+
+    fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        let frq = self.frequency();
+
+        // Split into whole seconds and the remainder, so that the multiplication cannot overflow.
+        let secs = ticks / frq;
+        let nanos = ((ticks % frq) * NS_PER_S) / frq;
+
+        Duration::new(secs, nanos as u32)
+    }
+
+    fn duration_to_ticks(&self, duration: Duration) -> u64 {
+        let frq = u128::from(self.frequency());
+        let ns_per_s = u128::from(NS_PER_S);
+
+        // Round up, so that waiting never ends early.
+        let ticks = ((duration.as_nanos() * frq) + (ns_per_s - 1)) / ns_per_s;
+
+        if ticks > u128::from(u64::MAX) {
+            return u64::MAX;
+        }
+
+        ticks as u64
     }
 
     fn spin_for(&self, duration: Duration) {
-        // Instantly return on zero.
-        if duration.as_nanos() == 0 {
-            return;
+        let deadline = self
+            .read_cntpct()
+            .saturating_add(self.duration_to_ticks(duration));
+
+        while self.read_cntpct() < deadline {}
+    }
+
+    fn wait_for(&self, duration: Duration) {
+        let deadline = self
+            .read_cntpct()
+            .saturating_add(self.duration_to_ticks(duration));
+
+        self.enable_event_stream();
+
+        // Woken up at least by every event of the event stream, and additionally by IRQs or SEV.
+        while self.read_cntpct() < deadline {
+            asm::wfe();
         }
+    }
 
-        // Calculate the register compare value.
-        let frq = CNTFRQ_EL0.get();
-        let x = match frq.checked_mul(duration.as_nanos() as u64) {
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        use bsp::exception::asynchronous::irq_manager;
+        use exception::asynchronous::{interface::IRQManager, IRQDescriptor};
+
+        let descriptor = IRQDescriptor {
+            name: "ARM Generic Timer",
+            handler: self,
+        };
+        let irq_number = bsp::exception::asynchronous::arch_timer_irq();
+
+        irq_manager().register_handler(irq_number, descriptor)?;
+        irq_manager().enable(irq_number);
+
+        Ok(())
+    }
+
+    fn set_alarm(&self, deadline: Option<Instant>) {
+        let cval = match deadline {
+            // Disabling the timer also deasserts its IRQ.
             None => {
-                warn!("Spin duration too long, skipping");
+                CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::CLEAR);
                 return;
             }
-            Some(val) => val,
-        };
-        let tval = x / NS_PER_S;
-
-        // Check if it is within supported bounds.
-        let warn: Option<&str> = if tval == 0 {
-            Some("smaller")
-        // The upper 32 bits of CNTP_TVAL_EL0 are reserved.
-        } else if tval > u32::max_value().into() {
-            Some("bigger")
-        } else {
-            None
+            Some(x) => x.counter_ticks(),
         };
 
-        if let Some(w) = warn {
-            warn!(
-                "Spin duration {} than architecturally supported, skipping",
-                w
-            );
-            return;
-        }
-
-        // Set the compare value register.
-        CNTP_TVAL_EL0.set(tval);
-
-        // Kick off the counting.                       // Disable timer interrupt.
-        CNTP_CTL_EL0.modify(CNTP_CTL_EL0::ENABLE::SET + CNTP_CTL_EL0::IMASK::SET);
+        CNTP_CVAL_EL0.set(cval);
+        CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::SET + CNTP_CTL_EL0::IMASK::CLEAR);
+    }
+}
 
-        // ISTATUS will be '1' when cval ticks have passed. Busy-check it.
-        while !CNTP_CTL_EL0.matches_all(CNTP_CTL_EL0::ISTATUS::SET) {}
+impl exception::asynchronous::interface::IRQHandler for GenericTimer {
+    fn handle(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        // Rearms the timer for the next deadline, which also deasserts the IRQ.
+        time::timer_queue::handle_expired();
 
-        // Disable counting again.
-        CNTP_CTL_EL0.modify(CNTP_CTL_EL0::ENABLE::CLEAR);
+        Ok(exception::asynchronous::IRQReturn::Handled)
     }
 }
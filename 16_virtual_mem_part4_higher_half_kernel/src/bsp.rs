@@ -6,6 +6,9 @@
 
 mod device_driver;
 
+#[cfg(test)]
+pub use device_driver::FakeIRQHandler;
+
 #[cfg(any(feature = "bsp_rpi3", feature = "bsp_rpi4"))]
 mod raspberrypi;
 
@@ -14,3 +14,6 @@ mod common;
 pub use arm::*;
 #[cfg(any(feature = "bsp_rpi3", feature = "bsp_rpi4"))]
 pub use bcm::*;
+
+#[cfg(test)]
+pub use common::FakeIRQHandler;
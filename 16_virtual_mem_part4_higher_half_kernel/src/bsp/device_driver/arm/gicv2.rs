@@ -86,7 +86,10 @@ use core::sync::atomic::{AtomicBool, Ordering};
 // Private Definitions
 //--------------------------------------------------------------------------------------------------
 
-type HandlerTable = [Option<exception::asynchronous::IRQDescriptor>; GICv2::NUM_IRQS];
+type HandlerTable = exception::asynchronous::IRQHandlerTable<{ GICv2::NUM_IRQS }>;
+
+/// The priority that all IRQs start out with.
+const DEFAULT_PRIORITY: u8 = priority_value(exception::asynchronous::IRQPriority::Normal);
 
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
@@ -111,6 +114,53 @@ pub struct GICv2 {
 
     /// Stores registered IRQ handlers. Writable only during kernel init. RO afterwards.
     handler_table: InitStateLock<HandlerTable>,
+
+    /// Per-IRQ statistics.
+    stats: exception::asynchronous::IRQStatsTable<{ GICv2::NUM_IRQS }>,
+
+    /// Stores the registered FIQ handler. Writable only during kernel init. RO afterwards.
+    fiq_handler: InitStateLock<Option<exception::asynchronous::IRQDescriptor>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// The GIC priority value of an IRQ priority. Lower values are higher priorities.
+///
+/// GICv2 implements at least the upper four bits of each priority field. The values are far enough
+/// apart to land in different priority groups for any binary point setting that allows preemption
+/// at all, so the CPU interface's running priority lets higher priorities preempt lower ones.
+const fn priority_value(priority: exception::asynchronous::IRQPriority) -> u8 {
+    use exception::asynchronous::IRQPriority;
+
+    match priority {
+        IRQPriority::Low => 0xC0,
+        IRQPriority::Normal => 0x80,
+        IRQPriority::High => 0x40,
+    }
+}
+
+/// The GICD target mask of an IPI. `None` selects all cores except the executing one.
+fn sgi_target_mask(
+    irq_number: IRQNumber,
+    target: exception::asynchronous::IPITarget,
+) -> Result<Option<u8>, &'static str> {
+    if irq_number.get() > 15 {
+        return Err("IPIs must use a software generated interrupt");
+    }
+
+    // On the BCM2711, the GIC CPU interface number of a core equals its core id.
+    match target {
+        exception::asynchronous::IPITarget::AllOthers => Ok(None),
+        exception::asynchronous::IPITarget::Core(core) => {
+            if core >= bsp::cpu::NUM_CORES {
+                return Err("Invalid core id");
+            }
+
+            Ok(Some(1u8 << core))
+        }
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -121,6 +171,10 @@ impl GICv2 {
     const MAX_IRQ_NUMBER: usize = 300; // Normally 1019, but keep it lower to save some space.
     const NUM_IRQS: usize = Self::MAX_IRQ_NUMBER + 1;
 
+    /// Interrupt numbers 1020..1023 are reserved for special purposes, e.g. 1023 signals a spurious
+    /// interrupt.
+    const FIRST_SPECIAL_IRQ_NUMBER: usize = 1020;
+
     /// Create an instance.
     ///
     /// # Safety
@@ -136,7 +190,9 @@ impl GICv2 {
             gicd: gicd::GICD::new(gicd_mmio_descriptor.start_addr().into_usize()),
             gicc: gicc::GICC::new(gicc_mmio_descriptor.start_addr().into_usize()),
             is_mmio_remapped: AtomicBool::new(false),
-            handler_table: InitStateLock::new([None; Self::NUM_IRQS]),
+            handler_table: InitStateLock::new(HandlerTable::new()),
+            stats: exception::asynchronous::IRQStatsTable::new(),
+            fiq_handler: InitStateLock::new(None),
         }
     }
 }
@@ -187,62 +243,236 @@ impl exception::asynchronous::interface::IRQManager for GICv2 {
         irq_number: Self::IRQNumberType,
         descriptor: exception::asynchronous::IRQDescriptor,
     ) -> Result<(), &'static str> {
-        self.handler_table.write(|table| {
-            let irq_number = irq_number.get();
+        self.handler_table
+            .write(|table| table.register(irq_number.get(), descriptor))
+    }
 
-            if table[irq_number].is_some() {
-                return Err("IRQ handler already registered");
+    fn enable(&self, irq_number: Self::IRQNumberType) {
+        self.gicd.enable(irq_number);
+    }
+
+    fn set_priority(
+        &self,
+        irq_number: Self::IRQNumberType,
+        priority: exception::asynchronous::IRQPriority,
+    ) -> Result<(), &'static str> {
+        self.gicd.set_priority(irq_number, priority_value(priority));
+
+        Ok(())
+    }
+
+    fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        // Acknowledge the highest priority pending IRQ through the Interrupt Acknowledge Register
+        // (IAR).
+        let acknowledged = self.gicc.acknowledge_pending_irq(ic);
+        let irq_number = acknowledged.number();
+
+        // Account spurious interrupts, and interrupts beyond the handler table, which can not have
+        // been enabled by the kernel.
+        if irq_number > GICv2::MAX_IRQ_NUMBER {
+            self.stats.record_spurious();
+
+            // The special interrupt numbers must not be marked as completed.
+            if irq_number < GICv2::FIRST_SPECIAL_IRQ_NUMBER {
+                self.gicc.mark_comleted(acknowledged, ic);
             }
 
-            table[irq_number] = Some(descriptor);
+            return;
+        }
 
-            Ok(())
-        })
+        // Call the IRQ's handlers, or account the IRQ as unhandled if there are none.
+        //
+        // Until completion is signaled, the CPU interface only signals IRQs of a higher priority,
+        // so handlers that allow nesting can run with IRQs unmasked.
+        self.handler_table.read(|table| {
+            // Panics on failure.
+            self.stats
+                .dispatch(
+                    table,
+                    irq_number,
+                    |descriptor| exception::asynchronous::handle_nestable(descriptor, ic),
+                    || self.gicd.disable(IRQNumber::new(irq_number)),
+                )
+                .expect("Error handling IRQ");
+        });
+
+        // Signal completion of handling.
+        self.gicc.mark_comleted(acknowledged, ic);
     }
 
-    fn enable(&self, irq_number: Self::IRQNumberType) {
+    fn register_fiq_handler(
+        &self,
+        irq_number: Self::IRQNumberType,
+        descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        // Group 0 interrupts are signaled as FIQ.
+        if !self.gicd.is_group_configurable() {
+            return Err("FIQ routing requires Secure state");
+        }
+
+        self.fiq_handler.write(|fiq_handler| {
+            if fiq_handler.is_some() {
+                return Err("FIQ handler already registered");
+            }
+
+            *fiq_handler = Some(descriptor);
+
+            Ok(())
+        })?;
+
+        self.gicd.set_group0(irq_number);
+        self.gicc.enable_fiq();
         self.gicd.enable(irq_number);
+
+        Ok(())
     }
 
-    fn handle_pending_irqs<'irq_context>(
+    fn handle_pending_fiq<'irq_context>(
         &'irq_context self,
         ic: &exception::asynchronous::IRQContext<'irq_context>,
     ) {
-        // Extract the highest priority pending IRQ number from the Interrupt Acknowledge Register
-        // (IAR).
-        let irq_number = self.gicc.pending_irq_number(ic);
+        let acknowledged = self.gicc.acknowledge_pending_irq(ic);
+        let irq_number = acknowledged.number();
 
         // Guard against spurious interrupts.
         if irq_number > GICv2::MAX_IRQ_NUMBER {
             return;
         }
 
-        // Call the IRQ handler. Panic if there is none.
-        self.handler_table.read(|table| {
-            match table[irq_number] {
-                None => panic!("No handler registered for IRQ {}", irq_number),
-                Some(descriptor) => {
-                    // Call the IRQ handler. Panics on failure.
-                    descriptor.handler.handle().expect("Error handling IRQ");
-                }
+        self.fiq_handler.read(|fiq_handler| match fiq_handler {
+            None => panic!("No FIQ handler registered"),
+            Some(descriptor) => {
+                // Call the FIQ handler. Panics on failure.
+                descriptor.handler.handle().expect("Error handling FIQ");
             }
         });
 
         // Signal completion of handling.
-        self.gicc.mark_comleted(irq_number as u32, ic);
+        self.gicc.mark_comleted(acknowledged, ic);
     }
 
     fn print_handler(&self) {
         use crate::info;
 
+        info!("      Software generated handler:");
+
+        self.handler_table.read(|table| {
+            for (i, handler) in table.iter().filter(|(i, _)| *i < 16) {
+                info!("            {: >3}. {}", i, handler.name);
+            }
+        });
+
         info!("      Peripheral handler:");
 
         self.handler_table.read(|table| {
-            for (i, opt) in table.iter().skip(32).enumerate() {
-                if let Some(handler) = opt {
-                    info!("            {: >3}. {}", i + 32, handler.name);
-                }
+            for (i, handler) in table.iter().filter(|(i, _)| *i >= 32) {
+                info!("            {: >3}. {}", i, handler.name);
+            }
+        });
+
+        self.fiq_handler.read(|fiq_handler| {
+            if let Some(handler) = fiq_handler {
+                info!("      FIQ handler:");
+                info!("                 {}", handler.name);
             }
         });
     }
+
+    fn print_stats(&self) {
+        self.handler_table.read(|table| self.stats.print(table));
+    }
+
+    unsafe fn secondary_core_init(&self) {
+        // The CPU interface and the SGI enables are banked, so each core must set up its own.
+        self.gicd.secondary_core_init();
+        self.gicc.priority_accept_all();
+        self.gicc.enable();
+    }
+
+    fn send_ipi(
+        &self,
+        irq_number: Self::IRQNumberType,
+        target: exception::asynchronous::IPITarget,
+    ) -> Result<(), &'static str> {
+        let target_mask = sgi_target_mask(irq_number, target)?;
+        self.gicd.send_sgi(irq_number, target_mask);
+
+        Ok(())
+    }
+
+    unsafe fn panic_send_ipi(
+        &self,
+        irq_number: Self::IRQNumberType,
+        target: exception::asynchronous::IPITarget,
+    ) -> Result<(), &'static str> {
+        let target_mask = sgi_target_mask(irq_number, target)?;
+        self.gicd.panic_send_sgi(irq_number, target_mask);
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bsp::device_driver::common::{FakeIRQHandler, FakeMMIO};
+    use exception::asynchronous::{interface::IRQManager, IRQDescriptor};
+    use test_macros::kernel_test;
+
+    const DESCRIPTOR: IRQDescriptor = IRQDescriptor {
+        name: "Test FIQ",
+        handler: &FakeIRQHandler::HANDLED,
+    };
+
+    const GICD_TYPER: usize = 0x004;
+    const GICD_IGROUPR1: usize = 0x084;
+    const GICD_ISENABLER1: usize = 0x104;
+    const GICC_CTLR: usize = 0x000;
+
+    /// FIQ routing must be refused if the GIC implements the Security Extensions.
+    #[kernel_test]
+    fn fiq_routing_requires_secure_state() {
+        let mut gicd_mmio = FakeMMIO::new();
+        let mut gicc_mmio = FakeMMIO::new();
+        gicd_mmio.set(GICD_TYPER, 1 << 10);
+        gicd_mmio.set(GICD_IGROUPR1, u32::MAX);
+
+        let gic = unsafe { GICv2::new(gicd_mmio.descriptor(), gicc_mmio.descriptor()) };
+        let irq_number = IRQNumber::new(40);
+
+        assert_eq!(
+            gic.register_fiq_handler(irq_number, DESCRIPTOR),
+            Err("FIQ routing requires Secure state")
+        );
+        assert_eq!(gicd_mmio.get(GICD_IGROUPR1), u32::MAX);
+        assert_eq!(gicc_mmio.get(GICC_CTLR), 0);
+    }
+
+    /// Without the Security Extensions, the IRQ must be moved to group 0, which is signaled as FIQ.
+    #[kernel_test]
+    fn fiq_routing_moves_irq_to_group0() {
+        let mut gicd_mmio = FakeMMIO::new();
+        let mut gicc_mmio = FakeMMIO::new();
+        gicd_mmio.set(GICD_IGROUPR1, u32::MAX);
+
+        let gic = unsafe { GICv2::new(gicd_mmio.descriptor(), gicc_mmio.descriptor()) };
+        let irq_number = IRQNumber::new(40);
+
+        assert_eq!(gic.register_fiq_handler(irq_number, DESCRIPTOR), Ok(()));
+        assert_eq!(gicd_mmio.get(GICD_IGROUPR1), !(1 << 8));
+        assert_eq!(gicd_mmio.get(GICD_ISENABLER1), 1 << 8);
+        assert_eq!(gicc_mmio.get(GICC_CTLR), 1 << 3);
+
+        assert_eq!(
+            gic.register_fiq_handler(irq_number, DESCRIPTOR),
+            Err("FIQ handler already registered")
+        );
+    }
 }
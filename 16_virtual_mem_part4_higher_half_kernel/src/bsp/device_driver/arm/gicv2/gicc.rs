@@ -7,7 +7,7 @@
 use crate::{
     bsp::device_driver::common::MMIODerefWrapper, exception, synchronization::InitStateLock,
 };
-use register::{mmio::*, register_bitfields, register_structs};
+use register::{mmio::*, register_bitfields, register_structs, LocalRegisterCopy};
 
 //--------------------------------------------------------------------------------------------------
 // Private Definitions
@@ -18,6 +18,10 @@ register_bitfields! {
 
     /// CPU Interface Control Register
     CTLR [
+        /// Signal group 0 interrupts using the FIQ signal. Only accessible in Secure state, or if
+        /// the GIC does not implement the Security Extensions.
+        FIQEn OFFSET(3) NUMBITS(1) [],
+
         Enable OFFSET(0) NUMBITS(1) []
     ],
 
@@ -28,11 +32,17 @@ register_bitfields! {
 
     /// Interrupt Acknowledge Register
     IAR [
+        /// For SGIs, the core that requested the interrupt.
+        CPUID OFFSET(10) NUMBITS(3) [],
+
         InterruptID OFFSET(0) NUMBITS(10) []
     ],
 
     /// End of Interrupt Register
     EOIR [
+        /// For SGIs, must match the CPUID of the acknowledged interrupt.
+        CPUID OFFSET(10) NUMBITS(3) [],
+
         EOIINTID OFFSET(0) NUMBITS(10) []
     ]
 }
@@ -61,11 +71,22 @@ pub struct GICC {
     registers: InitStateLock<Registers>,
 }
 
+/// An interrupt that has been acknowledged at the CPU interface.
+#[derive(Copy, Clone)]
+pub struct AcknowledgedIRQ(LocalRegisterCopy<u32, IAR::Register>);
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
 use crate::synchronization::interface::ReadWriteEx;
 
+impl AcknowledgedIRQ {
+    /// The interrupt's number.
+    pub fn number(&self) -> usize {
+        self.0.read(IAR::InterruptID) as usize
+    }
+}
+
 impl GICC {
     /// Create an instance.
     ///
@@ -112,7 +133,19 @@ impl GICC {
         });
     }
 
-    /// Extract the number of the highest-priority pending IRQ.
+    /// Signal group 0 interrupts as FIQ instead of IRQ.
+    ///
+    /// # Safety
+    ///
+    /// - GICC MMIO registers are banked per CPU core. It is therefore safe to have `&self` instead
+    ///   of `&mut self`.
+    pub fn enable_fiq(&self) {
+        self.registers.read(|regs| {
+            regs.CTLR.modify(CTLR::FIQEn::SET);
+        });
+    }
+
+    /// Acknowledge the highest-priority pending IRQ.
     ///
     /// Can only be called from IRQ context, which is ensured by taking an `IRQContext` token.
     ///
@@ -121,19 +154,19 @@ impl GICC {
     /// - GICC MMIO registers are banked per CPU core. It is therefore safe to have `&self` instead
     ///   of `&mut self`.
     #[allow(clippy::trivially_copy_pass_by_ref)]
-    pub fn pending_irq_number<'irq_context>(
+    pub fn acknowledge_pending_irq<'irq_context>(
         &self,
         _ic: &exception::asynchronous::IRQContext<'irq_context>,
-    ) -> usize {
+    ) -> AcknowledgedIRQ {
         self.registers
-            .read(|regs| regs.IAR.read(IAR::InterruptID) as usize)
+            .read(|regs| AcknowledgedIRQ(regs.IAR.extract()))
     }
 
     /// Complete handling of the currently active IRQ.
     ///
     /// Can only be called from IRQ context, which is ensured by taking an `IRQContext` token.
     ///
-    /// To be called after `pending_irq_number()`.
+    /// To be called after `acknowledge_pending_irq()`.
     ///
     /// # Safety
     ///
@@ -142,11 +175,14 @@ impl GICC {
     #[allow(clippy::trivially_copy_pass_by_ref)]
     pub fn mark_comleted<'irq_context>(
         &self,
-        irq_number: u32,
+        irq: AcknowledgedIRQ,
         _ic: &exception::asynchronous::IRQContext<'irq_context>,
     ) {
         self.registers.read(|regs| {
-            regs.EOIR.write(EOIR::EOIINTID.val(irq_number));
+            regs.EOIR.write(
+                EOIR::EOIINTID.val(irq.0.read(IAR::InterruptID))
+                    + EOIR::CPUID.val(irq.0.read(IAR::CPUID)),
+            );
         });
     }
 }
@@ -6,13 +6,16 @@
 //!
 //! # Glossary
 //!   - SPI - Shared Peripheral Interrupt.
+//!   - SGI - Software Generated Interrupt.
+//!   - PPI - Private Peripheral Interrupt.
 
 use crate::{
     bsp::device_driver::common::MMIODerefWrapper,
     state, synchronization,
-    synchronization::{IRQSafeNullLock, InitStateLock},
+    synchronization::{IRQSafeSpinLock, InitStateLock},
 };
-use register::{mmio::*, register_bitfields, register_structs};
+use core::sync::atomic::{AtomicU32, Ordering};
+use register::{mmio::*, register_bitfields, register_structs, FieldValue};
 
 //--------------------------------------------------------------------------------------------------
 // Private Definitions
@@ -28,6 +31,7 @@ register_bitfields! {
 
     /// Interrupt Controller Type Register
     TYPER [
+        SecurityExtn  OFFSET(10) NUMBITS(1) [],
         ITLinesNumber OFFSET(0)  NUMBITS(5) []
     ],
 
@@ -37,6 +41,18 @@ register_bitfields! {
         Offset2 OFFSET(16) NUMBITS(8) [],
         Offset1 OFFSET(8)  NUMBITS(8) [],
         Offset0 OFFSET(0)  NUMBITS(8) []
+    ],
+
+    /// Software Generated Interrupt Register
+    SGIR [
+        TargetListFilter OFFSET(24) NUMBITS(2) [
+            CPUTargetList = 0b00,
+            AllOtherCPUs = 0b01
+        ],
+
+        CPUTargetList OFFSET(16) NUMBITS(8) [],
+
+        SGIINTID OFFSET(0) NUMBITS(4) []
     ]
 }
 
@@ -46,10 +62,18 @@ register_structs! {
         (0x000 => CTLR: ReadWrite<u32, CTLR::Register>),
         (0x004 => TYPER: ReadOnly<u32, TYPER::Register>),
         (0x008 => _reserved1),
+        (0x084 => IGROUPR: [ReadWrite<u32>; 31]),
+        (0x100 => _reserved2),
         (0x104 => ISENABLER: [ReadWrite<u32>; 31]),
-        (0x108 => _reserved2),
+        (0x180 => _reserved3),
+        (0x184 => ICENABLER: [WriteOnly<u32>; 31]),
+        (0x200 => _reserved4),
+        (0x420 => IPRIORITYR: [ReadWrite<u32>; 247]),
+        (0x7FC => _reserved5),
         (0x820 => ITARGETSR: [ReadWrite<u32, ITARGETSR::Register>; 248]),
-        (0x824 => @END),
+        (0xC00 => _reserved6),
+        (0xF00 => SGIR: WriteOnly<u32, SGIR::Register>),
+        (0xF04 => @END),
     }
 }
 
@@ -57,13 +81,25 @@ register_structs! {
     #[allow(non_snake_case)]
     BankedRegisterBlock {
         (0x000 => _reserved1),
+        (0x080 => IGROUPR: ReadWrite<u32>),
+        (0x084 => _reserved2),
         (0x100 => ISENABLER: ReadWrite<u32>),
-        (0x104 => _reserved2),
+        (0x104 => _reserved3),
+        (0x180 => ICENABLER: WriteOnly<u32>),
+        (0x184 => _reserved4),
+        (0x400 => IPRIORITYR: [ReadWrite<u32>; 8]),
+        (0x420 => _reserved5),
         (0x800 => ITARGETSR: [ReadOnly<u32, ITARGETSR::Register>; 8]),
-        (0x804 => @END),
+        (0x820 => @END),
     }
 }
 
+/// The number of private IRQs (SGIs and PPIs), whose registers are banked per core.
+const NUM_PRIVATE_IRQS: usize = 32;
+
+/// Each IPRIORITYR holds the priority bytes of four IRQs.
+const NUM_PRIVATE_PRIORITY_REGS: usize = NUM_PRIVATE_IRQS / 4;
+
 /// Abstraction for the non-banked parts of the associated MMIO registers.
 type SharedRegisters = MMIODerefWrapper<SharedRegisterBlock>;
 
@@ -77,10 +113,16 @@ type BankedRegisters = MMIODerefWrapper<BankedRegisterBlock>;
 /// Representation of the GIC Distributor.
 pub struct GICD {
     /// Access to shared registers is guarded with a lock.
-    shared_registers: IRQSafeNullLock<SharedRegisters>,
+    shared_registers: IRQSafeSpinLock<SharedRegisters>,
 
     /// Access to banked registers is unguarded.
     banked_registers: InitStateLock<BankedRegisters>,
+
+    /// The SGIs enabled so far. SGI enables are banked, so secondary cores replay them.
+    enabled_sgis: AtomicU32,
+
+    /// The IPRIORITYR values of the private IRQs, which secondary cores replay as well.
+    private_priorities: [AtomicU32; NUM_PRIVATE_PRIORITY_REGS],
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -112,6 +154,45 @@ impl SharedRegisters {
         // Rust automatically inserts slice range sanity check, i.e. max >= min.
         &self.ITARGETSR[0..spi_itargetsr_max_index]
     }
+
+    /// Return a slice of the implemented shared IPRIORITYR.
+    #[inline(always)]
+    fn implemented_ipriority_slice(&mut self) -> &[ReadWrite<u32>] {
+        // One byte per IRQ, so four SPIs per register. The last IRQ IDs are reserved, so the
+        // array is one register shorter than what the maximum number of IRQs would need.
+        let num_spi_ipriorityr = (self.num_irqs() - 32) >> 2;
+
+        &self.IPRIORITYR[0..num_spi_ipriorityr.min(self.IPRIORITYR.len())]
+    }
+}
+
+/// Replace the priority byte of `irq_num` in the value of its IPRIORITYR.
+fn with_priority(reg_val: u32, irq_num: usize, priority: u8) -> u32 {
+    let shift = (irq_num % 4) * 8;
+
+    (reg_val & !(0xFF << shift)) | ((priority as u32) << shift)
+}
+
+/// An IPRIORITYR value that sets all four IRQs to `priority`.
+const fn all_priorities(priority: u8) -> u32 {
+    (priority as u32) * 0x0101_0101
+}
+
+/// The SGIR value that raises the SGI `irq_num` on the cores selected by `target_mask`.
+fn sgir_value(
+    irq_num: super::IRQNumber,
+    target_mask: Option<u8>,
+) -> FieldValue<u32, SGIR::Register> {
+    let irq_num = irq_num.get() as u32;
+
+    match target_mask {
+        None => SGIR::TargetListFilter::AllOtherCPUs + SGIR::SGIINTID.val(irq_num),
+        Some(mask) => {
+            SGIR::TargetListFilter::CPUTargetList
+                + SGIR::CPUTargetList.val(mask as u32)
+                + SGIR::SGIINTID.val(irq_num)
+        }
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -127,9 +208,16 @@ impl GICD {
     ///
     /// - The user must ensure to provide a correct MMIO start address.
     pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        // Only used to initialize the array, so each element is a distinct atomic.
+        #[allow(clippy::declare_interior_mutable_const)]
+        const DEFAULT_PRIORITIES: AtomicU32 =
+            AtomicU32::new(all_priorities(super::DEFAULT_PRIORITY));
+
         Self {
-            shared_registers: IRQSafeNullLock::new(SharedRegisters::new(mmio_start_addr)),
+            shared_registers: IRQSafeSpinLock::new(SharedRegisters::new(mmio_start_addr)),
             banked_registers: InitStateLock::new(BankedRegisters::new(mmio_start_addr)),
+            enabled_sgis: AtomicU32::new(0),
+            private_priorities: [DEFAULT_PRIORITIES; NUM_PRIVATE_PRIORITY_REGS],
         }
     }
 
@@ -151,7 +239,17 @@ impl GICD {
             .read(|regs| regs.ITARGETSR[0].read(ITARGETSR::Offset0))
     }
 
-    /// Route all SPIs to the boot core and enable the distributor.
+    /// Write the recorded priorities of the private IRQs to the executing core's banked registers.
+    fn load_private_priorities(&self) {
+        self.banked_registers.read(|regs| {
+            for (reg, val) in regs.IPRIORITYR.iter().zip(self.private_priorities.iter()) {
+                reg.set(val.load(Ordering::Relaxed));
+            }
+        });
+    }
+
+    /// Route all SPIs to the boot core, give all IRQs the default priority and enable the
+    /// distributor.
     pub fn boot_core_init(&self) {
         assert!(
             state::state_manager().is_init(),
@@ -161,6 +259,8 @@ impl GICD {
         // Target all SPIs to the boot core only.
         let mask = self.local_gic_target_mask();
 
+        self.load_private_priorities();
+
         self.shared_registers.lock(|regs| {
             for i in regs.implemented_itargets_slice().iter() {
                 i.write(
@@ -171,10 +271,104 @@ impl GICD {
                 );
             }
 
+            // The reset value is the highest priority.
+            for i in regs.implemented_ipriority_slice().iter() {
+                i.set(all_priorities(super::DEFAULT_PRIORITY));
+            }
+
             regs.CTLR.write(CTLR::Enable::SET);
         });
     }
 
+    /// Enable the SGIs that were enabled on the boot core on the executing secondary core, and
+    /// apply the priorities of the private IRQs.
+    pub fn secondary_core_init(&self) {
+        let sgis = self.enabled_sgis.load(Ordering::Relaxed);
+
+        self.load_private_priorities();
+
+        self.banked_registers.read(|regs| {
+            let enable_reg = &regs.ISENABLER;
+            enable_reg.set(enable_reg.get() | sgis);
+        });
+    }
+
+    /// Set the priority of an interrupt. Lower values are higher priorities.
+    ///
+    /// The priorities of private IRQs are banked. They are applied on the executing core right
+    /// away, and on secondary cores when they start.
+    pub fn set_priority(&self, irq_num: super::IRQNumber, priority: u8) {
+        let irq_num = irq_num.get();
+
+        // Each IPRIORITYR holds four priorities. Shift right by 2 (division by 4) and arrive at
+        // the index for the respective IPRIORITYR[i].
+        let priority_reg_index = irq_num >> 2;
+
+        match irq_num {
+            // Private.
+            0..=31 => {
+                let recorded = &self.private_priorities[priority_reg_index];
+                let val = with_priority(recorded.load(Ordering::Relaxed), irq_num, priority);
+                recorded.store(val, Ordering::Relaxed);
+
+                self.banked_registers
+                    .read(|regs| regs.IPRIORITYR[priority_reg_index].set(val));
+            }
+            // Shared.
+            _ => {
+                let priority_reg_index_shared = priority_reg_index - NUM_PRIVATE_PRIORITY_REGS;
+
+                self.shared_registers.lock(|regs| {
+                    let priority_reg = &regs.IPRIORITYR[priority_reg_index_shared];
+                    priority_reg.set(with_priority(priority_reg.get(), irq_num, priority));
+                });
+            }
+        }
+    }
+
+    /// Check if the interrupt groups can be configured.
+    ///
+    /// Quoting the GICv2 Architecture Specification:
+    ///
+    ///   "In a GIC that implements the GIC Security Extensions, [GICD_IGROUPRn] are Secure, and
+    ///    accessible only by Secure accesses."
+    ///
+    /// The kernel runs in Non-secure state, so this is only possible if the GIC does not implement
+    /// the Security Extensions.
+    pub fn is_group_configurable(&self) -> bool {
+        self.shared_registers
+            .lock(|regs| !regs.TYPER.is_set(TYPER::SecurityExtn))
+    }
+
+    /// Assign an interrupt to group 0, which the CPU interface can signal as FIQ.
+    ///
+    /// Writes are ignored unless [`GICD::is_group_configurable`] is true.
+    pub fn set_group0(&self, irq_num: super::IRQNumber) {
+        let irq_num = irq_num.get();
+
+        // Each bit in the u32 group register corresponds to one IRQ number. Shift right by 5
+        // (division by 32) and arrive at the index for the respective IGROUPR[i].
+        let group_reg_index = irq_num >> 5;
+        let group_bit: u32 = 1u32 << (irq_num % 32);
+
+        match irq_num {
+            // Private.
+            0..=31 => self.banked_registers.read(|regs| {
+                let group_reg = &regs.IGROUPR;
+                group_reg.set(group_reg.get() & !group_bit);
+            }),
+            // Shared.
+            _ => {
+                let group_reg_index_shared = group_reg_index - 1;
+
+                self.shared_registers.lock(|regs| {
+                    let group_reg = &regs.IGROUPR[group_reg_index_shared];
+                    group_reg.set(group_reg.get() & !group_bit);
+                });
+            }
+        }
+    }
+
     /// Enable an interrupt.
     pub fn enable(&self, irq_num: super::IRQNumber) {
         let irq_num = irq_num.get();
@@ -184,6 +378,10 @@ impl GICD {
         let enable_reg_index = irq_num >> 5;
         let enable_bit: u32 = 1u32 << (irq_num % 32);
 
+        if irq_num < 16 {
+            self.enabled_sgis.fetch_or(enable_bit, Ordering::Relaxed);
+        }
+
         // Check if we are handling a private or shared IRQ.
         match irq_num {
             // Private.
@@ -202,4 +400,101 @@ impl GICD {
             }
         }
     }
+
+    /// Disable an interrupt.
+    ///
+    /// Private IRQs are only disabled on the executing core.
+    pub fn disable(&self, irq_num: super::IRQNumber) {
+        let irq_num = irq_num.get();
+
+        // Same layout as the enable registers.
+        let disable_reg_index = irq_num >> 5;
+        let disable_bit: u32 = 1u32 << (irq_num % 32);
+
+        if irq_num < 16 {
+            self.enabled_sgis.fetch_and(!disable_bit, Ordering::Relaxed);
+        }
+
+        // Writing a 1 to a bit disables the corresponding IRQ. All other IRQs are unaffected.
+        match irq_num {
+            // Private.
+            0..=31 => self
+                .banked_registers
+                .read(|regs| regs.ICENABLER.set(disable_bit)),
+            // Shared.
+            _ => {
+                let disable_reg_index_shared = disable_reg_index - 1;
+
+                self.shared_registers
+                    .lock(|regs| regs.ICENABLER[disable_reg_index_shared].set(disable_bit));
+            }
+        }
+    }
+
+    /// Raise the SGI `irq_num`.
+    ///
+    /// `target_mask` selects the cores by GIC CPU interface number. `None` selects all cores
+    /// except the executing one.
+    pub fn send_sgi(&self, irq_num: super::IRQNumber, target_mask: Option<u8>) {
+        let val = sgir_value(irq_num, target_mask);
+
+        self.shared_registers.lock(|regs| regs.SGIR.write(val));
+    }
+
+    /// Like [`send_sgi()`](Self::send_sgi), but without taking the lock.
+    ///
+    /// # Safety
+    ///
+    /// - Only use from the panic handler.
+    pub unsafe fn panic_send_sgi(&self, irq_num: super::IRQNumber, target_mask: Option<u8>) {
+        self.shared_registers
+            .get_unlocked()
+            .SGIR
+            .write(sgir_value(irq_num, target_mask));
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bsp::device_driver::{arm::gicv2::IRQNumber, common::FakeMMIO};
+    use test_macros::kernel_test;
+
+    const GICD_IPRIORITYR: usize = 0x400;
+
+    /// Priorities must land in the byte of their IRQ, and the banked priorities of the private IRQs
+    /// must be replayed on secondary cores.
+    #[kernel_test]
+    fn priorities_are_set_and_replayed() {
+        let mut mmio = FakeMMIO::new();
+        let gicd = unsafe { GICD::new(mmio.descriptor().start_addr().into_usize()) };
+        let default = all_priorities(super::super::DEFAULT_PRIORITY);
+
+        // Private IRQ 30 is in IPRIORITYR7, shared IRQ 41 in IPRIORITYR10.
+        gicd.set_priority(IRQNumber::new(30), 0x40);
+        gicd.set_priority(IRQNumber::new(41), 0xC0);
+
+        assert_eq!(
+            mmio.get(GICD_IPRIORITYR + 7 * 4),
+            (default & !0x00FF_0000) | 0x0040_0000
+        );
+        assert_eq!(mmio.get(GICD_IPRIORITYR + 10 * 4), 0x0000_C000);
+
+        // A secondary core starts with the reset values in its banked registers.
+        for i in 0..NUM_PRIVATE_PRIORITY_REGS {
+            mmio.set(GICD_IPRIORITYR + i * 4, 0);
+        }
+        gicd.secondary_core_init();
+
+        assert_eq!(mmio.get(GICD_IPRIORITYR), default);
+        assert_eq!(
+            mmio.get(GICD_IPRIORITYR + 7 * 4),
+            (default & !0x00FF_0000) | 0x0040_0000
+        );
+        assert_eq!(mmio.get(GICD_IPRIORITYR + 10 * 4), 0x0000_C000);
+    }
 }
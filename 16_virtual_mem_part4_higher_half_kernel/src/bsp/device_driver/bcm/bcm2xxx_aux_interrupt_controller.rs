@@ -0,0 +1,285 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! AUX Interrupt Controller Driver.
+//!
+//! The auxiliary peripherals, i.e. the mini UART and the SPI1 and SPI2 masters, share a single IRQ
+//! of the primary interrupt controller. The AUX_IRQ register tells which of them are pending, so
+//! this driver is chained beneath the primary controller: It registers itself as the handler of
+//! the shared IRQ, and dispatches to the handlers of the pending auxiliary peripherals. The shared
+//! IRQ is only enabled once the controller is chained and the first auxiliary peripheral registered
+//! a handler.
+//!
+//! # Resources
+//!
+//! - BCM2835 ARM Peripherals, chapter 2.1 "Auxiliary peripherals"
+
+use crate::{
+    bsp, bsp::device_driver::common::MMIODerefWrapper, driver, exception, memory, synchronization,
+    synchronization::InitStateLock,
+};
+use core::sync::atomic::{AtomicBool, Ordering};
+use register::{mmio::*, register_bitfields, register_structs};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u32,
+
+    /// Auxiliary Interrupt status. Each bit is set while the peripheral has a pending interrupt.
+    AUX_IRQ [
+        SPI2 OFFSET(2) NUMBITS(1) [],
+        SPI1 OFFSET(1) NUMBITS(1) [],
+        MINI_UART OFFSET(0) NUMBITS(1) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => AUX_IRQ: ReadOnly<u32, AUX_IRQ::Register>),
+        (0x04 => @END),
+    }
+}
+
+/// Abstraction for the associated MMIO registers.
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+type HandlerTable =
+    exception::asynchronous::IRQHandlerTable<{ AuxInterruptController::NUM_AUX_IRQS }>;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Used for the associated type of trait [`exception::asynchronous::interface::IRQManager`].
+///
+/// The numbers are the bit positions in the AUX_IRQ register: 0 is the mini UART, 1 is SPI1 and 2
+/// is SPI2.
+pub type AuxIRQ =
+    exception::asynchronous::IRQNumber<{ AuxInterruptController::MAX_AUX_IRQ_NUMBER }>;
+
+/// Representation of the AUX interrupt controller.
+pub struct AuxInterruptController {
+    mmio_descriptor: memory::mmu::MMIODescriptor,
+
+    /// Register read access is unguarded.
+    registers: InitStateLock<Registers>,
+
+    /// The IRQ of the primary interrupt controller that signals the auxiliary peripherals' IRQs.
+    parent_irq_number: bsp::device_driver::IRQNumber,
+
+    /// Stores registered IRQ handlers. Writable only during kernel init. RO afterwards.
+    handler_table: InitStateLock<HandlerTable>,
+
+    /// Per-IRQ statistics.
+    stats: exception::asynchronous::IRQStatsTable<{ AuxInterruptController::NUM_AUX_IRQS }>,
+
+    /// Whether the controller registered itself as the handler of the parent IRQ.
+    chained: AtomicBool,
+
+    /// Whether an auxiliary peripheral registered a handler.
+    has_handlers: AtomicBool,
+
+    /// Whether the parent IRQ was enabled, which happens once it is both chained and has handlers.
+    parent_enabled: AtomicBool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl AuxInterruptController {
+    const MAX_AUX_IRQ_NUMBER: usize = 2;
+    const NUM_AUX_IRQS: usize = Self::MAX_AUX_IRQ_NUMBER + 1;
+
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide a correct MMIO descriptor.
+    pub const unsafe fn new(
+        mmio_descriptor: memory::mmu::MMIODescriptor,
+        parent_irq_number: bsp::device_driver::IRQNumber,
+    ) -> Self {
+        Self {
+            mmio_descriptor,
+            registers: InitStateLock::new(Registers::new(
+                mmio_descriptor.start_addr().into_usize(),
+            )),
+            parent_irq_number,
+            handler_table: InitStateLock::new(HandlerTable::new()),
+            stats: exception::asynchronous::IRQStatsTable::new(),
+            chained: AtomicBool::new(false),
+            has_handlers: AtomicBool::new(false),
+            parent_enabled: AtomicBool::new(false),
+        }
+    }
+
+    /// Enable the parent IRQ once the controller is chained and has handlers, so that it neither
+    /// fires without a consumer nor before it is routed here.
+    fn enable_parent_if_ready(&self) {
+        use bsp::exception::asynchronous::irq_manager;
+        use exception::asynchronous::interface::IRQManager;
+
+        if !self.chained.load(Ordering::Relaxed) || !self.has_handlers.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if !self.parent_enabled.swap(true, Ordering::Relaxed) {
+            irq_manager().enable(self.parent_irq_number);
+        }
+    }
+
+    /// Query the bitmask of pending IRQs.
+    fn pending_irqs(&self) -> u32 {
+        self.registers.read(|regs| regs.AUX_IRQ.get())
+    }
+
+    /// Call the handlers of all pending IRQs.
+    fn dispatch_pending_irqs(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        use exception::asynchronous::IRQReturn;
+
+        let pending = self.pending_irqs();
+        let mut ret = IRQReturn::NotHandled;
+
+        self.handler_table.read(|table| {
+            for irq_number in (0..Self::NUM_AUX_IRQS).filter(|x| pending & (1 << x) != 0) {
+                // AUX IRQs can not be masked here. If one keeps going unhandled, so does the
+                // parent line, which is then disabled by its controller.
+                let irq_ret = self.stats.dispatch(
+                    table,
+                    irq_number,
+                    |descriptor| descriptor.handler.handle(),
+                    || (),
+                )?;
+
+                if irq_ret == IRQReturn::Handled {
+                    ret = IRQReturn::Handled;
+                }
+            }
+
+            Ok(ret)
+        })
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+use synchronization::interface::ReadWriteEx;
+
+impl driver::interface::DeviceDriver for AuxInterruptController {
+    fn compatible(&self) -> &'static str {
+        "BCM AUX Interrupt Controller"
+    }
+
+    unsafe fn init(&self) -> Result<(), &'static str> {
+        let virt_addr =
+            memory::mmu::kernel_map_mmio(self.compatible(), &self.mmio_descriptor)?.into_usize();
+
+        self.registers
+            .write(|regs| *regs = Registers::new(virt_addr));
+
+        Ok(())
+    }
+
+    /// Chain the controller beneath the primary interrupt controller.
+    ///
+    /// The parent IRQ stays disabled until an auxiliary peripheral registers a handler.
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        use bsp::exception::asynchronous::irq_manager;
+        use exception::asynchronous::{interface::IRQManager, IRQDescriptor};
+
+        let descriptor = IRQDescriptor {
+            name: self.compatible(),
+            handler: self,
+        };
+
+        irq_manager().register_handler(self.parent_irq_number, descriptor)?;
+        self.chained.store(true, Ordering::Relaxed);
+        self.enable_parent_if_ready();
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQHandler for AuxInterruptController {
+    /// Not handled if none of the auxiliary peripherals raised the interrupt, which leaves the
+    /// parent IRQ to other devices that share it.
+    fn handle(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        self.dispatch_pending_irqs()
+    }
+}
+
+impl exception::asynchronous::interface::IRQManager for AuxInterruptController {
+    type IRQNumberType = AuxIRQ;
+
+    fn register_handler(
+        &self,
+        irq: Self::IRQNumberType,
+        descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        self.handler_table
+            .write(|table| table.register(irq.get(), descriptor))?;
+        self.has_handlers.store(true, Ordering::Relaxed);
+        self.enable_parent_if_ready();
+
+        Ok(())
+    }
+
+    /// The auxiliary peripherals' IRQs are enabled in the peripherals themselves, and the parent
+    /// IRQ is enabled once the controller is chained and has handlers. So there is nothing left to
+    /// do here.
+    fn enable(&self, _irq: Self::IRQNumberType) {}
+
+    fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        if self.pending_irqs() == 0 {
+            self.stats.record_spurious();
+            return;
+        }
+
+        self.dispatch_pending_irqs().expect("Error handling IRQ");
+    }
+
+    fn register_fiq_handler(
+        &self,
+        _irq: Self::IRQNumberType,
+        _descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        Err("FIQ routing of AUX IRQs not supported")
+    }
+
+    fn handle_pending_fiq<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        panic!("No AUX FIQ handler registered")
+    }
+
+    fn print_handler(&self) {
+        use crate::info;
+
+        info!("      AUX handler:");
+
+        self.handler_table.read(|table| {
+            for (i, handler) in table.iter() {
+                info!("            {: >3}. {}", i, handler.name);
+            }
+        });
+    }
+
+    fn print_stats(&self) {
+        use crate::info;
+
+        info!("      AUX IRQs:");
+
+        self.handler_table.read(|table| self.stats.print(table));
+    }
+}
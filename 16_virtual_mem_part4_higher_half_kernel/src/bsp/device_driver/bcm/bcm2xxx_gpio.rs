@@ -6,7 +6,7 @@
 
 use crate::{
     bsp::device_driver::common::MMIODerefWrapper, driver, memory, synchronization,
-    synchronization::IRQSafeNullLock,
+    synchronization::IRQSafeSpinLock,
 };
 use core::sync::atomic::{AtomicUsize, Ordering};
 use register::{mmio::*, register_bitfields, register_structs};
@@ -120,7 +120,7 @@ pub use GPIOInner as PanicGPIO;
 pub struct GPIO {
     mmio_descriptor: memory::mmu::MMIODescriptor,
     virt_mmio_start_addr: AtomicUsize,
-    inner: IRQSafeNullLock<GPIOInner>,
+    inner: IRQSafeSpinLock<GPIOInner>,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -211,7 +211,7 @@ impl GPIO {
         Self {
             mmio_descriptor,
             virt_mmio_start_addr: AtomicUsize::new(0),
-            inner: IRQSafeNullLock::new(GPIOInner::new(mmio_descriptor.start_addr().into_usize())),
+            inner: IRQSafeSpinLock::new(GPIOInner::new(mmio_descriptor.start_addr().into_usize())),
         }
     }
 
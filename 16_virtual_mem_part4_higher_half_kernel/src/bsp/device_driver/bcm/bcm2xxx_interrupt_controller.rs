@@ -4,23 +4,20 @@
 
 //! Interrupt Controller Driver.
 
+mod local_ic;
 mod peripheral_ic;
 
 use crate::{driver, exception, memory};
 
 //--------------------------------------------------------------------------------------------------
-// Private Definitions
+// Public Definitions
 //--------------------------------------------------------------------------------------------------
 
 /// Wrapper struct for a bitmask indicating pending IRQ numbers.
-struct PendingIRQs {
+pub struct PendingIRQs {
     bitmask: u64,
 }
 
-//--------------------------------------------------------------------------------------------------
-// Public Definitions
-//--------------------------------------------------------------------------------------------------
-
 pub type LocalIRQ =
     exception::asynchronous::IRQNumber<{ InterruptController::MAX_LOCAL_IRQ_NUMBER }>;
 pub type PeripheralIRQ =
@@ -35,6 +32,7 @@ pub enum IRQNumber {
 
 /// Representation of the Interrupt Controller.
 pub struct InterruptController {
+    local: local_ic::LocalIC,
     periph: peripheral_ic::PeripheralIC,
 }
 
@@ -46,6 +44,11 @@ impl PendingIRQs {
     pub fn new(bitmask: u64) -> Self {
         Self { bitmask }
     }
+
+    /// Check if no IRQ is pending.
+    pub fn is_empty(&self) -> bool {
+        self.bitmask == 0
+    }
 }
 
 impl Iterator for PendingIRQs {
@@ -80,10 +83,11 @@ impl InterruptController {
     ///
     /// - The user must ensure to provide correct MMIO descriptors.
     pub const unsafe fn new(
-        _local_mmio_descriptor: memory::mmu::MMIODescriptor,
+        local_mmio_descriptor: memory::mmu::MMIODescriptor,
         periph_mmio_descriptor: memory::mmu::MMIODescriptor,
     ) -> Self {
         Self {
+            local: local_ic::LocalIC::new(local_mmio_descriptor),
             periph: peripheral_ic::PeripheralIC::new(periph_mmio_descriptor),
         }
     }
@@ -99,6 +103,7 @@ impl driver::interface::DeviceDriver for InterruptController {
     }
 
     unsafe fn init(&self) -> Result<(), &'static str> {
+        self.local.init()?;
         self.periph.init()
     }
 }
@@ -112,14 +117,14 @@ impl exception::asynchronous::interface::IRQManager for InterruptController {
         descriptor: exception::asynchronous::IRQDescriptor,
     ) -> Result<(), &'static str> {
         match irq {
-            IRQNumber::Local(_) => unimplemented!("Local IRQ controller not implemented."),
+            IRQNumber::Local(lirq) => self.local.register_handler(lirq, descriptor),
             IRQNumber::Peripheral(pirq) => self.periph.register_handler(pirq, descriptor),
         }
     }
 
     fn enable(&self, irq: Self::IRQNumberType) {
         match irq {
-            IRQNumber::Local(_) => unimplemented!("Local IRQ controller not implemented."),
+            IRQNumber::Local(lirq) => self.local.enable(lirq),
             IRQNumber::Peripheral(pirq) => self.periph.enable(pirq),
         }
     }
@@ -128,11 +133,71 @@ impl exception::asynchronous::interface::IRQManager for InterruptController {
         &'irq_context self,
         ic: &exception::asynchronous::IRQContext<'irq_context>,
     ) {
-        // It can only be a peripheral IRQ pending because enable() does not support local IRQs yet.
-        self.periph.handle_pending_irqs(ic)
+        // Peripheral IRQs are signaled to the routed core through the local GPU IRQ.
+        let gpu_irq_pending = self
+            .local
+            .pending_irqs()
+            .any(|x| x == local_ic::LocalIC::GPU_IRQ);
+
+        self.local.handle_pending_irqs(ic);
+
+        if gpu_irq_pending {
+            self.periph.handle_pending_irqs(ic)
+        }
+    }
+
+    fn register_fiq_handler(
+        &self,
+        irq: Self::IRQNumberType,
+        descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        match irq {
+            IRQNumber::Local(lirq) => self.local.register_fiq_handler(lirq, descriptor),
+            IRQNumber::Peripheral(pirq) => self.periph.register_fiq_handler(pirq, descriptor),
+        }
+    }
+
+    fn handle_pending_fiq<'irq_context>(
+        &'irq_context self,
+        ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        // It can only be a peripheral FIQ because local IRQs can not be routed to the FIQ.
+        self.periph.handle_pending_fiq(ic)
     }
 
     fn print_handler(&self) {
+        self.local.print_handler();
         self.periph.print_handler();
     }
+
+    fn print_stats(&self) {
+        self.local.print_stats();
+        self.periph.print_stats();
+    }
+
+    unsafe fn secondary_core_init(&self) {
+        self.local.secondary_core_init();
+    }
+
+    fn send_ipi(
+        &self,
+        irq: Self::IRQNumberType,
+        target: exception::asynchronous::IPITarget,
+    ) -> Result<(), &'static str> {
+        match irq {
+            IRQNumber::Local(lirq) => self.local.send_ipi(lirq, target),
+            IRQNumber::Peripheral(_) => Err("Peripheral IRQs can not be used as IPIs"),
+        }
+    }
+
+    unsafe fn panic_send_ipi(
+        &self,
+        irq: Self::IRQNumberType,
+        target: exception::asynchronous::IPITarget,
+    ) -> Result<(), &'static str> {
+        match irq {
+            IRQNumber::Local(lirq) => self.local.panic_send_ipi(lirq, target),
+            IRQNumber::Peripheral(_) => Err("Peripheral IRQs can not be used as IPIs"),
+        }
+    }
 }
@@ -0,0 +1,398 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Local Interrupt Controller Driver.
+//!
+//! The per-core interrupt controller of the BCM2836 and later, which collects the core timer,
+//! mailbox and PMU interrupts, and routes the GPU (peripheral) interrupt to one of the cores.
+//!
+//! Each core has four mailboxes. Writing a mailbox's set register from any core raises the
+//! mailbox IRQ on the owning core, which makes mailboxes the inter-processor interrupt mechanism.
+//!
+//! # Resources
+//!
+//! - BCM2836 ARM-local peripherals (QA7_rev3.4.pdf)
+
+use super::{LocalIRQ, PendingIRQs};
+use crate::{
+    bsp,
+    bsp::device_driver::common::MMIODerefWrapper,
+    cpu, driver, exception, memory, synchronization,
+    synchronization::{IRQSafeSpinLock, InitStateLock},
+};
+use core::sync::atomic::{AtomicU32, Ordering};
+use register::{mmio::*, register_bitfields, register_structs};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u32,
+
+    /// GPU Interrupts Routing.
+    GPU_INT_ROUTING [
+        /// The core that receives the GPU FIQ.
+        FIQ_CORE OFFSET(2) NUMBITS(2) [],
+
+        /// The core that receives the GPU IRQ.
+        IRQ_CORE OFFSET(0) NUMBITS(2) []
+    ],
+
+    /// Local Interrupt Routing. Routes the local timer interrupt.
+    LOCAL_INT_ROUTING [
+        /// Destination core and interrupt type. Values 0..3 select the IRQ of cores 0..3.
+        DESTINATION OFFSET(0) NUMBITS(3) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => _reserved1),
+        (0x0c => GPU_INT_ROUTING: ReadWrite<u32, GPU_INT_ROUTING::Register>),
+        (0x10 => PMU_INT_ROUTING_SET: WriteOnly<u32>),
+        (0x14 => PMU_INT_ROUTING_CLR: WriteOnly<u32>),
+        (0x18 => _reserved2),
+        (0x24 => LOCAL_INT_ROUTING: ReadWrite<u32, LOCAL_INT_ROUTING::Register>),
+        (0x28 => _reserved3),
+        (0x40 => CORE_TIMER_INT_CONTROL: [ReadWrite<u32>; 4]),
+        (0x50 => CORE_MAILBOX_INT_CONTROL: [ReadWrite<u32>; 4]),
+        (0x60 => CORE_IRQ_SOURCE: [ReadOnly<u32>; 4]),
+        (0x70 => CORE_FIQ_SOURCE: [ReadOnly<u32>; 4]),
+        (0x80 => CORE_MAILBOX_WRITE_SET: [WriteOnly<u32>; 16]),
+        (0xc0 => CORE_MAILBOX_READ_WRITE_CLR: [ReadWrite<u32>; 16]),
+        (0x100 => @END),
+    }
+}
+
+/// Abstraction for the associated MMIO registers.
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+type HandlerTable = exception::asynchronous::IRQHandlerTable<{ LocalIC::NUM_LOCAL_IRQS }>;
+
+/// Local IRQ numbers, which are the bit positions in the core IRQ source registers.
+mod local_irq {
+    pub const CNTVIRQ: usize = 3;
+    pub const MAILBOX_0: usize = 4;
+    pub const MAILBOX_3: usize = 7;
+    pub const GPU: usize = 8;
+    pub const PMU: usize = 9;
+    pub const AXI_OUTSTANDING: usize = 10;
+    pub const LOCAL_TIMER: usize = 11;
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Representation of the local interrupt controller.
+pub struct LocalIC {
+    mmio_descriptor: memory::mmu::MMIODescriptor,
+
+    /// Access to registers is guarded with a lock.
+    registers: IRQSafeSpinLock<Registers>,
+
+    /// Stores registered IRQ handlers. Writable only during kernel init. RO afterwards.
+    handler_table: InitStateLock<HandlerTable>,
+
+    /// Per-IRQ statistics.
+    stats: exception::asynchronous::IRQStatsTable<{ LocalIC::NUM_LOCAL_IRQS }>,
+
+    /// The mailboxes enabled so far. Mailbox enables are per core, so secondary cores replay them.
+    enabled_mailboxes: AtomicU32,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// The executing core, as index into the per-core registers.
+fn core_index() -> usize {
+    cpu::smp::core_id::<usize>()
+}
+
+/// Index of a core's mailbox into the mailbox set and clear registers.
+fn mailbox_index(core: usize, irq_number: usize) -> usize {
+    core * 4 + (irq_number - local_irq::MAILBOX_0)
+}
+
+/// Raise the mailbox IRQ `irq_number` on the cores selected by `target`.
+fn write_mailboxes(
+    regs: &Registers,
+    irq_number: usize,
+    target: exception::asynchronous::IPITarget,
+) -> Result<(), &'static str> {
+    if !(local_irq::MAILBOX_0..=local_irq::MAILBOX_3).contains(&irq_number) {
+        return Err("IPIs must use a mailbox IRQ");
+    }
+
+    let targets = match target {
+        exception::asynchronous::IPITarget::AllOthers => {
+            let all: u32 = (1 << bsp::cpu::NUM_CORES) - 1;
+
+            all & !(1 << core_index())
+        }
+        exception::asynchronous::IPITarget::Core(core) => {
+            if core >= bsp::cpu::NUM_CORES {
+                return Err("Invalid core id");
+            }
+
+            1 << core
+        }
+    };
+
+    for core in 0..bsp::cpu::NUM_CORES {
+        if targets & (1 << core) != 0 {
+            regs.CORE_MAILBOX_WRITE_SET[mailbox_index(core, irq_number)].set(1);
+        }
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl LocalIC {
+    const NUM_LOCAL_IRQS: usize = super::InterruptController::MAX_LOCAL_IRQ_NUMBER + 1;
+
+    /// The local IRQ number that signals pending peripheral IRQs.
+    pub const GPU_IRQ: usize = local_irq::GPU;
+
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide correct MMIO descriptors.
+    pub const unsafe fn new(mmio_descriptor: memory::mmu::MMIODescriptor) -> Self {
+        let addr = mmio_descriptor.start_addr().into_usize();
+
+        Self {
+            mmio_descriptor,
+            registers: IRQSafeSpinLock::new(Registers::new(addr)),
+            handler_table: InitStateLock::new(HandlerTable::new()),
+            stats: exception::asynchronous::IRQStatsTable::new(),
+            enabled_mailboxes: AtomicU32::new(0),
+        }
+    }
+
+    /// Route the GPU IRQ and FIQ, which carry the peripheral interrupts, to the given core.
+    pub fn route_peripheral_irqs_to(&self, core: usize) {
+        self.registers.lock(|regs| {
+            regs.GPU_INT_ROUTING.write(
+                GPU_INT_ROUTING::IRQ_CORE.val(core as u32)
+                    + GPU_INT_ROUTING::FIQ_CORE.val(core as u32),
+            )
+        });
+    }
+
+    /// Query the list of pending local IRQs of the executing core.
+    pub fn pending_irqs(&self) -> PendingIRQs {
+        self.registers
+            .lock(|regs| PendingIRQs::new(u64::from(regs.CORE_IRQ_SOURCE[core_index()].get())))
+    }
+
+    /// Disable a local IRQ of the executing core.
+    fn disable(&self, irq_number: usize) {
+        let core = core_index();
+
+        self.registers.lock(|regs| match irq_number {
+            0..=local_irq::CNTVIRQ => {
+                let reg = &regs.CORE_TIMER_INT_CONTROL[core];
+                reg.set(reg.get() & !(1 << irq_number));
+            }
+            local_irq::MAILBOX_0..=local_irq::MAILBOX_3 => {
+                let mailbox_bit = 1 << (irq_number - local_irq::MAILBOX_0);
+                self.enabled_mailboxes
+                    .fetch_and(!mailbox_bit, Ordering::Relaxed);
+
+                let reg = &regs.CORE_MAILBOX_INT_CONTROL[core];
+                reg.set(reg.get() & !mailbox_bit);
+            }
+            local_irq::PMU => regs.PMU_INT_ROUTING_CLR.set(1 << core),
+            // The local timer can only be silenced at its source.
+            _ => (),
+        });
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+use synchronization::interface::{Mutex, ReadWriteEx};
+
+impl driver::interface::DeviceDriver for LocalIC {
+    fn compatible(&self) -> &'static str {
+        "BCM Local Interrupt Controller"
+    }
+
+    unsafe fn init(&self) -> Result<(), &'static str> {
+        let virt_addr =
+            memory::mmu::kernel_map_mmio(self.compatible(), &self.mmio_descriptor)?.into_usize();
+
+        self.registers
+            .lock(|regs| *regs = Registers::new(virt_addr));
+
+        // Peripheral interrupts are handled by the boot core.
+        self.route_peripheral_irqs_to(core_index());
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQManager for LocalIC {
+    type IRQNumberType = LocalIRQ;
+
+    fn register_handler(
+        &self,
+        irq: Self::IRQNumberType,
+        descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        let irq_number = irq.get();
+
+        match irq_number {
+            local_irq::GPU => return Err("The GPU IRQ is reserved for peripheral IRQs"),
+            local_irq::AXI_OUTSTANDING => return Err("AXI-outstanding IRQ not supported"),
+            _ => (),
+        }
+
+        self.handler_table
+            .write(|table| table.register(irq_number, descriptor))
+    }
+
+    /// Enable the IRQ for the executing core.
+    fn enable(&self, irq: Self::IRQNumberType) {
+        let core = core_index();
+        let irq_number = irq.get();
+
+        self.registers.lock(|regs| match irq_number {
+            0..=local_irq::CNTVIRQ => {
+                let reg = &regs.CORE_TIMER_INT_CONTROL[core];
+                reg.set(reg.get() | (1 << irq_number));
+            }
+            local_irq::MAILBOX_0..=local_irq::MAILBOX_3 => {
+                let mailbox_bit = 1 << (irq_number - local_irq::MAILBOX_0);
+                self.enabled_mailboxes
+                    .fetch_or(mailbox_bit, Ordering::Relaxed);
+
+                let reg = &regs.CORE_MAILBOX_INT_CONTROL[core];
+                reg.set(reg.get() | mailbox_bit);
+            }
+            local_irq::GPU => regs
+                .GPU_INT_ROUTING
+                .modify(GPU_INT_ROUTING::IRQ_CORE.val(core as u32)),
+            local_irq::PMU => regs.PMU_INT_ROUTING_SET.set(1 << core),
+            local_irq::LOCAL_TIMER => regs
+                .LOCAL_INT_ROUTING
+                .write(LOCAL_INT_ROUTING::DESTINATION.val(core as u32)),
+            _ => panic!("Local IRQ {} can not be enabled", irq_number),
+        });
+    }
+
+    fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        let pending_irqs = self.pending_irqs();
+
+        // The IRQ source registers are the first to look at when an IRQ is taken.
+        if pending_irqs.is_empty() {
+            self.stats.record_spurious();
+            return;
+        }
+
+        self.handler_table.read(|table| {
+            for irq_number in pending_irqs {
+                // Pending peripheral IRQs are dispatched by the caller.
+                if irq_number == local_irq::GPU {
+                    continue;
+                }
+
+                // Acknowledge mailbox IRQs before calling the handler, so that a request raised
+                // during handling is not lost.
+                if let local_irq::MAILBOX_0..=local_irq::MAILBOX_3 = irq_number {
+                    let index = mailbox_index(core_index(), irq_number);
+
+                    self.registers.lock(|regs| {
+                        let reg = &regs.CORE_MAILBOX_READ_WRITE_CLR[index];
+                        reg.set(reg.get());
+                    });
+                }
+
+                // Call the IRQ's handlers. Panics on failure.
+                self.stats
+                    .dispatch(
+                        table,
+                        irq_number,
+                        |descriptor| descriptor.handler.handle(),
+                        || self.disable(irq_number),
+                    )
+                    .expect("Error handling IRQ");
+            }
+        })
+    }
+
+    fn register_fiq_handler(
+        &self,
+        _irq: Self::IRQNumberType,
+        _descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        Err("FIQ routing of local IRQs not supported")
+    }
+
+    fn handle_pending_fiq<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        panic!("No local FIQ handler registered")
+    }
+
+    fn print_handler(&self) {
+        use crate::info;
+
+        info!("      Local handler:");
+
+        self.handler_table.read(|table| {
+            for (i, handler) in table.iter() {
+                info!("            {: >3}. {}", i, handler.name);
+            }
+        });
+    }
+
+    fn print_stats(&self) {
+        use crate::info;
+
+        info!("      Local IRQs:");
+
+        self.handler_table.read(|table| self.stats.print(table));
+    }
+
+    unsafe fn secondary_core_init(&self) {
+        let mailboxes = self.enabled_mailboxes.load(Ordering::Relaxed);
+
+        self.registers.lock(|regs| {
+            let reg = &regs.CORE_MAILBOX_INT_CONTROL[core_index()];
+            reg.set(reg.get() | mailboxes);
+        });
+    }
+
+    fn send_ipi(
+        &self,
+        irq: Self::IRQNumberType,
+        target: exception::asynchronous::IPITarget,
+    ) -> Result<(), &'static str> {
+        self.registers
+            .lock(|regs| write_mailboxes(regs, irq.get(), target))
+    }
+
+    unsafe fn panic_send_ipi(
+        &self,
+        irq: Self::IRQNumberType,
+        target: exception::asynchronous::IPITarget,
+    ) -> Result<(), &'static str> {
+        write_mailboxes(self.registers.get_unlocked(), irq.get(), target)
+    }
+}
@@ -8,20 +8,38 @@ use super::{InterruptController, PendingIRQs, PeripheralIRQ};
 use crate::{
     bsp::device_driver::common::MMIODerefWrapper,
     driver, exception, memory, synchronization,
-    synchronization::{IRQSafeNullLock, InitStateLock},
+    synchronization::{IRQSafeSpinLock, InitStateLock},
 };
-use register::{mmio::*, register_structs};
+use register::{mmio::*, register_bitfields, register_structs};
 
 //--------------------------------------------------------------------------------------------------
 // Private Definitions
 //--------------------------------------------------------------------------------------------------
 
+register_bitfields! {
+    u32,
+
+    /// FIQ Control Register.
+    FIQ_CONTROL [
+        /// Enable the FIQ generation.
+        ENABLE OFFSET(7) NUMBITS(1) [],
+
+        /// Select which interrupt source can generate a FIQ. Sources 0..63 are the peripheral
+        /// IRQs.
+        SOURCE OFFSET(0) NUMBITS(7) []
+    ]
+}
+
 register_structs! {
     #[allow(non_snake_case)]
     WORegisterBlock {
         (0x00 => _reserved1),
+        (0x0c => FIQ_CONTROL: WriteOnly<u32, FIQ_CONTROL::Register>),
         (0x10 => ENABLE_1: WriteOnly<u32>),
         (0x14 => ENABLE_2: WriteOnly<u32>),
+        (0x18 => _reserved2),
+        (0x1c => DISABLE_1: WriteOnly<u32>),
+        (0x20 => DISABLE_2: WriteOnly<u32>),
         (0x24 => @END),
     }
 }
@@ -43,7 +61,7 @@ type WriteOnlyRegisters = MMIODerefWrapper<WORegisterBlock>;
 type ReadOnlyRegisters = MMIODerefWrapper<RORegisterBlock>;
 
 type HandlerTable =
-    [Option<exception::asynchronous::IRQDescriptor>; InterruptController::NUM_PERIPHERAL_IRQS];
+    exception::asynchronous::IRQHandlerTable<{ InterruptController::NUM_PERIPHERAL_IRQS }>;
 
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
@@ -54,13 +72,19 @@ pub struct PeripheralIC {
     mmio_descriptor: memory::mmu::MMIODescriptor,
 
     /// Access to write registers is guarded with a lock.
-    wo_registers: IRQSafeNullLock<WriteOnlyRegisters>,
+    wo_registers: IRQSafeSpinLock<WriteOnlyRegisters>,
 
     /// Register read access is unguarded.
     ro_registers: InitStateLock<ReadOnlyRegisters>,
 
     /// Stores registered IRQ handlers. Writable only during kernel init. RO afterwards.
     handler_table: InitStateLock<HandlerTable>,
+
+    /// Per-IRQ statistics.
+    stats: exception::asynchronous::IRQStatsTable<{ InterruptController::NUM_PERIPHERAL_IRQS }>,
+
+    /// Stores the registered FIQ handler. Writable only during kernel init. RO afterwards.
+    fiq_handler: InitStateLock<Option<exception::asynchronous::IRQDescriptor>>,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -78,9 +102,11 @@ impl PeripheralIC {
 
         Self {
             mmio_descriptor,
-            wo_registers: IRQSafeNullLock::new(WriteOnlyRegisters::new(addr)),
+            wo_registers: IRQSafeSpinLock::new(WriteOnlyRegisters::new(addr)),
             ro_registers: InitStateLock::new(ReadOnlyRegisters::new(addr)),
-            handler_table: InitStateLock::new([None; InterruptController::NUM_PERIPHERAL_IRQS]),
+            handler_table: InitStateLock::new(HandlerTable::new()),
+            stats: exception::asynchronous::IRQStatsTable::new(),
+            fiq_handler: InitStateLock::new(None),
         }
     }
 
@@ -93,6 +119,20 @@ impl PeripheralIC {
             PendingIRQs::new(pending_mask)
         })
     }
+
+    /// Disable an IRQ.
+    fn disable(&self, irq_number: usize) {
+        self.wo_registers.lock(|regs| {
+            let disable_reg = if irq_number <= 31 {
+                &regs.DISABLE_1
+            } else {
+                &regs.DISABLE_2
+            };
+
+            // Like the enable registers, bits written as 0 are unaffected.
+            disable_reg.set(1 << (irq_number % 32));
+        });
+    }
 }
 
 //------------------------------------------------------------------------------
@@ -126,17 +166,8 @@ impl exception::asynchronous::interface::IRQManager for PeripheralIC {
         irq: Self::IRQNumberType,
         descriptor: exception::asynchronous::IRQDescriptor,
     ) -> Result<(), &'static str> {
-        self.handler_table.write(|table| {
-            let irq_number = irq.get();
-
-            if table[irq_number].is_some() {
-                return Err("IRQ handler already registered");
-            }
-
-            table[irq_number] = Some(descriptor);
-
-            Ok(())
-        })
+        self.handler_table
+            .write(|table| table.register(irq.get(), descriptor))
     }
 
     fn enable(&self, irq: Self::IRQNumberType) {
@@ -159,15 +190,63 @@ impl exception::asynchronous::interface::IRQManager for PeripheralIC {
         &'irq_context self,
         _ic: &exception::asynchronous::IRQContext<'irq_context>,
     ) {
+        let pending_irqs = self.pending_irqs();
+
+        // The GPU IRQ was signaled without a pending peripheral IRQ.
+        if pending_irqs.is_empty() {
+            self.stats.record_spurious();
+            return;
+        }
+
         self.handler_table.read(|table| {
-            for irq_number in self.pending_irqs() {
-                match table[irq_number] {
-                    None => panic!("No handler registered for IRQ {}", irq_number),
-                    Some(descriptor) => {
-                        // Call the IRQ handler. Panics on failure.
-                        descriptor.handler.handle().expect("Error handling IRQ");
-                    }
-                }
+            for irq_number in pending_irqs {
+                // Call the IRQ's handlers. Panics on failure.
+                self.stats
+                    .dispatch(
+                        table,
+                        irq_number,
+                        |descriptor| descriptor.handler.handle(),
+                        || self.disable(irq_number),
+                    )
+                    .expect("Error handling IRQ");
+            }
+        })
+    }
+
+    fn register_fiq_handler(
+        &self,
+        irq: Self::IRQNumberType,
+        descriptor: exception::asynchronous::IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        self.fiq_handler.write(|fiq_handler| {
+            if fiq_handler.is_some() {
+                return Err("FIQ handler already registered");
+            }
+
+            *fiq_handler = Some(descriptor);
+
+            Ok(())
+        })?;
+
+        // Only a single source can be routed to the FIQ at any time.
+        self.wo_registers.lock(|regs| {
+            regs.FIQ_CONTROL
+                .write(FIQ_CONTROL::SOURCE.val(irq.get() as u32) + FIQ_CONTROL::ENABLE::SET);
+        });
+
+        Ok(())
+    }
+
+    fn handle_pending_fiq<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        // There is no pending register for the FIQ. The single FIQ source is the one that fired.
+        self.fiq_handler.read(|fiq_handler| match fiq_handler {
+            None => panic!("No FIQ handler registered"),
+            Some(descriptor) => {
+                // Call the FIQ handler. Panics on failure.
+                descriptor.handler.handle().expect("Error handling FIQ");
             }
         })
     }
@@ -178,11 +257,66 @@ impl exception::asynchronous::interface::IRQManager for PeripheralIC {
         info!("      Peripheral handler:");
 
         self.handler_table.read(|table| {
-            for (i, opt) in table.iter().enumerate() {
-                if let Some(handler) = opt {
-                    info!("            {: >3}. {}", i, handler.name);
-                }
+            for (i, handler) in table.iter() {
+                info!("            {: >3}. {}", i, handler.name);
             }
         });
+
+        self.fiq_handler.read(|fiq_handler| {
+            if let Some(handler) = fiq_handler {
+                info!("      FIQ handler:");
+                info!("                 {}", handler.name);
+            }
+        });
+    }
+
+    fn print_stats(&self) {
+        use crate::info;
+
+        info!("      Peripheral IRQs:");
+
+        self.handler_table.read(|table| self.stats.print(table));
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bsp::device_driver::common::{FakeIRQHandler, FakeMMIO};
+    use exception::asynchronous::{interface::IRQManager, IRQDescriptor};
+    use test_macros::kernel_test;
+
+    const DESCRIPTOR: IRQDescriptor = IRQDescriptor {
+        name: "Test FIQ",
+        handler: &FakeIRQHandler::HANDLED,
+    };
+
+    const FIQ_CONTROL: usize = 0x0c;
+
+    /// Registering the FIQ handler must route its source to the FIQ, and only a single FIQ handler
+    /// is allowed.
+    #[kernel_test]
+    fn fiq_registration_routes_source() {
+        let mut mmio = FakeMMIO::new();
+        let ic = unsafe { PeripheralIC::new(mmio.descriptor()) };
+
+        // A source routed to the FIQ before must be replaced.
+        mmio.set(FIQ_CONTROL, (1 << 7) | 1);
+
+        assert_eq!(
+            ic.register_fiq_handler(PeripheralIRQ::new(57), DESCRIPTOR),
+            Ok(())
+        );
+        assert_eq!(mmio.get(FIQ_CONTROL), (1 << 7) | 57);
+
+        assert_eq!(
+            ic.register_fiq_handler(PeripheralIRQ::new(1), DESCRIPTOR),
+            Err("FIQ handler already registered")
+        );
+        assert_eq!(mmio.get(FIQ_CONTROL), (1 << 7) | 57);
     }
 }
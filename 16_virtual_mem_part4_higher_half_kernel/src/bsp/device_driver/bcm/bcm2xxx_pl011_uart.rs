@@ -11,11 +11,12 @@
 
 use crate::{
     bsp, bsp::device_driver::common::MMIODerefWrapper, console, cpu, driver, exception, memory,
-    synchronization, synchronization::IRQSafeNullLock,
+    synchronization, synchronization::IRQSafeSpinLock,
 };
 use core::{
     fmt,
     sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll, Waker},
 };
 use register::{mmio::*, register_bitfields, register_structs};
 
@@ -228,6 +229,13 @@ pub struct PL011UartInner {
     registers: Registers,
     chars_written: usize,
     chars_read: usize,
+
+    /// The task that awaits the next received character.
+    rx_waker: Option<Waker>,
+
+    /// Received characters belong to an async reader, also between two of its reads. Set when it
+    /// polls and cleared when it cancels a read.
+    async_reader_active: bool,
 }
 
 // Export the inner struct so that BSPs can use it for the panic handler.
@@ -237,7 +245,7 @@ pub use PL011UartInner as PanicUart;
 pub struct PL011Uart {
     mmio_descriptor: memory::mmu::MMIODescriptor,
     virt_mmio_start_addr: AtomicUsize,
-    inner: IRQSafeNullLock<PL011UartInner>,
+    inner: IRQSafeSpinLock<PL011UartInner>,
     irq_number: bsp::device_driver::IRQNumber,
 }
 
@@ -256,6 +264,8 @@ impl PL011UartInner {
             registers: Registers::new(mmio_start_addr),
             chars_written: 0,
             chars_read: 0,
+            rx_waker: None,
+            async_reader_active: false,
         }
     }
 
@@ -318,9 +328,7 @@ impl PL011UartInner {
         self.registers.IFLS.write(IFLS::RXIFLSEL::OneEigth);
 
         // Enable RX IRQ + RX timeout IRQ.
-        self.registers
-            .IMSC
-            .write(IMSC::RXIM::Enabled + IMSC::RTIM::Enabled);
+        self.enable_rx_irqs();
 
         // Turn the UART on.
         self.registers
@@ -330,6 +338,45 @@ impl PL011UartInner {
         Ok(())
     }
 
+    fn enable_rx_irqs(&mut self) {
+        self.registers
+            .IMSC
+            .write(IMSC::RXIM::Enabled + IMSC::RTIM::Enabled);
+    }
+
+    /// Mask the RX IRQs while received characters are left in the FIFO for an async reader.
+    /// Otherwise, the RX timeout IRQ would fire over and over again.
+    fn disable_rx_irqs(&mut self) {
+        self.registers
+            .IMSC
+            .write(IMSC::RXIM::Disabled + IMSC::RTIM::Disabled);
+    }
+
+    /// Take a received character, or store the waker of the task that awaits one.
+    fn poll_read_char(&mut self, cx: &mut Context<'_>) -> Poll<char> {
+        // The IRQ handler might have masked the RX IRQs for the task that polls now.
+        self.async_reader_active = true;
+        self.enable_rx_irqs();
+
+        match self.read_char_converting(BlockingMode::NonBlocking) {
+            Some(c) => {
+                self.rx_waker = None;
+                Poll::Ready(c)
+            }
+            None => {
+                self.rx_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Drop the stored waker, and let the IRQ handler echo received characters again.
+    fn cancel_read(&mut self) {
+        self.rx_waker = None;
+        self.async_reader_active = false;
+        self.enable_rx_irqs();
+    }
+
     /// Send a character.
     fn write_char(&mut self, c: char) {
         // Spin while TX FIFO full is set, waiting for an empty slot.
@@ -414,7 +461,7 @@ impl PL011Uart {
         Self {
             mmio_descriptor,
             virt_mmio_start_addr: AtomicUsize::new(0),
-            inner: IRQSafeNullLock::new(PL011UartInner::new(
+            inner: IRQSafeSpinLock::new(PL011UartInner::new(
                 mmio_descriptor.start_addr().into_usize(),
             )),
             irq_number,
@@ -505,6 +552,16 @@ impl console::interface::Read for PL011Uart {
     }
 }
 
+impl console::interface::AsyncRead for PL011Uart {
+    fn poll_read_char(&self, cx: &mut Context<'_>) -> Poll<char> {
+        self.inner.lock(|inner| inner.poll_read_char(cx))
+    }
+
+    fn cancel_read(&self) {
+        self.inner.lock(|inner| inner.cancel_read())
+    }
+}
+
 impl console::interface::Statistics for PL011Uart {
     fn chars_written(&self) -> usize {
         self.inner.lock(|inner| inner.chars_written)
@@ -516,22 +573,45 @@ impl console::interface::Statistics for PL011Uart {
 }
 
 impl exception::asynchronous::interface::IRQHandler for PL011Uart {
-    fn handle(&self) -> Result<(), &'static str> {
-        self.inner.lock(|inner| {
+    fn handle(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        use exception::asynchronous::IRQReturn;
+
+        let (ret, rx_waker) = self.inner.lock(|inner| {
             let pending = inner.registers.MIS.extract();
 
+            // The interrupt came from another device on a shared line.
+            if pending.get() == 0 {
+                return (IRQReturn::NotHandled, None);
+            }
+
             // Clear all pending IRQs.
             inner.registers.ICR.write(ICR::ALL::CLEAR);
 
             // Check for any kind of RX interrupt.
-            if pending.matches_any(MIS::RXMIS::SET + MIS::RTMIS::SET) {
-                // Echo any received characters.
-                while let Some(c) = inner.read_char_converting(BlockingMode::NonBlocking) {
-                    inner.write_char(c)
-                }
+            if !pending.matches_any(MIS::RXMIS::SET + MIS::RTMIS::SET) {
+                return (IRQReturn::Handled, None);
+            }
+
+            // Leave the received characters in the FIFO for an async reader, if there is one. It
+            // might be between two reads, in which case there is no task to wake.
+            if inner.async_reader_active {
+                inner.disable_rx_irqs();
+                return (IRQReturn::Handled, inner.rx_waker.take());
+            }
+
+            // Echo any received characters.
+            while let Some(c) = inner.read_char_converting(BlockingMode::NonBlocking) {
+                inner.write_char(c)
             }
+
+            (IRQReturn::Handled, None)
         });
 
-        Ok(())
+        // Wake the reader with the driver unlocked.
+        if let Some(waker) = rx_waker {
+            waker.wake();
+        }
+
+        Ok(ret)
     }
 }
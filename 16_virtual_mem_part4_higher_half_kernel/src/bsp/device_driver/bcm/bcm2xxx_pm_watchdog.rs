@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Power Management Watchdog Driver.
+//!
+//! Once started, the watchdog counts down and resets the board when it reaches zero. Petting it
+//! reloads the counter. Rebooting is done by starting the watchdog with a tiny timeout.
+//!
+//! # Resources
+//!
+//! - Linux: drivers/watchdog/bcm2835_wdt.c
+
+use crate::{
+    bsp::device_driver::common::MMIODerefWrapper, cpu, driver, memory, synchronization,
+    synchronization::IRQSafeSpinLock, watchdog,
+};
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use register::{mmio::*, register_bitfields, register_structs};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u32,
+
+    /// Reset Control.
+    PM_RSTC [
+        /// Writes are ignored unless they carry the password.
+        PASSWD OFFSET(24) NUMBITS(8) [
+            Magic = 0x5a
+        ],
+
+        /// Action taken when the watchdog expires.
+        WRCFG OFFSET(4) NUMBITS(2) [
+            Clear = 0b00,
+            FullReset = 0b10
+        ]
+    ],
+
+    /// Reset Status. The firmware reads the boot partition from here after a reset.
+    PM_RSTS [
+        /// Writes are ignored unless they carry the password.
+        PASSWD OFFSET(24) NUMBITS(8) [
+            Magic = 0x5a
+        ],
+
+        /// The partition bits, which are spread across every other bit.
+        PARTITION OFFSET(0) NUMBITS(11) [
+            /// Partition 63 makes the firmware halt instead of booting.
+            Halt = 0x555
+        ]
+    ],
+
+    /// Watchdog Timer.
+    PM_WDOG [
+        /// Writes are ignored unless they carry the password.
+        PASSWD OFFSET(24) NUMBITS(8) [
+            Magic = 0x5a
+        ],
+
+        /// Remaining time, in ticks of 2^-16 seconds.
+        TIME OFFSET(0) NUMBITS(20) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => _reserved1),
+        (0x1c => RSTC: ReadWrite<u32, PM_RSTC::Register>),
+        (0x20 => RSTS: ReadWrite<u32, PM_RSTS::Register>),
+        (0x24 => WDOG: ReadWrite<u32, PM_WDOG::Register>),
+        (0x28 => @END),
+    }
+}
+
+/// Abstraction for the associated MMIO registers.
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// The watchdog counts in ticks of 2^-16 seconds.
+const TICKS_PER_SEC: u128 = 1 << 16;
+
+/// The largest timeout that fits into the 20 bit counter, which is just below 16 seconds.
+const MAX_TICKS: u32 = (1 << 20) - 1;
+
+/// The timeout used for rebooting.
+const REBOOT_TICKS: u32 = 10;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Representation of the power management watchdog.
+pub struct PMWatchdog {
+    mmio_descriptor: memory::mmu::MMIODescriptor,
+    registers: IRQSafeSpinLock<Registers>,
+
+    /// The timeout in watchdog ticks that is reloaded on petting. Zero if stopped.
+    timeout_ticks: AtomicU32,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+impl PMWatchdog {
+    /// Load the counter and arm the full reset on expiry.
+    fn arm(&self, ticks: u32) {
+        self.registers.lock(|regs| {
+            regs.WDOG
+                .write(PM_WDOG::PASSWD::Magic + PM_WDOG::TIME.val(ticks));
+            regs.RSTC
+                .modify(PM_RSTC::PASSWD::Magic + PM_RSTC::WRCFG::FullReset);
+        });
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl PMWatchdog {
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide correct MMIO descriptors.
+    pub const unsafe fn new(mmio_descriptor: memory::mmu::MMIODescriptor) -> Self {
+        Self {
+            mmio_descriptor,
+            registers: IRQSafeSpinLock::new(Registers::new(
+                mmio_descriptor.start_addr().into_usize(),
+            )),
+            timeout_ticks: AtomicU32::new(0),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl driver::interface::DeviceDriver for PMWatchdog {
+    fn compatible(&self) -> &'static str {
+        "BCM Power Management Watchdog"
+    }
+
+    unsafe fn init(&self) -> Result<(), &'static str> {
+        let virt_addr =
+            memory::mmu::kernel_map_mmio(self.compatible(), &self.mmio_descriptor)?.into_usize();
+
+        self.registers
+            .lock(|regs| *regs = Registers::new(virt_addr));
+
+        Ok(())
+    }
+}
+
+impl watchdog::interface::Watchdog for PMWatchdog {
+    fn start(&self, timeout: Duration) -> Result<(), &'static str> {
+        let ticks = (timeout.as_nanos() * TICKS_PER_SEC) / 1_000_000_000;
+
+        if ticks == 0 || ticks > u128::from(MAX_TICKS) {
+            return Err("Watchdog timeout out of range");
+        }
+
+        self.timeout_ticks.store(ticks as u32, Ordering::Relaxed);
+        self.arm(ticks as u32);
+
+        Ok(())
+    }
+
+    fn pet(&self) {
+        let ticks = self.timeout_ticks.load(Ordering::Relaxed);
+        if ticks == 0 {
+            return;
+        }
+
+        self.registers.lock(|regs| {
+            regs.WDOG
+                .write(PM_WDOG::PASSWD::Magic + PM_WDOG::TIME.val(ticks))
+        });
+    }
+
+    fn stop(&self) {
+        self.timeout_ticks.store(0, Ordering::Relaxed);
+
+        self.registers.lock(|regs| {
+            regs.RSTC
+                .modify(PM_RSTC::PASSWD::Magic + PM_RSTC::WRCFG::Clear)
+        });
+    }
+
+    fn reboot(&self) -> ! {
+        self.arm(REBOOT_TICKS);
+
+        cpu::wait_forever()
+    }
+
+    fn halt(&self) -> ! {
+        // The firmware checks the partition after the reset and halts if it is the magic one.
+        self.registers.lock(|regs| {
+            regs.RSTS
+                .modify(PM_RSTS::PASSWD::Magic + PM_RSTS::PARTITION::Halt)
+        });
+
+        self.reboot()
+    }
+}
@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! System Timer Driver.
+//!
+//! A free-running 64-bit counter at 1 MHz with four 32-bit compare channels. Channels 0 and 2 are
+//! used by the GPU firmware, so this driver uses channel 1 for its alarms.
+//!
+//! An expired alarm invokes the registered alarm callback from the IRQ handler. When the system
+//! timer is the kernel's time manager, that callback drives the software timer queue.
+//!
+//! # Resources
+//!
+//! - BCM2835 ARM Peripherals, chapter 12 "System Timer"
+
+use crate::{
+    bsp, bsp::device_driver::common::MMIODerefWrapper, driver, exception, memory, synchronization,
+    synchronization::IRQSafeSpinLock, time, time::Instant,
+};
+use core::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+use register::{mmio::*, register_bitfields, register_structs};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u32,
+
+    /// Control/Status Register. Writing a 1 clears the match flag of a channel.
+    CS [
+        M3 OFFSET(3) NUMBITS(1) [],
+        M2 OFFSET(2) NUMBITS(1) [],
+        M1 OFFSET(1) NUMBITS(1) [],
+        M0 OFFSET(0) NUMBITS(1) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => CS: ReadWrite<u32, CS::Register>),
+        (0x04 => CLO: ReadOnly<u32>),
+        (0x08 => CHI: ReadOnly<u32>),
+        (0x0c => C: [ReadWrite<u32>; 4]),
+        (0x1c => @END),
+    }
+}
+
+/// Abstraction for the associated MMIO registers.
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// The counter frequency is fixed.
+const FREQUENCY_HZ: u64 = 1_000_000;
+
+const NS_PER_TICK: u64 = 1_000_000_000 / FREQUENCY_HZ;
+
+/// The compare channel used for alarms.
+const ALARM_CHANNEL: usize = 1;
+
+/// How far into the future an alarm is moved if its deadline passed while it was programmed.
+const LATE_ALARM_TICKS: u64 = 10;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A function that is called in IRQ context when an alarm expires.
+pub type AlarmCallback = fn();
+
+/// Representation of the system timer.
+///
+/// As a [`time::interface::TimeManager`], it returns [`Instant`]s in units of its own 1 MHz
+/// counter. Unless it is the kernel's time manager, these must not be mixed with the instants of
+/// the kernel's time manager.
+pub struct SystemTimer {
+    mmio_descriptor: memory::mmu::MMIODescriptor,
+    registers: IRQSafeSpinLock<Registers>,
+    irq_number: bsp::device_driver::IRQNumber,
+
+    /// The 64-bit deadline of the armed alarm. Zero if disarmed.
+    alarm_deadline: AtomicU64,
+
+    alarm_callback: IRQSafeSpinLock<Option<AlarmCallback>>,
+    irq_registered: AtomicBool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+/// Read the 64-bit counter, which is split into two registers.
+fn counter(regs: &Registers) -> u64 {
+    loop {
+        let hi = regs.CHI.get();
+        let lo = regs.CLO.get();
+
+        // Retry if the low word wrapped in between.
+        if regs.CHI.get() == hi {
+            return (u64::from(hi) << 32) | u64::from(lo);
+        }
+    }
+}
+
+impl SystemTimer {
+    /// Read the 64-bit counter.
+    fn read_counter(&self) -> u64 {
+        self.registers.lock(|regs| counter(regs))
+    }
+
+    /// Program the compare channel with the low word of the deadline.
+    ///
+    /// The channel only compares 32 bits, so deadlines further away than ~71 minutes fire early
+    /// and are rearmed by the IRQ handler.
+    ///
+    /// A match is only signaled when the counter equals the compare value. If the deadline passed
+    /// before the channel was written, the alarm would therefore only fire after the counter
+    /// wrapped. Such alarms are moved a few ticks into the future instead.
+    fn program_alarm(&self, deadline: u64) {
+        self.registers.lock(|regs| {
+            regs.CS.write(CS::M1::SET);
+
+            let mut compare = deadline;
+            loop {
+                regs.C[ALARM_CHANNEL].set(compare as u32);
+
+                let now = counter(regs);
+                if now < compare || regs.CS.is_set(CS::M1) {
+                    return;
+                }
+
+                compare = now + LATE_ALARM_TICKS;
+            }
+        });
+    }
+
+    /// Register and enable the IRQ handler, unless that was done before.
+    ///
+    /// When the system timer is the kernel's time manager, both the driver manager and the time
+    /// manager interface ask for it.
+    fn register_and_enable_irq_handler_once(&'static self) -> Result<(), &'static str> {
+        use bsp::exception::asynchronous::irq_manager;
+        use exception::asynchronous::{interface::IRQManager, IRQDescriptor};
+
+        if self.irq_registered.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let descriptor = IRQDescriptor {
+            name: "BCM System Timer",
+            handler: self,
+        };
+
+        irq_manager().register_handler(self.irq_number, descriptor)?;
+        irq_manager().enable(self.irq_number);
+        self.irq_registered.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl SystemTimer {
+    /// Create an instance.
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide correct MMIO descriptors.
+    pub const unsafe fn new(
+        mmio_descriptor: memory::mmu::MMIODescriptor,
+        irq_number: bsp::device_driver::IRQNumber,
+    ) -> Self {
+        Self {
+            mmio_descriptor,
+            registers: IRQSafeSpinLock::new(Registers::new(
+                mmio_descriptor.start_addr().into_usize(),
+            )),
+            irq_number,
+            alarm_deadline: AtomicU64::new(0),
+            alarm_callback: IRQSafeSpinLock::new(None),
+            irq_registered: AtomicBool::new(false),
+        }
+    }
+
+    /// Set the function that is called when an alarm expires.
+    pub fn set_alarm_callback(&self, callback: AlarmCallback) {
+        self.alarm_callback.lock(|x| *x = Some(callback));
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl driver::interface::DeviceDriver for SystemTimer {
+    fn compatible(&self) -> &'static str {
+        "BCM System Timer"
+    }
+
+    unsafe fn init(&self) -> Result<(), &'static str> {
+        let virt_addr =
+            memory::mmu::kernel_map_mmio(self.compatible(), &self.mmio_descriptor)?.into_usize();
+
+        self.registers
+            .lock(|regs| *regs = Registers::new(virt_addr));
+
+        Ok(())
+    }
+
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        self.register_and_enable_irq_handler_once()
+    }
+}
+
+impl time::interface::TimeManager for SystemTimer {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(NS_PER_TICK)
+    }
+
+    fn uptime(&self) -> Duration {
+        self.ticks_to_duration(self.read_counter())
+    }
+
+    fn now(&self) -> Instant {
+        Instant::from_counter_ticks(self.read_counter())
+    }
+
+    fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        Duration::from_micros(ticks)
+    }
+
+    fn duration_to_ticks(&self, duration: Duration) -> u64 {
+        // Round up, so that waiting never ends early.
+        let ticks = (duration.as_nanos() + u128::from(NS_PER_TICK - 1)) / u128::from(NS_PER_TICK);
+
+        if ticks > u128::from(u64::MAX) {
+            return u64::MAX;
+        }
+
+        ticks as u64
+    }
+
+    fn spin_for(&self, duration: Duration) {
+        let deadline = self
+            .read_counter()
+            .saturating_add(self.duration_to_ticks(duration));
+
+        while self.read_counter() < deadline {}
+    }
+
+    fn wait_for(&self, duration: Duration) {
+        // There is no event stream to wake up from a low-power state.
+        self.spin_for(duration)
+    }
+
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        // Expired alarms drive the software timer queue, like those of the architectural timer.
+        self.set_alarm_callback(time::timer_queue::handle_expired);
+
+        self.register_and_enable_irq_handler_once()
+    }
+
+    fn set_alarm(&self, deadline: Option<Instant>) {
+        match deadline {
+            None => {
+                self.alarm_deadline.store(0, Ordering::Relaxed);
+                self.registers.lock(|regs| regs.CS.write(CS::M1::SET));
+            }
+            Some(x) => {
+                let deadline = x.counter_ticks().max(1);
+
+                self.alarm_deadline.store(deadline, Ordering::Relaxed);
+                self.program_alarm(deadline);
+            }
+        }
+    }
+}
+
+impl exception::asynchronous::interface::IRQHandler for SystemTimer {
+    fn handle(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        use exception::asynchronous::IRQReturn;
+
+        // Acknowledge the match, if there is one.
+        let matched = self.registers.lock(|regs| {
+            if !regs.CS.is_set(CS::M1) {
+                return false;
+            }
+
+            regs.CS.write(CS::M1::SET);
+            true
+        });
+
+        if !matched {
+            return Ok(IRQReturn::NotHandled);
+        }
+
+        let deadline = self.alarm_deadline.load(Ordering::Relaxed);
+        if deadline == 0 {
+            return Ok(IRQReturn::Handled);
+        }
+
+        // Only the low word was compared. Rearm if the full deadline has not been reached yet.
+        if self.read_counter() < deadline {
+            self.program_alarm(deadline);
+            return Ok(IRQReturn::Handled);
+        }
+
+        self.alarm_deadline.store(0, Ordering::Relaxed);
+
+        // Invoked without holding locks, so that the callback is free to set the next alarm.
+        if let Some(callback) = self.alarm_callback.lock(|x| *x) {
+            callback();
+        }
+
+        Ok(IRQReturn::Handled)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bsp::device_driver::common::FakeMMIO;
+    use exception::asynchronous::{interface::IRQHandler, IRQReturn};
+    use test_macros::kernel_test;
+    use time::interface::TimeManager;
+
+    const CS: usize = 0x00;
+    const CLO: usize = 0x04;
+    const C1: usize = 0x10;
+
+    static ALARM_FIRED: AtomicBool = AtomicBool::new(false);
+
+    fn alarm_fired() {
+        ALARM_FIRED.store(true, Ordering::Relaxed);
+    }
+
+    /// An armed alarm must invoke the alarm callback once its deadline is reached, and not before.
+    #[kernel_test]
+    fn armed_alarm_fires_callback() {
+        let mut mmio = FakeMMIO::new();
+        let timer = unsafe {
+            SystemTimer::new(
+                mmio.descriptor(),
+                bsp::exception::asynchronous::irq_map::SYSTEM_TIMER,
+            )
+        };
+        timer.set_alarm_callback(alarm_fired);
+
+        mmio.set(CLO, 1000);
+        timer.set_alarm(Some(Instant::from_counter_ticks(1005)));
+        assert_eq!(mmio.get(C1), 1005);
+
+        // No match signaled yet.
+        mmio.set(CS, 0);
+        assert_eq!(timer.handle(), Ok(IRQReturn::NotHandled));
+        assert!(!ALARM_FIRED.load(Ordering::Relaxed));
+
+        mmio.set(CLO, 1005);
+        mmio.set(CS, 1 << ALARM_CHANNEL);
+        assert_eq!(timer.handle(), Ok(IRQReturn::Handled));
+        assert!(ALARM_FIRED.load(Ordering::Relaxed));
+    }
+}
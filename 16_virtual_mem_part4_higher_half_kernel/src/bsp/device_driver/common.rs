@@ -6,6 +6,9 @@
 
 use core::{marker::PhantomData, ops};
 
+#[cfg(test)]
+use crate::exception;
+
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
 //--------------------------------------------------------------------------------------------------
@@ -36,3 +39,60 @@ impl<T> ops::Deref for MMIODerefWrapper<T> {
         unsafe { &*(self.start_addr as *const _) }
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+/// A page of memory that stands in for a driver's MMIO registers in unit tests.
+#[cfg(test)]
+#[repr(align(4096))]
+pub struct FakeMMIO([u32; 1024]);
+
+#[cfg(test)]
+impl FakeMMIO {
+    /// Create an instance with all registers zeroed.
+    pub const fn new() -> Self {
+        Self([0; 1024])
+    }
+
+    /// Return an MMIO descriptor that covers the fake registers.
+    pub fn descriptor(&mut self) -> crate::memory::mmu::MMIODescriptor {
+        use crate::memory::{mmu::MMIODescriptor, Address};
+
+        MMIODescriptor::new(
+            Address::new(self.0.as_mut_ptr() as usize),
+            core::mem::size_of::<Self>(),
+        )
+    }
+
+    /// Read the register at byte offset `offset`.
+    pub fn get(&self, offset: usize) -> u32 {
+        unsafe { core::ptr::read_volatile(&self.0[offset / 4]) }
+    }
+
+    /// Write the register at byte offset `offset`.
+    pub fn set(&mut self, offset: usize, value: u32) {
+        unsafe { core::ptr::write_volatile(&mut self.0[offset / 4], value) }
+    }
+}
+
+/// An IRQ handler for unit tests that answers every IRQ with the same result.
+#[cfg(test)]
+pub struct FakeIRQHandler(exception::asynchronous::IRQReturn);
+
+#[cfg(test)]
+impl FakeIRQHandler {
+    /// Services every IRQ.
+    pub const HANDLED: Self = Self(exception::asynchronous::IRQReturn::Handled);
+
+    /// Services no IRQ.
+    pub const NOT_HANDLED: Self = Self(exception::asynchronous::IRQReturn::NotHandled);
+}
+
+#[cfg(test)]
+impl exception::asynchronous::interface::IRQHandler for FakeIRQHandler {
+    fn handle(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        Ok(self.0)
+    }
+}
@@ -11,7 +11,7 @@ pub mod exception;
 pub mod memory;
 
 use super::device_driver;
-use crate::memory::mmu::MMIODescriptor;
+use crate::{memory::mmu::MMIODescriptor, time, watchdog};
 use memory::map::mmio;
 
 //--------------------------------------------------------------------------------------------------
@@ -28,6 +28,20 @@ static PL011_UART: device_driver::PL011Uart = unsafe {
     )
 };
 
+static SYSTEM_TIMER: device_driver::SystemTimer = unsafe {
+    device_driver::SystemTimer::new(
+        MMIODescriptor::new(mmio::SYSTEM_TIMER_START, mmio::SYSTEM_TIMER_SIZE),
+        exception::asynchronous::irq_map::SYSTEM_TIMER,
+    )
+};
+
+static PM_WATCHDOG: device_driver::PMWatchdog = unsafe {
+    device_driver::PMWatchdog::new(MMIODescriptor::new(
+        mmio::PM_WATCHDOG_START,
+        mmio::PM_WATCHDOG_SIZE,
+    ))
+};
+
 #[cfg(feature = "bsp_rpi3")]
 static INTERRUPT_CONTROLLER: device_driver::InterruptController = unsafe {
     device_driver::InterruptController::new(
@@ -44,6 +58,13 @@ static INTERRUPT_CONTROLLER: device_driver::GICv2 = unsafe {
     )
 };
 
+static AUX_INTERRUPT_CONTROLLER: device_driver::AuxInterruptController = unsafe {
+    device_driver::AuxInterruptController::new(
+        MMIODescriptor::new(mmio::AUX_IC_START, mmio::AUX_IC_SIZE),
+        exception::asynchronous::irq_map::AUX,
+    )
+};
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
@@ -60,3 +81,16 @@ pub fn board_name() -> &'static str {
         "Raspberry Pi 4"
     }
 }
+
+/// Return a reference to the BCM system timer.
+///
+/// An alternative time manager for cross-checking the architectural timer. With the
+/// `system_timer_backend` feature, it is the kernel's time manager.
+pub fn system_timer() -> &'static impl time::interface::TimeManager {
+    &SYSTEM_TIMER
+}
+
+/// Return a reference to the power management watchdog.
+pub fn watchdog() -> &'static impl watchdog::interface::Watchdog {
+    &PM_WATCHDOG
+}
@@ -4,6 +4,10 @@
 
 //! BSP Processor code.
 
+use super::memory::map;
+use crate::memory::{self, mmu::MMIODescriptor, Address, Physical};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
 //--------------------------------------------------------------------------------------------------
@@ -12,3 +16,55 @@
 #[no_mangle]
 #[link_section = ".text._start_arguments"]
 pub static BOOT_CORE_ID: u64 = 0;
+
+/// The number of cores on the board.
+pub const NUM_CORES: usize = 4;
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// Virtual address of the remapped spin table. Zero until mapped.
+static SPIN_TABLE_VIRT_ADDR: AtomicUsize = AtomicUsize::new(0);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Map the spin table through which the secondary cores are released.
+///
+/// # Safety
+///
+/// - Same as `memory::mmu::kernel_map_mmio()`.
+pub unsafe fn map_spin_table() -> Result<(), &'static str> {
+    let virt_addr = memory::mmu::kernel_map_mmio(
+        "Spin table",
+        &MMIODescriptor::new(map::SPIN_TABLE_START, map::SPIN_TABLE_SIZE),
+    )?;
+
+    SPIN_TABLE_VIRT_ADDR.store(virt_addr.into_usize(), Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// Write the entry address of a secondary core into its spin table slot.
+///
+/// The core only starts executing after it has been woken up with an event.
+pub fn release_secondary_core(
+    core_id: usize,
+    phys_entry_addr: Address<Physical>,
+) -> Result<(), &'static str> {
+    let spin_table = SPIN_TABLE_VIRT_ADDR.load(Ordering::Relaxed);
+    if spin_table == 0 {
+        return Err("Spin table not mapped");
+    }
+
+    if core_id as u64 == BOOT_CORE_ID || core_id >= NUM_CORES {
+        return Err("Not a secondary core");
+    }
+
+    let slot = (spin_table + core_id * 8) as *mut u64;
+    unsafe { core::ptr::write_volatile(slot, phys_entry_addr.into_usize() as u64) };
+
+    Ok(())
+}
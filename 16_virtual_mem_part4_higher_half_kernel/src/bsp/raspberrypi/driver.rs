@@ -10,20 +10,45 @@ use crate::driver;
 // Private Definitions
 //--------------------------------------------------------------------------------------------------
 
+/// The number of drivers at the start of the driver list that are needed for printing.
+#[cfg(not(feature = "system_timer_backend"))]
+const NUM_EARLY_PRINT_DEVICE_DRIVERS: usize = 2;
+
+#[cfg(feature = "system_timer_backend")]
+const NUM_EARLY_PRINT_DEVICE_DRIVERS: usize = 3;
+
 /// Device Driver Manager type.
 struct BSPDriverManager {
-    device_drivers: [&'static (dyn DeviceDriver + Sync); 3],
+    device_drivers: [&'static (dyn DeviceDriver + Sync); 6],
 }
 
 //--------------------------------------------------------------------------------------------------
 // Global instances
 //--------------------------------------------------------------------------------------------------
 
+#[cfg(not(feature = "system_timer_backend"))]
+static BSP_DRIVER_MANAGER: BSPDriverManager = BSPDriverManager {
+    device_drivers: [
+        &super::GPIO,
+        &super::PL011_UART,
+        &super::INTERRUPT_CONTROLLER,
+        &super::AUX_INTERRUPT_CONTROLLER,
+        &super::SYSTEM_TIMER,
+        &super::PM_WATCHDOG,
+    ],
+};
+
+/// Log timestamps are read from the time manager, so the system timer is brought up before the
+/// console when it is the time manager.
+#[cfg(feature = "system_timer_backend")]
 static BSP_DRIVER_MANAGER: BSPDriverManager = BSPDriverManager {
     device_drivers: [
+        &super::SYSTEM_TIMER,
         &super::GPIO,
         &super::PL011_UART,
         &super::INTERRUPT_CONTROLLER,
+        &super::AUX_INTERRUPT_CONTROLLER,
+        &super::PM_WATCHDOG,
     ],
 };
 
@@ -47,11 +72,11 @@ impl driver::interface::DriverManager for BSPDriverManager {
     }
 
     fn early_print_device_drivers(&self) -> &[&'static (dyn DeviceDriver + Sync)] {
-        &self.device_drivers[0..=1]
+        &self.device_drivers[0..NUM_EARLY_PRINT_DEVICE_DRIVERS]
     }
 
     fn non_early_print_device_drivers(&self) -> &[&'static (dyn DeviceDriver + Sync)] {
-        &self.device_drivers[2..]
+        &self.device_drivers[NUM_EARLY_PRINT_DEVICE_DRIVERS..]
     }
 
     fn post_early_print_device_driver_init(&self) {
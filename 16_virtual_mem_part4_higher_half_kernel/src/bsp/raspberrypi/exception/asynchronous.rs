@@ -12,16 +12,24 @@ use crate::{bsp, exception};
 
 #[cfg(feature = "bsp_rpi3")]
 pub(in crate::bsp) mod irq_map {
-    use super::bsp::device_driver::{IRQNumber, PeripheralIRQ};
+    use super::bsp::device_driver::{IRQNumber, LocalIRQ, PeripheralIRQ};
 
+    pub const ARCH_TIMER: IRQNumber = IRQNumber::Local(LocalIRQ::new(1));
+    pub const AUX: IRQNumber = IRQNumber::Peripheral(PeripheralIRQ::new(29));
+    pub const IPI: IRQNumber = IRQNumber::Local(LocalIRQ::new(4));
     pub const PL011_UART: IRQNumber = IRQNumber::Peripheral(PeripheralIRQ::new(57));
+    pub const SYSTEM_TIMER: IRQNumber = IRQNumber::Peripheral(PeripheralIRQ::new(1));
 }
 
 #[cfg(feature = "bsp_rpi4")]
 pub(in crate::bsp) mod irq_map {
     use super::bsp::device_driver::IRQNumber;
 
+    pub const ARCH_TIMER: IRQNumber = IRQNumber::new(30);
+    pub const AUX: IRQNumber = IRQNumber::new(125);
+    pub const IPI: IRQNumber = IRQNumber::new(0);
     pub const PL011_UART: IRQNumber = IRQNumber::new(153);
+    pub const SYSTEM_TIMER: IRQNumber = IRQNumber::new(97);
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -34,3 +42,24 @@ pub fn irq_manager() -> &'static impl exception::asynchronous::interface::IRQMan
 > {
     &super::super::INTERRUPT_CONTROLLER
 }
+
+/// Return a reference to the AUX interrupt controller, which is chained beneath the IRQ manager.
+///
+/// Drivers of the auxiliary peripherals register their handlers here.
+pub fn aux_irq_manager() -> &'static impl exception::asynchronous::interface::IRQManager<
+    IRQNumberType = bsp::device_driver::AuxIRQ,
+> {
+    &super::super::AUX_INTERRUPT_CONTROLLER
+}
+
+/// Return the IRQ number of the ARM generic timer's EL1 physical timer.
+pub fn arch_timer_irq() -> bsp::device_driver::IRQNumber {
+    irq_map::ARCH_TIMER
+}
+
+/// Return the IRQ number used for inter-processor interrupts.
+///
+/// Mailbox 0 of the local interrupt controller on the RPi3, SGI 0 on the RPi4.
+pub fn ipi_irq() -> bsp::device_driver::IRQNumber {
+    irq_map::IPI
+}
@@ -16,6 +16,7 @@
 //! | .text                                       |
 //! | .rodata                                     |
 //! | .got                                        |
+//! | .exception_fixup                            |
 //! |                                             | rx_end_inclusive
 //! +---------------------------------------------+
 //! |                                             | rw_start == rx_end
@@ -33,11 +34,23 @@
 //! |                                             |                                | direction
 //! |                                             | boot_core_stack_end_inclusive  |
 //! +---------------------------------------------+
+//! |                                             | secondary_core_stacks_start
+//! | Unmapped Core 1 Stack Guard Page            |
+//! | Core 1 Stack                                |
+//! | Unmapped Core 2 Stack Guard Page            |
+//! | Core 2 Stack                                |
+//! | Unmapped Core 3 Stack Guard Page            |
+//! | Core 3 Stack                                |
+//! |                                             | secondary_core_stacks_end_inclusive
+//! +---------------------------------------------+
 
 pub mod mmu;
 
-use crate::memory::{Address, Physical, Virtual};
-use core::{cell::UnsafeCell, ops::RangeInclusive};
+use crate::{
+    exception::fixup::FixupEntry,
+    memory::{Address, Physical, Virtual},
+};
+use core::{cell::UnsafeCell, mem::size_of, ops::RangeInclusive, slice};
 
 //--------------------------------------------------------------------------------------------------
 // Private Definitions
@@ -48,6 +61,9 @@ extern "Rust" {
     static __rx_start: UnsafeCell<()>;
     static __rx_end_exclusive: UnsafeCell<()>;
 
+    static __exception_fixup_start: UnsafeCell<FixupEntry>;
+    static __exception_fixup_end_exclusive: UnsafeCell<FixupEntry>;
+
     static __rw_start: UnsafeCell<()>;
     static __bss_start: UnsafeCell<u64>;
     static __bss_end_inclusive: UnsafeCell<u64>;
@@ -58,6 +74,9 @@ extern "Rust" {
 
     static __boot_core_stack_guard_page_start: UnsafeCell<()>;
     static __boot_core_stack_guard_page_end_exclusive: UnsafeCell<()>;
+
+    static __secondary_core_stacks_start: UnsafeCell<()>;
+    static __secondary_core_stacks_end_exclusive: UnsafeCell<()>;
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -69,20 +88,33 @@ extern "Rust" {
 pub(super) mod map {
     use super::*;
 
+    /// The firmware's spin table. Parked secondary cores poll their slot for an entry address.
+    pub const SPIN_TABLE_START: Address<Physical> = Address::new(0xD8);
+    pub const SPIN_TABLE_SIZE:  usize             =              0x20;
+
     /// Physical devices.
     #[cfg(feature = "bsp_rpi3")]
     pub mod mmio {
         use super::*;
 
+        pub const SYSTEM_TIMER_START:  Address<Physical> = Address::new(0x3F00_3000);
+        pub const SYSTEM_TIMER_SIZE:   usize             =              0x1C;
+
         pub const PERIPHERAL_IC_START: Address<Physical> = Address::new(0x3F00_B200);
         pub const PERIPHERAL_IC_SIZE:  usize             =              0x24;
 
+        pub const PM_WATCHDOG_START:   Address<Physical> = Address::new(0x3F10_0000);
+        pub const PM_WATCHDOG_SIZE:    usize             =              0x28;
+
         pub const GPIO_START:          Address<Physical> = Address::new(0x3F20_0000);
         pub const GPIO_SIZE:           usize             =              0xA0;
 
         pub const PL011_UART_START:    Address<Physical> = Address::new(0x3F20_1000);
         pub const PL011_UART_SIZE:     usize             =              0x48;
 
+        pub const AUX_IC_START:        Address<Physical> = Address::new(0x3F21_5000);
+        pub const AUX_IC_SIZE:         usize             =              0x4;
+
         pub const LOCAL_IC_START:      Address<Physical> = Address::new(0x4000_0000);
         pub const LOCAL_IC_SIZE:       usize             =              0x100;
 
@@ -94,19 +126,28 @@ pub(super) mod map {
     pub mod mmio {
         use super::*;
 
-        pub const GPIO_START:       Address<Physical> = Address::new(0xFE20_0000);
-        pub const GPIO_SIZE:        usize             =              0xA0;
+        pub const SYSTEM_TIMER_START: Address<Physical> = Address::new(0xFE00_3000);
+        pub const SYSTEM_TIMER_SIZE:  usize             =              0x1C;
+
+        pub const PM_WATCHDOG_START:  Address<Physical> = Address::new(0xFE10_0000);
+        pub const PM_WATCHDOG_SIZE:   usize             =              0x28;
+
+        pub const GPIO_START:         Address<Physical> = Address::new(0xFE20_0000);
+        pub const GPIO_SIZE:          usize             =              0xA0;
 
-        pub const PL011_UART_START: Address<Physical> = Address::new(0xFE20_1000);
-        pub const PL011_UART_SIZE:  usize             =              0x48;
+        pub const PL011_UART_START:   Address<Physical> = Address::new(0xFE20_1000);
+        pub const PL011_UART_SIZE:    usize             =              0x48;
 
-        pub const GICD_START:       Address<Physical> = Address::new(0xFF84_1000);
-        pub const GICD_SIZE:        usize             =              0x824;
+        pub const AUX_IC_START:       Address<Physical> = Address::new(0xFE21_5000);
+        pub const AUX_IC_SIZE:        usize             =              0x4;
 
-        pub const GICC_START:       Address<Physical> = Address::new(0xFF84_2000);
-        pub const GICC_SIZE:        usize             =              0x14;
+        pub const GICD_START:         Address<Physical> = Address::new(0xFF84_1000);
+        pub const GICD_SIZE:          usize             =              0xF04;
 
-        pub const END:              Address<Physical> = Address::new(0xFF85_0000);
+        pub const GICC_START:         Address<Physical> = Address::new(0xFF84_2000);
+        pub const GICC_SIZE:          usize             =              0x14;
+
+        pub const END:                Address<Physical> = Address::new(0xFF85_0000);
     }
 
     pub const END: Address<Physical> = mmio::END;
@@ -181,6 +222,38 @@ fn boot_core_stack_guard_page_size() -> usize {
     }
 }
 
+/// Distance between the starts of two consecutive secondary core stack slots. Each slot consists of
+/// a guard page followed by the stack.
+#[inline(always)]
+fn secondary_core_stack_stride() -> usize {
+    let size = unsafe {
+        (__secondary_core_stacks_end_exclusive.get() as usize)
+            - (__secondary_core_stacks_start.get() as usize)
+    };
+
+    size / (super::cpu::NUM_CORES - 1)
+}
+
+/// Start address of a secondary core's stack guard page.
+#[inline(always)]
+fn virt_secondary_core_stack_guard_page_start(core_id: usize) -> Address<Virtual> {
+    let start = unsafe { __secondary_core_stacks_start.get() as usize };
+
+    Address::new(start + (core_id - 1) * secondary_core_stack_stride())
+}
+
+/// Start address of a secondary core's stack.
+#[inline(always)]
+fn virt_secondary_core_stack_start(core_id: usize) -> Address<Virtual> {
+    virt_secondary_core_stack_guard_page_start(core_id) + mmu::KernelGranule::SIZE
+}
+
+/// Size of a secondary core's stack.
+#[inline(always)]
+fn secondary_core_stack_size() -> usize {
+    secondary_core_stack_stride() - mmu::KernelGranule::SIZE
+}
+
 /// Exclusive end address of the physical address space.
 #[inline(always)]
 fn phys_addr_space_end() -> Address<Physical> {
@@ -206,3 +279,19 @@ pub fn bss_range_inclusive() -> RangeInclusive<*mut u64> {
 
     range
 }
+
+/// Return the exception fixup table.
+///
+/// # Safety
+///
+/// - Values are provided by the linker script and must be trusted as-is.
+/// - The linker-provided addresses must be `FixupEntry` aligned.
+pub fn exception_fixup_table() -> &'static [FixupEntry] {
+    unsafe {
+        let start = __exception_fixup_start.get() as usize;
+        let end_exclusive = __exception_fixup_end_exclusive.get() as usize;
+        let num_entries = (end_exclusive - start) / size_of::<FixupEntry>();
+
+        slice::from_raw_parts(start as *const FixupEntry, num_entries)
+    }
+}
@@ -102,6 +102,13 @@ fn virt_boot_core_stack_page_desc() -> PageSliceDescriptor<Virtual> {
     PageSliceDescriptor::from_addr(super::virt_boot_core_stack_start(), num_pages)
 }
 
+/// A secondary core's stack.
+fn virt_secondary_core_stack_page_desc(core_id: usize) -> PageSliceDescriptor<Virtual> {
+    let num_pages = size_to_num_pages(super::secondary_core_stack_size());
+
+    PageSliceDescriptor::from_addr(super::virt_secondary_core_stack_start(core_id), num_pages)
+}
+
 // There is no reason to expect the following conversions to fail, since they were generated offline
 // by the `translation table tool`. If it doesn't work, a panic due to the unwrap is justified.
 
@@ -120,6 +127,13 @@ fn phys_boot_core_stack_page_desc() -> PageSliceDescriptor<Physical> {
     virt_boot_core_stack_page_desc().try_into().unwrap()
 }
 
+/// A secondary core's stack.
+fn phys_secondary_core_stack_page_desc(core_id: usize) -> PageSliceDescriptor<Physical> {
+    virt_secondary_core_stack_page_desc(core_id)
+        .try_into()
+        .unwrap()
+}
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
@@ -136,6 +150,14 @@ pub fn virt_boot_core_stack_guard_page_desc() -> PageSliceDescriptor<Virtual> {
     PageSliceDescriptor::from_addr(super::virt_boot_core_stack_guard_page_start(), num_pages)
 }
 
+/// A secondary core's stack guard page.
+pub fn virt_secondary_core_stack_guard_page_desc(core_id: usize) -> PageSliceDescriptor<Virtual> {
+    PageSliceDescriptor::from_addr(
+        super::virt_secondary_core_stack_guard_page_start(core_id),
+        1,
+    )
+}
+
 /// Pointer to the last page of the physical address space.
 pub fn phys_addr_space_end_page() -> *const Page<Physical> {
     common::align_down(
@@ -184,4 +206,25 @@ pub fn kernel_add_mapping_records_for_precomputed() {
             execute_never: true,
         },
     );
+
+    const SECONDARY_CORE_STACK_NAMES: [&str; 3] = [
+        "Kernel core 1 stack",
+        "Kernel core 2 stack",
+        "Kernel core 3 stack",
+    ];
+
+    for (i, name) in SECONDARY_CORE_STACK_NAMES.iter().enumerate() {
+        let core_id = i + 1;
+
+        generic_mmu::kernel_add_mapping_record(
+            *name,
+            &virt_secondary_core_stack_page_desc(core_id),
+            &phys_secondary_core_stack_page_desc(core_id),
+            &AttributeFields {
+                mem_attributes: MemAttributes::CacheableDRAM,
+                acc_perms: AccessPermissions::ReadWrite,
+                execute_never: true,
+            },
+        );
+    }
 }
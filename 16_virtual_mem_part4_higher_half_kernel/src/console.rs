@@ -4,13 +4,22 @@
 
 //! System console.
 
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
 //--------------------------------------------------------------------------------------------------
 
 /// Console interfaces.
 pub mod interface {
-    use core::fmt;
+    use core::{
+        fmt,
+        task::{Context, Poll},
+    };
 
     /// Console write functions.
     pub trait Write {
@@ -35,6 +44,25 @@ pub mod interface {
         fn clear_rx(&self);
     }
 
+    /// Console read functions for async code.
+    pub trait AsyncRead {
+        /// Take a received character, or arrange for the task in `cx` to be woken once one
+        /// arrives.
+        fn poll_read_char(&self, cx: &mut Context<'_>) -> Poll<char>;
+
+        /// Forget the task that [`poll_read_char()`](AsyncRead::poll_read_char) arranged to wake,
+        /// because it stopped waiting for a character.
+        fn cancel_read(&self);
+
+        /// A future that resolves to the next received character.
+        fn read_char_async(&self) -> super::ReadChar<'_, Self> {
+            super::ReadChar {
+                console: self,
+                pending: false,
+            }
+        }
+    }
+
     /// Console statistics.
     pub trait Statistics {
         /// Return the number of characters written.
@@ -49,5 +77,136 @@ pub mod interface {
     }
 
     /// Trait alias for a full-fledged console.
-    pub trait All = Write + Read + Statistics;
+    pub trait All = Write + Read + AsyncRead + Statistics;
+}
+
+/// A future that resolves to the next character received by a console.
+///
+/// Dropping the future while it is pending cancels the read.
+pub struct ReadChar<'a, T: interface::AsyncRead + ?Sized> {
+    console: &'a T,
+
+    /// Whether the console will wake the task that polled last.
+    pending: bool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Read a line from the console into `buf` and echo it. Returns the length of the line.
+///
+/// The line ends at a newline, which is not stored. Non-ASCII characters and characters that do
+/// not fit into `buf` are dropped, so the line is always valid UTF-8.
+pub async fn read_line<T>(console: &T, buf: &mut [u8]) -> usize
+where
+    T: interface::AsyncRead + interface::Write + ?Sized,
+{
+    let mut len = 0;
+
+    loop {
+        let c = console.read_char_async().await;
+        console.write_char(c);
+
+        if c == '\n' {
+            break;
+        }
+
+        if c.is_ascii() && len < buf.len() {
+            buf[len] = c as u8;
+            len += 1;
+        }
+    }
+
+    len
+}
+
+impl<T: interface::AsyncRead + ?Sized> Future for ReadChar<'_, T> {
+    type Output = char;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<char> {
+        let this = self.get_mut();
+        let ret = this.console.poll_read_char(cx);
+
+        this.pending = ret.is_pending();
+        ret
+    }
+}
+
+impl<T: interface::AsyncRead + ?Sized> Drop for ReadChar<'_, T> {
+    fn drop(&mut self) {
+        if self.pending {
+            self.console.cancel_read();
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{
+        cell::Cell,
+        ptr,
+        task::{RawWaker, RawWakerVTable, Waker},
+    };
+    use interface::AsyncRead;
+    use test_macros::kernel_test;
+
+    /// A console that receives the characters put into `input`.
+    struct FakeConsole {
+        input: Cell<Option<char>>,
+        num_cancels: Cell<usize>,
+    }
+
+    impl AsyncRead for FakeConsole {
+        fn poll_read_char(&self, _cx: &mut Context<'_>) -> Poll<char> {
+            match self.input.take() {
+                Some(c) => Poll::Ready(c),
+                None => Poll::Pending,
+            }
+        }
+
+        fn cancel_read(&self) {
+            self.num_cancels.set(self.num_cancels.get() + 1);
+        }
+    }
+
+    static NOOP_WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
+        noop_waker_clone,
+        noop_waker_fn,
+        noop_waker_fn,
+        noop_waker_fn,
+    );
+
+    unsafe fn noop_waker_clone(_data: *const ()) -> RawWaker {
+        RawWaker::new(ptr::null(), &NOOP_WAKER_VTABLE)
+    }
+
+    unsafe fn noop_waker_fn(_data: *const ()) {}
+
+    /// Dropping a pending read must cancel it, while dropping a completed read must not.
+    #[kernel_test]
+    fn read_char_cancels_pending_read_on_drop() {
+        let console = FakeConsole {
+            input: Cell::new(None),
+            num_cancels: Cell::new(0),
+        };
+        let waker = unsafe { Waker::from_raw(noop_waker_clone(ptr::null())) };
+        let mut cx = Context::from_waker(&waker);
+
+        let mut read = console.read_char_async();
+        assert_eq!(Pin::new(&mut read).poll(&mut cx), Poll::Pending);
+        drop(read);
+        assert_eq!(console.num_cancels.get(), 1);
+
+        console.input.set(Some('x'));
+        let mut read = console.read_char_async();
+        assert_eq!(Pin::new(&mut read).poll(&mut cx), Poll::Ready('x'));
+        drop(read);
+        assert_eq!(console.num_cancels.get(), 1);
+    }
 }
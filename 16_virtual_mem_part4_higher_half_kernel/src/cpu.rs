@@ -10,12 +10,14 @@ mod arch_cpu;
 
 mod boot;
 
+pub mod debug;
+pub mod percpu;
 pub mod smp;
 
 //--------------------------------------------------------------------------------------------------
 // Architectural Public Reexports
 //--------------------------------------------------------------------------------------------------
-pub use arch_cpu::{nop, wait_forever};
+pub use arch_cpu::{nop, wait_for_event, wait_for_interrupt, wait_forever};
 
 #[cfg(feature = "test_build")]
 pub use arch_cpu::{qemu_exit_failure, qemu_exit_success};
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Self-hosted debug.
+//!
+//! Hardware breakpoints and watchpoints that are programmed and handled by the kernel itself,
+//! without the need for an external debugger. A triggered breakpoint or watchpoint either invokes
+//! the callback that was supplied when it was set, or prints the exception context.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "../_arch/aarch64/cpu/debug.rs"]
+mod arch_debug;
+
+use crate::memory::{Address, Virtual};
+use core::fmt;
+
+//--------------------------------------------------------------------------------------------------
+// Architectural Public Reexports
+//--------------------------------------------------------------------------------------------------
+pub use arch_debug::{
+    clear_breakpoint, clear_watchpoint, handle_debug_exception, num_breakpoints, num_watchpoints,
+    secondary_core_init, set_breakpoint, set_watchpoint,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The kind of memory access a watchpoint triggers on.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WatchpointKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// The kind of debug event that occurred.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DebugEventKind {
+    Breakpoint,
+    Watchpoint,
+}
+
+/// Information about a triggered breakpoint or watchpoint.
+#[derive(Copy, Clone)]
+pub struct DebugEvent {
+    /// The kind of the event.
+    pub kind: DebugEventKind,
+
+    /// The hardware slot that triggered.
+    pub slot: usize,
+
+    /// The breakpoint address, or the data address that was accessed in case of a watchpoint.
+    pub addr: Address<Virtual>,
+
+    /// The virtual address of the instruction that triggered the event.
+    pub instruction_addr: Address<Virtual>,
+}
+
+/// A function that is called in exception context when a breakpoint or watchpoint triggers.
+pub type DebugCallback = fn(&DebugEvent);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl fmt::Display for DebugEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            DebugEventKind::Breakpoint => "Breakpoint",
+            DebugEventKind::Watchpoint => "Watchpoint",
+        };
+
+        write!(
+            f,
+            "{} {} hit: address {}, instruction {}",
+            kind, self.slot, self.addr, self.instruction_addr
+        )
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use test_macros::kernel_test;
+
+    static NUM_EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_event(_event: &DebugEvent) {
+        NUM_EVENTS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(never)]
+    fn breakpoint_target() -> usize {
+        // The volatile read keeps the compiler from evaluating calls at compile time.
+        unsafe { core::ptr::read_volatile(&42) }
+    }
+
+    /// A write to a watched static must invoke the callback exactly once and still take effect.
+    #[kernel_test]
+    fn watchpoint_triggers_on_write() {
+        static mut WATCHED: u64 = 0;
+
+        NUM_EVENTS.store(0, Ordering::Relaxed);
+        let addr = Address::new(unsafe { &WATCHED as *const _ as usize });
+        let slot = set_watchpoint(addr, 8, WatchpointKind::Write, Some(count_event)).unwrap();
+
+        unsafe { core::ptr::write_volatile(&mut WATCHED, 7) };
+        assert_eq!(NUM_EVENTS.load(Ordering::Relaxed), 1);
+        assert_eq!(unsafe { core::ptr::read_volatile(&WATCHED) }, 7);
+
+        clear_watchpoint(slot).unwrap();
+        unsafe { core::ptr::write_volatile(&mut WATCHED, 8) };
+        assert_eq!(NUM_EVENTS.load(Ordering::Relaxed), 1);
+    }
+
+    /// A wider write that only partially overlaps the watched bytes must invoke the callback
+    /// exactly once.
+    #[kernel_test]
+    fn watchpoint_triggers_on_overlapping_write() {
+        static mut WATCHED: u64 = 0;
+
+        NUM_EVENTS.store(0, Ordering::Relaxed);
+        let addr = Address::new(unsafe { &WATCHED as *const _ as usize } + 4);
+        let slot = set_watchpoint(addr, 4, WatchpointKind::Write, Some(count_event)).unwrap();
+
+        unsafe { core::ptr::write_volatile(&mut WATCHED, u64::MAX) };
+        assert_eq!(NUM_EVENTS.load(Ordering::Relaxed), 1);
+        assert_eq!(unsafe { core::ptr::read_volatile(&WATCHED) }, u64::MAX);
+
+        clear_watchpoint(slot).unwrap();
+    }
+
+    /// Executing a function with a breakpoint must invoke the callback exactly once.
+    #[kernel_test]
+    fn breakpoint_triggers_on_execution() {
+        NUM_EVENTS.store(0, Ordering::Relaxed);
+        let addr = Address::new(breakpoint_target as usize);
+        let slot = set_breakpoint(addr, Some(count_event)).unwrap();
+
+        assert_eq!(breakpoint_target(), 42);
+        assert_eq!(NUM_EVENTS.load(Ordering::Relaxed), 1);
+
+        clear_breakpoint(slot).unwrap();
+        assert_eq!(breakpoint_target(), 42);
+        assert_eq!(NUM_EVENTS.load(Ordering::Relaxed), 1);
+    }
+}
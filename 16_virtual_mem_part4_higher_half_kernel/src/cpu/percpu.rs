@@ -0,0 +1,180 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Per-core data.
+//!
+//! Each core owns a block, whose address is stored in a core-private register during the core's
+//! init. [`PerCpu`] wraps one instance of a value per core and hands out the executing core's
+//! instance.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "../_arch/aarch64/cpu/percpu.rs"]
+mod arch_percpu;
+
+use crate::{bsp, cpu, exception};
+use core::cell::UnsafeCell;
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The per-core block.
+struct CoreBlock {
+    core_id: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// One instance of `T` per core.
+///
+/// Each core only ever accesses its own instance, with IRQs masked, so no locking is needed.
+pub struct PerCpu<T> {
+    data: UnsafeCell<[T; bsp::cpu::NUM_CORES]>,
+    borrowed: UnsafeCell<[bool; bsp::cpu::NUM_CORES]>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static CORE_BLOCKS: [CoreBlock; bsp::cpu::NUM_CORES] = new_core_blocks();
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+const fn new_core_blocks() -> [CoreBlock; bsp::cpu::NUM_CORES] {
+    const UNINIT: CoreBlock = CoreBlock { core_id: 0 };
+    let mut blocks = [UNINIT; bsp::cpu::NUM_CORES];
+
+    let mut i = 0;
+    while i < bsp::cpu::NUM_CORES {
+        blocks[i].core_id = i;
+        i += 1;
+    }
+
+    blocks
+}
+
+impl<T> PerCpu<T> {
+    /// Raw pointer to the executing core's instance.
+    fn local_ptr(&self) -> *mut T {
+        let all_cores = self.data.get() as *mut T;
+
+        unsafe { all_cores.add(core_id()) }
+    }
+
+    /// Run `f` with the executing core's instance while IRQs are masked.
+    ///
+    /// Panics if the instance is already borrowed, which can only happen if `f` calls back into
+    /// the same `PerCpu`.
+    fn borrow<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        exception::asynchronous::exec_with_irq_masked(|| {
+            let borrowed = unsafe { (self.borrowed.get() as *mut bool).add(core_id()) };
+            if unsafe { *borrowed } {
+                panic!("PerCpu already borrowed");
+            }
+
+            unsafe { *borrowed = true };
+            let ret = f(self.local_ptr());
+            unsafe { *borrowed = false };
+
+            ret
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Store the address of the executing core's block in the core.
+///
+/// # Safety
+///
+/// - Must be called once during the init of each core, before any per-core data is accessed.
+pub unsafe fn init() {
+    let block = &CORE_BLOCKS[cpu::smp::core_id::<usize>()];
+
+    arch_percpu::set_block_addr(block as *const CoreBlock as usize);
+}
+
+/// The executing core's id, read from its block.
+#[inline(always)]
+pub fn core_id() -> usize {
+    let block = arch_percpu::block_addr() as *const CoreBlock;
+
+    unsafe { (*block).core_id }
+}
+
+unsafe impl<T> Sync for PerCpu<T> where T: Send {}
+
+impl<T> PerCpu<T> {
+    /// Create an instance with the given value for each core.
+    pub const fn new(value: T) -> Self
+    where
+        T: Copy,
+    {
+        Self::from_array([value; bsp::cpu::NUM_CORES])
+    }
+
+    /// Create an instance with an individual value for each core.
+    pub const fn from_array(values: [T; bsp::cpu::NUM_CORES]) -> Self {
+        Self {
+            data: UnsafeCell::new(values),
+            borrowed: UnsafeCell::new([false; bsp::cpu::NUM_CORES]),
+        }
+    }
+
+    /// Grant temporary immutable access to the executing core's instance.
+    ///
+    /// IRQs are masked for the duration of the closure, so the code can neither be preempted nor
+    /// migrate to another core while it holds the reference.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.borrow(|ptr| f(unsafe { &*ptr }))
+    }
+
+    /// Grant temporary mutable access to the executing core's instance.
+    ///
+    /// IRQs are masked for the duration of the closure, same as for [`PerCpu::with`].
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.borrow(|ptr| f(unsafe { &mut *ptr }))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// The block must report the same core id as the hardware.
+    #[kernel_test]
+    fn core_id_matches_hardware() {
+        assert_eq!(core_id(), cpu::smp::core_id::<usize>());
+    }
+
+    /// Changes must only affect the executing core's instance.
+    #[kernel_test]
+    fn per_cpu_is_core_local() {
+        static COUNTER: PerCpu<u64> = PerCpu::new(0);
+
+        COUNTER.with_mut(|x| *x += 5);
+        assert_eq!(COUNTER.with(|x| *x), 5);
+
+        let all_cores = unsafe { &*COUNTER.data.get() };
+        let others: u64 = all_cores
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != core_id())
+            .map(|(_, x)| *x)
+            .sum();
+        assert_eq!(others, 0);
+    }
+}
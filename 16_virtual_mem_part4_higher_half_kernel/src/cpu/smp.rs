@@ -8,7 +8,90 @@
 #[path = "../_arch/aarch64/cpu/smp.rs"]
 mod arch_smp;
 
+use crate::{bsp, cpu, exception, memory, time};
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// How long to wait for the secondary cores to come online.
+const SECONDARY_CORE_BOOT_TIMEOUT: Duration = Duration::from_secs(1);
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// Bitmask of the cores that have finished their init. The boot core is always online.
+static CORES_ONLINE: AtomicUsize = AtomicUsize::new(1 << bsp::cpu::BOOT_CORE_ID);
+
 //--------------------------------------------------------------------------------------------------
 // Architectural Public Reexports
 //--------------------------------------------------------------------------------------------------
-pub use arch_smp::core_id;
+pub use arch_smp::{core_id, send_event};
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Init code of the secondary cores.
+///
+/// When this code runs, virtual memory is already enabled.
+///
+/// # Safety
+///
+/// - Must only be called from `secondary_runtime_init()`.
+pub unsafe fn secondary_core_init() -> ! {
+    use exception::asynchronous::interface::IRQManager;
+
+    exception::handling_init();
+    bsp::exception::asynchronous::irq_manager().secondary_core_init();
+
+    CORES_ONLINE.fetch_or(1 << core_id::<usize>(), Ordering::Release);
+
+    // Replay the breakpoints and watchpoints only after going online, so that none that is set in
+    // between is missed. Setting them on this core again through a cross-core call is harmless.
+    cpu::debug::secondary_core_init();
+
+    exception::asynchronous::local_irq_unmask();
+    cpu::wait_forever()
+}
+
+/// Release the secondary cores and wait until they have finished their init.
+///
+/// Returns the number of cores that are online afterwards.
+pub fn start_secondary_cores() -> Result<usize, &'static str> {
+    let entry = memory::mmu::try_virt_to_phys(arch_smp::secondary_core_entry_addr())
+        .map_err(|_| "Secondary core entry is not mapped")?;
+
+    for core_id in 0..bsp::cpu::NUM_CORES {
+        if core_id as u64 == bsp::cpu::BOOT_CORE_ID {
+            continue;
+        }
+
+        bsp::cpu::release_secondary_core(core_id, entry)?;
+    }
+    send_event();
+
+    let deadline = time::Deadline::after(SECONDARY_CORE_BOOT_TIMEOUT);
+    while num_cores_online() < bsp::cpu::NUM_CORES {
+        if deadline.has_expired() {
+            return Err("Timed out waiting for the secondary cores");
+        }
+    }
+
+    Ok(num_cores_online())
+}
+
+/// The number of cores that have finished their init.
+pub fn num_cores_online() -> usize {
+    CORES_ONLINE.load(Ordering::Acquire).count_ones() as usize
+}
+
+/// Check if the given core has finished its init.
+pub fn is_core_online(core_id: usize) -> bool {
+    CORES_ONLINE.load(Ordering::Acquire) & (1 << core_id) != 0
+}
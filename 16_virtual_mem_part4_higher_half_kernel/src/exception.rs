@@ -9,11 +9,13 @@
 mod arch_exception;
 
 pub mod asynchronous;
+pub mod fixup;
+pub mod synchronous;
 
 //--------------------------------------------------------------------------------------------------
 // Architectural Public Reexports
 //--------------------------------------------------------------------------------------------------
-pub use arch_exception::{current_privilege_level, handling_init};
+pub use arch_exception::{current_privilege_level, handling_init, num_recovered_serrors};
 
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
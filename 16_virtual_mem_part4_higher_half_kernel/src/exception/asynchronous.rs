@@ -8,20 +8,28 @@
 #[path = "../_arch/aarch64/exception/asynchronous.rs"]
 mod arch_asynchronous;
 
+mod handler_table;
+pub mod ipi;
+mod stats;
+
+use crate::cpu::percpu::PerCpu;
 use core::{fmt, marker::PhantomData};
 
 //--------------------------------------------------------------------------------------------------
 // Architectural Public Reexports
 //--------------------------------------------------------------------------------------------------
 pub use arch_asynchronous::{
-    is_local_irq_masked, local_irq_mask, local_irq_mask_save, local_irq_restore, local_irq_unmask,
-    print_state,
+    is_local_irq_masked, local_fiq_mask, local_fiq_unmask, local_irq_mask, local_irq_mask_save,
+    local_irq_restore, local_irq_unmask, local_serror_unmask, print_state,
 };
 
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
 //--------------------------------------------------------------------------------------------------
 
+pub use handler_table::IRQHandlerTable;
+pub use stats::{IRQStats, IRQStatsTable};
+
 /// Interrupt descriptor.
 #[derive(Copy, Clone)]
 pub struct IRQDescriptor {
@@ -32,6 +40,17 @@ pub struct IRQDescriptor {
     pub handler: &'static (dyn interface::IRQHandler + Sync),
 }
 
+/// Tells whether an IRQ handler serviced an interrupt.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IRQReturn {
+    /// The handler's device raised the interrupt, and the handler serviced it.
+    Handled,
+
+    /// The handler's device did not raise the interrupt. Happens on shared IRQ lines, where the
+    /// interrupt came from another device on the same line.
+    NotHandled,
+}
+
 /// IRQContext token.
 ///
 /// An instance of this type indicates that the local core is currently executing in IRQ
@@ -44,13 +63,52 @@ pub struct IRQContext<'irq_context> {
     _0: PhantomData<&'irq_context ()>,
 }
 
+/// The cores that receive an inter-processor interrupt (IPI).
+#[derive(Copy, Clone)]
+pub enum IPITarget {
+    /// A single core, identified by its id.
+    Core(usize),
+
+    /// All cores except the executing one.
+    AllOthers,
+}
+
+/// Interrupt priorities.
+///
+/// On interrupt controllers that support priorities, a pending IRQ of a higher priority preempts
+/// the handler of a lower priority IRQ, if that handler allows nesting.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum IRQPriority {
+    Low,
+    Normal,
+    High,
+}
+
 /// Asynchronous exception handling interfaces.
 pub mod interface {
 
     /// Implemented by types that handle IRQs.
     pub trait IRQHandler {
         /// Called when the corresponding interrupt is asserted.
-        fn handle(&self) -> Result<(), &'static str>;
+        ///
+        /// Handlers on shared IRQ lines must check if their device raised the interrupt, and
+        /// return [`IRQReturn::NotHandled`](super::IRQReturn::NotHandled) otherwise.
+        ///
+        /// A chained interrupt controller, i.e. one that signals its own IRQs through a single IRQ
+        /// of a primary controller, registers itself as the handler of that IRQ and dispatches to
+        /// the handlers of its pending IRQs.
+        fn handle(&self) -> Result<super::IRQReturn, &'static str>;
+
+        /// Opt in to running with IRQs unmasked, so that IRQs of a higher priority can preempt
+        /// the handler.
+        ///
+        /// The handler must then be safe against being interrupted at any point, e.g. it must
+        /// only use IRQ-safe locks for data that the preempting handlers also touch. Only has an
+        /// effect on interrupt controllers that support priorities.
+        fn allows_nesting(&self) -> bool {
+            false
+        }
     }
 
     /// IRQ management functions.
@@ -62,6 +120,9 @@ pub mod interface {
         type IRQNumberType;
 
         /// Register a handler.
+        ///
+        /// Several handlers can be registered for the same IRQ, which makes it a shared IRQ. All
+        /// handlers of a shared IRQ are called when it is asserted.
         fn register_handler(
             &self,
             irq_number: Self::IRQNumberType,
@@ -71,11 +132,27 @@ pub mod interface {
         /// Enable an interrupt in the controller.
         fn enable(&self, irq_number: Self::IRQNumberType);
 
+        /// Set the priority of an interrupt.
+        ///
+        /// All interrupts start out with [`IRQPriority::Normal`](super::IRQPriority::Normal).
+        fn set_priority(
+            &self,
+            _irq_number: Self::IRQNumberType,
+            _priority: super::IRQPriority,
+        ) -> Result<(), &'static str> {
+            Err("IRQ priorities not supported")
+        }
+
         /// Handle pending interrupts.
         ///
+        /// IRQs that turn out to have no pending source or no registered handler are counted in
+        /// the statistics instead of being treated as fatal.
+        ///
         /// This function is called directly from the CPU's IRQ exception vector. On AArch64,
         /// this means that the respective CPU core has disabled exception handling.
-        /// This function can therefore not be preempted and runs start to finish.
+        /// This function can therefore not be preempted and runs start to finish, unless the
+        /// interrupt controller supports priorities and the handler allows nesting. See
+        /// [`handle_nestable()`](super::handle_nestable).
         ///
         /// Takes an IRQContext token to ensure it can only be called from IRQ context.
         #[allow(clippy::trivially_copy_pass_by_ref)]
@@ -84,8 +161,71 @@ pub mod interface {
             ic: &super::IRQContext<'irq_context>,
         );
 
+        /// Register the fast interrupt request (FIQ) handler and route the interrupt to the FIQ
+        /// exception.
+        ///
+        /// FIQs bypass the normal IRQ path, which makes them suitable for latency-critical
+        /// sources. Only a single FIQ handler can be registered. The interrupt must not be enabled
+        /// as a normal IRQ at the same time.
+        fn register_fiq_handler(
+            &self,
+            irq_number: Self::IRQNumberType,
+            descriptor: super::IRQDescriptor,
+        ) -> Result<(), &'static str>;
+
+        /// Handle a pending FIQ.
+        ///
+        /// This function is called directly from the CPU's FIQ exception vector. On AArch64, this
+        /// means that the respective CPU core has disabled IRQ and FIQ handling.
+        ///
+        /// Takes an IRQContext token to ensure it can only be called from IRQ context.
+        #[allow(clippy::trivially_copy_pass_by_ref)]
+        fn handle_pending_fiq<'irq_context>(
+            &'irq_context self,
+            ic: &super::IRQContext<'irq_context>,
+        );
+
         /// Print list of registered handlers.
         fn print_handler(&self);
+
+        /// Print the per-IRQ statistics of the registered handlers, and the number of spurious
+        /// and unhandled IRQs.
+        fn print_stats(&self);
+
+        /// Prepare the executing secondary core for taking interrupts.
+        ///
+        /// The boot core is prepared by the controller driver's `init()`.
+        ///
+        /// # Safety
+        ///
+        /// - Changes the HW state of the executing core.
+        unsafe fn secondary_core_init(&self) {}
+
+        /// Raise the inter-processor interrupt `irq_number` on the cores selected by `target`.
+        ///
+        /// Once enabled, inter-processor interrupts are also enabled on the secondary cores by
+        /// [`secondary_core_init()`](IRQManager::secondary_core_init).
+        fn send_ipi(
+            &self,
+            _irq_number: Self::IRQNumberType,
+            _target: super::IPITarget,
+        ) -> Result<(), &'static str> {
+            Err("Inter-processor interrupts not supported")
+        }
+
+        /// Like [`send_ipi()`](IRQManager::send_ipi), but without taking any locks.
+        ///
+        /// # Safety
+        ///
+        /// - Only use from the panic handler. The controller's registers are accessed while other
+        ///   cores might be using them.
+        unsafe fn panic_send_ipi(
+            &self,
+            _irq_number: Self::IRQNumberType,
+            _target: super::IPITarget,
+        ) -> Result<(), &'static str> {
+            Err("Inter-processor interrupts not supported")
+        }
     }
 }
 
@@ -93,6 +233,13 @@ pub mod interface {
 #[derive(Copy, Clone)]
 pub struct IRQNumber<const MAX_INCLUSIVE: usize>(usize);
 
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// The number of handlers on the executing core that run with IRQs unmasked.
+static NESTABLE_HANDLER_DEPTH: PerCpu<usize> = PerCpu::new(0);
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
@@ -134,6 +281,43 @@ impl<const MAX_INCLUSIVE: usize> fmt::Display for IRQNumber<{ MAX_INCLUSIVE }> {
     }
 }
 
+/// Call the handler of an acknowledged IRQ, with IRQs unmasked if the handler allows nesting.
+///
+/// Only for interrupt controllers that do not signal IRQs of the same or a lower priority while
+/// the handler runs, e.g. through the running priority of the GIC. Otherwise, a level-triggered
+/// IRQ would preempt its own handler over and over again.
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub fn handle_nestable(
+    descriptor: &IRQDescriptor,
+    _ic: &IRQContext,
+) -> Result<IRQReturn, &'static str> {
+    if !descriptor.handler.allows_nesting() {
+        return descriptor.handler.handle();
+    }
+
+    NESTABLE_HANDLER_DEPTH.with_mut(|depth| *depth += 1);
+
+    // The interrupted context's exception return state was saved on the stack already, so a
+    // nested IRQ can not clobber it.
+    let result = unsafe {
+        local_irq_unmask();
+        let result = descriptor.handler.handle();
+        local_irq_mask();
+
+        result
+    };
+
+    NESTABLE_HANDLER_DEPTH.with_mut(|depth| *depth -= 1);
+
+    result
+}
+
+/// Check if the executing core is in a handler that runs with IRQs unmasked, i.e. if an IRQ that
+/// is taken now is nested.
+pub fn is_in_nestable_handler() -> bool {
+    NESTABLE_HANDLER_DEPTH.with(|depth| *depth > 0)
+}
+
 /// Executes the provided closure while IRQs are masked on the executing core.
 ///
 /// While the function temporarily changes the HW state of the executing core, it restores it to the
@@ -150,3 +334,64 @@ pub fn exec_with_irq_masked<T>(f: impl FnOnce() -> T) -> T {
 
     ret
 }
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Reports whether it runs as a nestable handler, i.e. accounted and with IRQs unmasked.
+    struct Handler {
+        allows_nesting: bool,
+    }
+
+    impl interface::IRQHandler for Handler {
+        fn handle(&self) -> Result<IRQReturn, &'static str> {
+            if is_in_nestable_handler() && !is_local_irq_masked() {
+                return Ok(IRQReturn::Handled);
+            }
+
+            Ok(IRQReturn::NotHandled)
+        }
+
+        fn allows_nesting(&self) -> bool {
+            self.allows_nesting
+        }
+    }
+
+    /// Only handlers that allow nesting must run with IRQs unmasked, and the depth must be back to
+    /// zero once they return.
+    #[kernel_test]
+    fn nestable_handler_depth_is_accounted() {
+        let nestable = IRQDescriptor {
+            name: "Nestable",
+            handler: &Handler {
+                allows_nesting: true,
+            },
+        };
+        let not_nestable = IRQDescriptor {
+            name: "Not nestable",
+            handler: &Handler {
+                allows_nesting: false,
+            },
+        };
+
+        // Handlers are called from the IRQ vector, where IRQs are masked.
+        exec_with_irq_masked(|| {
+            let ic = unsafe { IRQContext::new() };
+
+            assert_eq!(handle_nestable(&nestable, &ic), Ok(IRQReturn::Handled));
+            assert!(!is_in_nestable_handler());
+            assert!(is_local_irq_masked());
+
+            assert_eq!(
+                handle_nestable(&not_nestable, &ic),
+                Ok(IRQReturn::NotHandled)
+            );
+        });
+    }
+}
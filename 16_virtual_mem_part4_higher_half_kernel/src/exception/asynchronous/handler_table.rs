@@ -0,0 +1,157 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! IRQ handler tables.
+//!
+//! An IRQ line can be shared by several devices. The first handler of a line is stored in the
+//! line's slot. Only a few lines are shared in practice, so further handlers go to a small pool
+//! that all lines of a table share.
+//!
+//! When a shared line is asserted, all of its handlers are called, because a level-triggered line
+//! stays asserted until every device on it was serviced.
+
+use super::{IRQDescriptor, IRQReturn};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The maximum number of handlers per table, beyond the first handler of each line.
+const MAX_SHARED_HANDLERS: usize = 8;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The registered handlers of an interrupt controller.
+pub struct IRQHandlerTable<const NUM_IRQS: usize> {
+    lines: [Option<IRQDescriptor>; NUM_IRQS],
+
+    /// Further handlers of shared lines, together with the number of their line.
+    shared: [Option<(usize, IRQDescriptor)>; MAX_SHARED_HANDLERS],
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl<const NUM_IRQS: usize> IRQHandlerTable<{ NUM_IRQS }> {
+    /// Create an instance.
+    pub const fn new() -> Self {
+        Self {
+            lines: [None; NUM_IRQS],
+            shared: [None; MAX_SHARED_HANDLERS],
+        }
+    }
+
+    /// Add a handler to an IRQ line.
+    pub fn register(
+        &mut self,
+        irq_number: usize,
+        descriptor: IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        let line = &mut self.lines[irq_number];
+        if line.is_none() {
+            *line = Some(descriptor);
+            return Ok(());
+        }
+
+        let slot = self
+            .shared
+            .iter_mut()
+            .find(|x| x.is_none())
+            .ok_or("Too many shared IRQ handlers")?;
+        *slot = Some((irq_number, descriptor));
+
+        Ok(())
+    }
+
+    /// Check if an IRQ line has at least one handler.
+    pub fn has_handler(&self, irq_number: usize) -> bool {
+        self.lines[irq_number].is_some()
+    }
+
+    /// The handlers of an IRQ line, in order of registration.
+    pub fn handlers(&self, irq_number: usize) -> impl Iterator<Item = &IRQDescriptor> + '_ {
+        let shared = self
+            .shared
+            .iter()
+            .flatten()
+            .filter(move |(number, _)| *number == irq_number)
+            .map(|(_, descriptor)| descriptor);
+
+        self.lines[irq_number].iter().chain(shared)
+    }
+
+    /// All handlers, together with the number of their line, ordered by IRQ number.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &IRQDescriptor)> + '_ {
+        (0..NUM_IRQS).flat_map(move |irq_number| {
+            self.handlers(irq_number)
+                .map(move |descriptor| (irq_number, descriptor))
+        })
+    }
+
+    /// Call all handlers of an IRQ line through `call`.
+    ///
+    /// Returns [`IRQReturn::Handled`] if at least one of the handlers serviced the interrupt.
+    pub fn dispatch(
+        &self,
+        irq_number: usize,
+        mut call: impl FnMut(&IRQDescriptor) -> Result<IRQReturn, &'static str>,
+    ) -> Result<IRQReturn, &'static str> {
+        let mut ret = IRQReturn::NotHandled;
+
+        for descriptor in self.handlers(irq_number) {
+            if call(descriptor)? == IRQReturn::Handled {
+                ret = IRQReturn::Handled;
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bsp::FakeIRQHandler;
+    use test_macros::kernel_test;
+
+    /// All handlers of a shared line must be called, and the line counts as handled if one of
+    /// them handled it.
+    #[kernel_test]
+    fn shared_line_calls_all_handlers() {
+        let mut table: IRQHandlerTable<4> = IRQHandlerTable::new();
+        let not_handled = IRQDescriptor {
+            name: "Not handled",
+            handler: &FakeIRQHandler::NOT_HANDLED,
+        };
+        let handled = IRQDescriptor {
+            name: "Handled",
+            handler: &FakeIRQHandler::HANDLED,
+        };
+
+        table.register(1, not_handled).unwrap();
+        table.register(1, handled).unwrap();
+        table.register(2, not_handled).unwrap();
+
+        let mut calls = 0;
+        let ret = table.dispatch(1, |descriptor| {
+            calls += 1;
+            descriptor.handler.handle()
+        });
+        assert_eq!(ret, Ok(IRQReturn::Handled));
+        assert_eq!(calls, 2);
+
+        let ret = table.dispatch(2, |descriptor| descriptor.handler.handle());
+        assert_eq!(ret, Ok(IRQReturn::NotHandled));
+
+        assert!(!table.has_handler(0));
+        assert_eq!(table.iter().count(), 3);
+    }
+}
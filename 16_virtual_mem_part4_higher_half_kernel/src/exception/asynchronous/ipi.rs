@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Inter-processor interrupts (IPI).
+//!
+//! Cross-core function calls on top of the BSP's IPI. The caller publishes a call and a bitmask of
+//! target cores, raises the IPI on each target and waits until every target has run the call.
+//!
+//! Only one cross-core call is in flight at a time. A core waiting for its turn keeps serving the
+//! calls directed at itself, so that two cores calling each other with IRQs masked can not
+//! deadlock.
+
+use crate::{bsp, cpu, exception, synchronization, synchronization::IRQSafeSpinLock};
+use core::{
+    hint::spin_loop,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A function to run on other cores, and its argument.
+#[derive(Copy, Clone)]
+struct CrossCall {
+    func: fn(usize),
+    arg: usize,
+}
+
+/// The IPI handler.
+struct IPIHandler;
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static IPI_HANDLER: IPIHandler = IPIHandler;
+
+/// Serializes cross-core calls.
+static CALL_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// The call in flight.
+static CURRENT_CALL: IRQSafeSpinLock<Option<CrossCall>> = IRQSafeSpinLock::new(None);
+
+/// Bitmask of the cores that have yet to run the call in flight.
+static PENDING_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set when the other cores shall stop for good.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+/// Run the call in flight if the executing core is one of its targets.
+fn serve_pending_call() {
+    let core_bit = 1 << cpu::smp::core_id::<usize>();
+
+    if PENDING_CALLS.load(Ordering::Acquire) & core_bit == 0 {
+        return;
+    }
+
+    if let Some(call) = CURRENT_CALL.lock(|c| *c) {
+        (call.func)(call.arg);
+    }
+
+    PENDING_CALLS.fetch_and(!core_bit, Ordering::Release);
+}
+
+/// Run `func(arg)` on the cores in `targets` and wait for completion.
+fn call_on_mask(targets: usize, func: fn(usize), arg: usize) -> Result<(), &'static str> {
+    use exception::asynchronous::interface::IRQManager;
+
+    if targets == 0 {
+        return Ok(());
+    }
+
+    while CALL_IN_PROGRESS
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        serve_pending_call();
+        spin_loop();
+    }
+
+    CURRENT_CALL.lock(|c| *c = Some(CrossCall { func, arg }));
+    PENDING_CALLS.store(targets, Ordering::Release);
+
+    let mut result = Ok(());
+    for core in 0..bsp::cpu::NUM_CORES {
+        if targets & (1 << core) == 0 {
+            continue;
+        }
+
+        result = bsp::exception::asynchronous::irq_manager().send_ipi(
+            bsp::exception::asynchronous::ipi_irq(),
+            super::IPITarget::Core(core),
+        );
+        if result.is_err() {
+            break;
+        }
+    }
+
+    match result {
+        Ok(()) => {
+            while PENDING_CALLS.load(Ordering::Acquire) != 0 {
+                spin_loop();
+            }
+        }
+        // Do not wait for cores that might never have been signaled.
+        Err(_) => PENDING_CALLS.store(0, Ordering::Release),
+    }
+
+    CURRENT_CALL.lock(|c| *c = None);
+    CALL_IN_PROGRESS.store(false, Ordering::Release);
+
+    result
+}
+
+impl exception::asynchronous::interface::IRQHandler for IPIHandler {
+    fn handle(&self) -> Result<exception::asynchronous::IRQReturn, &'static str> {
+        if STOP_REQUESTED.load(Ordering::Acquire) {
+            // IRQs are masked in the handler, so the core stays parked.
+            cpu::wait_forever()
+        }
+
+        serve_pending_call();
+
+        Ok(exception::asynchronous::IRQReturn::Handled)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Register and enable the IPI handler.
+///
+/// Must be called during kernel init. The secondary cores enable the IPI during their own init.
+pub fn register_and_enable_handler() -> Result<(), &'static str> {
+    use bsp::exception::asynchronous::irq_manager;
+    use exception::asynchronous::{interface::IRQManager, IRQDescriptor};
+
+    let descriptor = IRQDescriptor {
+        name: "IPI",
+        handler: &IPI_HANDLER,
+    };
+    let irq_number = bsp::exception::asynchronous::ipi_irq();
+
+    irq_manager().register_handler(irq_number, descriptor)?;
+    irq_manager().enable(irq_number);
+
+    Ok(())
+}
+
+/// Run `func(arg)` on the given core and wait until it returns.
+///
+/// Runs `func` directly if `core` is the executing core. The caller must not hold locks that
+/// `func` takes.
+pub fn call_on(core: usize, func: fn(usize), arg: usize) -> Result<(), &'static str> {
+    if core == cpu::smp::core_id::<usize>() {
+        func(arg);
+        return Ok(());
+    }
+
+    if !cpu::smp::is_core_online(core) {
+        return Err("Target core is not online");
+    }
+
+    call_on_mask(1 << core, func, arg)
+}
+
+/// Run `func(arg)` on all other online cores and wait until it returned on each of them.
+///
+/// The caller must not hold locks that `func` takes.
+pub fn call_on_others(func: fn(usize), arg: usize) -> Result<(), &'static str> {
+    let self_id = cpu::smp::core_id::<usize>();
+
+    let targets = (0..bsp::cpu::NUM_CORES)
+        .filter(|&core| core != self_id && cpu::smp::is_core_online(core))
+        .fold(0, |mask, core| mask | (1 << core));
+
+    call_on_mask(targets, func, arg)
+}
+
+/// Interrupt the given core without a call, e.g. to make it reschedule on its IRQ exit path.
+pub fn kick(core: usize) {
+    use exception::asynchronous::interface::IRQManager;
+
+    let _ = bsp::exception::asynchronous::irq_manager().send_ipi(
+        bsp::exception::asynchronous::ipi_irq(),
+        super::IPITarget::Core(core),
+    );
+}
+
+/// Park all other cores for good, without waiting for them.
+///
+/// Intended for the panic path. Cores with IRQs masked are not stopped. The IPI is sent without
+/// taking the interrupt controller's lock, because a stopped core or the panicking core itself
+/// might hold it.
+pub fn stop_other_cores() {
+    use exception::asynchronous::interface::IRQManager;
+
+    STOP_REQUESTED.store(true, Ordering::Release);
+
+    let _ = unsafe {
+        bsp::exception::asynchronous::irq_manager().panic_send_ipi(
+            bsp::exception::asynchronous::ipi_irq(),
+            super::IPITarget::AllOthers,
+        )
+    };
+}
@@ -0,0 +1,354 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! IRQ statistics.
+//!
+//! Interrupt controller drivers dispatch IRQs through an [`IRQStatsTable`], which accounts each
+//! call of an IRQ's handlers, and counts the IRQs that no handler took care of instead of
+//! panicking. Like Linux's `note_interrupt()`, an IRQ that keeps going unhandled is disabled, so
+//! that a stuck line can not lock up the core. The table prints itself in the style of Linux's
+//! `/proc/interrupts`, with one count column per core.
+
+use super::{IRQDescriptor, IRQHandlerTable, IRQReturn};
+use crate::{
+    bsp, cpu, info, synchronization, synchronization::IRQSafeSpinLock, time::Instant, warn,
+};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The number of times in a row an IRQ may go unhandled before it is disabled.
+const MAX_UNHANDLED_IN_A_ROW: u32 = 100_000;
+
+/// Renders the per-core column headers.
+struct CoreHeader;
+
+/// Renders the per-core counts of an IRQ.
+struct CoreCounts<'a>(&'a [u64; bsp::cpu::NUM_CORES]);
+
+/// Renders a point in time as uptime in seconds, or a dash if there is none.
+struct Timestamp(Option<Instant>);
+
+/// Renders the names of the handlers of an IRQ line.
+struct HandlerNames<'a, const NUM_IRQS: usize> {
+    handlers: &'a IRQHandlerTable<NUM_IRQS>,
+    irq_number: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Statistics of a single IRQ.
+#[derive(Copy, Clone)]
+pub struct IRQStats {
+    /// The number of handler calls, per core.
+    count: [u64; bsp::cpu::NUM_CORES],
+
+    /// The point in time the handler was called last.
+    last: Option<Instant>,
+
+    /// The longest time a handler call took.
+    max_duration: Duration,
+
+    /// The number of times the IRQ went unhandled since it was last handled.
+    unhandled_in_row: u32,
+}
+
+/// Statistics of all IRQs of an interrupt controller.
+pub struct IRQStatsTable<const NUM_IRQS: usize> {
+    irqs: IRQSafeSpinLock<[IRQStats; NUM_IRQS]>,
+
+    /// IRQs that were signaled, but turned out to have no pending source.
+    spurious: AtomicU64,
+
+    /// Pending IRQs that no handler serviced.
+    unhandled: AtomicU64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl fmt::Display for CoreHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for core in 0..bsp::cpu::NUM_CORES {
+            write!(f, "{:>9}{}", "CPU", core)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CoreCounts<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for count in self.0.iter() {
+            write!(f, "{:>10}", count)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let uptime = match self.0 {
+            None => return write!(f, "{:>14}", "-"),
+            Some(x) => x.as_duration_since_boot(),
+        };
+
+        write!(f, "{:>7}.{:06}", uptime.as_secs(), uptime.subsec_micros())
+    }
+}
+
+impl<const NUM_IRQS: usize> fmt::Display for HandlerNames<'_, { NUM_IRQS }> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, descriptor) in self.handlers.handlers(self.irq_number).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", descriptor.name)?;
+        }
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl IRQStats {
+    /// Create an instance.
+    pub const fn new() -> Self {
+        Self {
+            count: [0; bsp::cpu::NUM_CORES],
+            last: None,
+            max_duration: Duration::from_secs(0),
+            unhandled_in_row: 0,
+        }
+    }
+
+    /// The number of handler calls on all cores.
+    pub fn count(&self) -> u64 {
+        self.count.iter().sum()
+    }
+
+    /// The number of handler calls on the given core.
+    pub fn count_on(&self, core: usize) -> u64 {
+        self.count[core]
+    }
+
+    /// The point in time the handler was called last, if it was called at all.
+    pub fn last(&self) -> Option<Instant> {
+        self.last
+    }
+
+    /// The longest time a handler call took.
+    pub fn max_duration(&self) -> Duration {
+        self.max_duration
+    }
+}
+
+impl<const NUM_IRQS: usize> IRQStatsTable<{ NUM_IRQS }> {
+    /// Create an instance.
+    pub const fn new() -> Self {
+        Self {
+            irqs: IRQSafeSpinLock::new([IRQStats::new(); NUM_IRQS]),
+            spurious: AtomicU64::new(0),
+            unhandled: AtomicU64::new(0),
+        }
+    }
+
+    /// Call an IRQ's handler through `f` and account the call.
+    pub fn account<T>(&self, irq_number: usize, f: impl FnOnce() -> T) -> T {
+        use synchronization::interface::Mutex;
+
+        let start = Instant::now();
+        let ret = f();
+        let duration = start.elapsed();
+
+        self.irqs.lock(|irqs| {
+            let stats = &mut irqs[irq_number];
+
+            stats.count[cpu::smp::core_id::<usize>()] += 1;
+            stats.last = Some(start);
+            stats.max_duration = stats.max_duration.max(duration);
+        });
+
+        ret
+    }
+
+    /// Call all handlers of a pending IRQ through `call`, and account the call.
+    ///
+    /// The IRQ is counted as unhandled if it has no handlers, or none of them serviced it. Once it
+    /// went unhandled `MAX_UNHANDLED_IN_A_ROW` times in a row, it is disabled through `disable`.
+    pub fn dispatch(
+        &self,
+        handlers: &IRQHandlerTable<NUM_IRQS>,
+        irq_number: usize,
+        call: impl FnMut(&IRQDescriptor) -> Result<IRQReturn, &'static str>,
+        disable: impl FnOnce(),
+    ) -> Result<IRQReturn, &'static str> {
+        let ret = if handlers.has_handler(irq_number) {
+            self.account(irq_number, || handlers.dispatch(irq_number, call))?
+        } else {
+            IRQReturn::NotHandled
+        };
+
+        self.note_result(irq_number, ret, disable);
+
+        Ok(ret)
+    }
+
+    /// Track how often in a row an IRQ went unhandled, and disable it once that happened too
+    /// often.
+    fn note_result(&self, irq_number: usize, ret: IRQReturn, disable: impl FnOnce()) {
+        use synchronization::interface::Mutex;
+
+        if ret == IRQReturn::NotHandled {
+            self.record_unhandled();
+        }
+
+        let stuck = self.irqs.lock(|irqs| {
+            let stats = &mut irqs[irq_number];
+
+            if ret == IRQReturn::Handled {
+                stats.unhandled_in_row = 0;
+                return false;
+            }
+
+            stats.unhandled_in_row += 1;
+            if stats.unhandled_in_row < MAX_UNHANDLED_IN_A_ROW {
+                return false;
+            }
+
+            stats.unhandled_in_row = 0;
+            true
+        });
+
+        if stuck {
+            warn!(
+                "IRQ {} went unhandled {} times in a row. Disabling it",
+                irq_number, MAX_UNHANDLED_IN_A_ROW
+            );
+            disable();
+        }
+    }
+
+    /// Count a spurious IRQ.
+    pub fn record_spurious(&self) {
+        self.spurious.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a pending IRQ that no handler serviced.
+    pub fn record_unhandled(&self) {
+        self.unhandled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The statistics of an IRQ.
+    pub fn get(&self, irq_number: usize) -> IRQStats {
+        use synchronization::interface::Mutex;
+
+        self.irqs.lock(|irqs| irqs[irq_number])
+    }
+
+    /// The number of spurious IRQs.
+    pub fn spurious(&self) -> u64 {
+        self.spurious.load(Ordering::Relaxed)
+    }
+
+    /// The number of pending IRQs that no handler serviced.
+    pub fn unhandled(&self) -> u64 {
+        self.unhandled.load(Ordering::Relaxed)
+    }
+
+    /// Print the statistics of all IRQs that have a registered handler, followed by the spurious
+    /// and unhandled counts.
+    pub fn print(&self, handlers: &IRQHandlerTable<NUM_IRQS>) {
+        info!(
+            "            {:>4}{} {:>14} {:>9}  Name",
+            "IRQ", CoreHeader, "Last [s]", "Max [us]"
+        );
+
+        for irq_number in 0..NUM_IRQS {
+            if !handlers.has_handler(irq_number) {
+                continue;
+            }
+
+            // Copy the statistics first, so the table is not locked while printing.
+            let stats = self.get(irq_number);
+
+            info!(
+                "            {:>4}{} {} {:>9}  {}",
+                irq_number,
+                CoreCounts(&stats.count),
+                Timestamp(stats.last),
+                stats.max_duration.as_micros(),
+                HandlerNames {
+                    handlers,
+                    irq_number
+                }
+            );
+        }
+
+        info!("            {:>4}{:>10}", "SPU", self.spurious());
+        info!("            {:>4}{:>10}", "ERR", self.unhandled());
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Accounted calls must show up in the statistics of their IRQ only.
+    #[kernel_test]
+    fn account_counts_calls() {
+        let table: IRQStatsTable<2> = IRQStatsTable::new();
+
+        assert_eq!(table.account(1, || 42), 42);
+
+        let stats = table.get(1);
+        assert_eq!(stats.count(), 1);
+        assert_eq!(stats.count_on(cpu::smp::core_id::<usize>()), 1);
+        assert!(stats.last().is_some());
+        assert_eq!(table.get(0).count(), 0);
+
+        table.record_spurious();
+        assert_eq!(table.spurious(), 1);
+        assert_eq!(table.unhandled(), 0);
+    }
+
+    /// An IRQ that keeps going unhandled must be disabled exactly once, when it reaches the limit.
+    #[kernel_test]
+    fn stuck_irq_is_disabled() {
+        let table: IRQStatsTable<2> = IRQStatsTable::new();
+        let handlers: IRQHandlerTable<2> = IRQHandlerTable::new();
+        let mut disabled = 0;
+
+        for _ in 0..MAX_UNHANDLED_IN_A_ROW - 1 {
+            let ret = table.dispatch(&handlers, 1, |d| d.handler.handle(), || disabled += 1);
+            assert_eq!(ret, Ok(IRQReturn::NotHandled));
+        }
+        assert_eq!(disabled, 0);
+
+        table
+            .dispatch(&handlers, 1, |d| d.handler.handle(), || disabled += 1)
+            .unwrap();
+        assert_eq!(disabled, 1);
+        assert_eq!(table.unhandled(), u64::from(MAX_UNHANDLED_IN_A_ROW));
+    }
+}
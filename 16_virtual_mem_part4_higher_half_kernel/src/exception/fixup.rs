@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Exception fixup table.
+//!
+//! Instructions that are allowed to fault register a recovery address in the exception fixup
+//! table, which lives in its own linker section. If such an instruction causes a synchronous
+//! exception, the exception handler continues execution at the recovery address instead of
+//! panicking.
+//!
+//! The fault-tolerant memory accessors in this module are built on top of the fixup table.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "../_arch/aarch64/exception/fixup.rs"]
+mod arch_fixup;
+
+use crate::{
+    bsp,
+    memory::{Address, Virtual},
+};
+use core::fmt;
+
+//--------------------------------------------------------------------------------------------------
+// Architectural Public Reexports
+//--------------------------------------------------------------------------------------------------
+pub use arch_fixup::{probe_read_u32, probe_write_u32};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// An entry of the exception fixup table.
+///
+/// The layout must match the entries emitted by the architectural code.
+#[repr(C)]
+pub struct FixupEntry {
+    /// Address of the instruction that is allowed to fault.
+    insn_addr: usize,
+
+    /// Address at which execution continues if the instruction faulted.
+    fixup_addr: usize,
+}
+
+/// The error type of the fault-tolerant memory accessors.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Fault {
+    addr: Address<Virtual>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Fault {
+    /// Create an instance.
+    pub const fn new(addr: Address<Virtual>) -> Self {
+        Self { addr }
+    }
+
+    /// The address whose access faulted.
+    pub const fn addr(&self) -> Address<Virtual> {
+        self.addr
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Memory access to {} faulted", self.addr)
+    }
+}
+
+impl fmt::Debug for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Search the fixup table for the recovery address of a faulting instruction.
+pub fn search(insn_addr: Address<Virtual>) -> Option<Address<Virtual>> {
+    bsp::memory::exception_fixup_table()
+        .iter()
+        .find(|x| x.insn_addr == insn_addr.into_usize())
+        .map(|x| Address::new(x.fixup_addr))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    const UNMAPPED_ADDR: usize = 1024 * 1024 * 1024;
+
+    /// Probing a mapped address must return its content.
+    #[kernel_test]
+    fn probe_read_of_mapped_address_succeeds() {
+        static VALUE: u32 = 0xdead_beef;
+
+        let addr = Address::new(&VALUE as *const _ as usize);
+
+        assert_eq!(probe_read_u32(addr), Ok(0xdead_beef));
+    }
+
+    /// Probing an unmapped address must return an error instead of panicking.
+    #[kernel_test]
+    fn probe_read_of_unmapped_address_faults() {
+        let addr = Address::new(UNMAPPED_ADDR);
+
+        assert_eq!(probe_read_u32(addr), Err(Fault::new(addr)));
+    }
+
+    /// A probing write to a mapped address must be visible afterwards.
+    #[kernel_test]
+    fn probe_write_to_mapped_address_succeeds() {
+        let mut value: u32 = 0;
+        let addr = Address::new(&mut value as *mut _ as usize);
+
+        assert_eq!(unsafe { probe_write_u32(addr, 42) }, Ok(()));
+        assert_eq!(unsafe { core::ptr::read_volatile(&value) }, 42);
+    }
+
+    /// A probing write to an unmapped address must return an error instead of panicking.
+    #[kernel_test]
+    fn probe_write_to_unmapped_address_faults() {
+        let addr = Address::new(UNMAPPED_ADDR);
+
+        assert_eq!(unsafe { probe_write_u32(addr, 42) }, Err(Fault::new(addr)));
+    }
+}
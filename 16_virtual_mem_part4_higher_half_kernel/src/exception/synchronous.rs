@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Synchronous exception handling.
+//!
+//! Synchronous exceptions that the architectural code can attribute to a faulting address are
+//! offered to registered fault handlers before the kernel gives up and panics. A handler can fix
+//! the cause of the fault (for example, by mapping a page) and let execution resume at the faulting
+//! instruction, or it can ask for the faulting instruction to be skipped.
+
+use crate::{
+    memory::{mmu::PageSliceDescriptor, Address, Virtual},
+    synchronization,
+    synchronization::InitStateLock,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+const NUM_FAULT_HANDLERS: usize = 8;
+
+type FaultHandlerTable = [Option<FaultHandlerDescriptor>; NUM_FAULT_HANDLERS];
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Architecture agnostic classes of synchronous exceptions that can be resolved by fault handlers.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ExceptionClass {
+    DataAbort,
+    InstructionAbort,
+}
+
+/// Information about a synchronous exception, as handed to fault handlers.
+#[derive(Copy, Clone)]
+pub struct FaultInfo {
+    /// The class of the exception.
+    pub class: ExceptionClass,
+
+    /// The virtual address that caused the fault.
+    pub fault_addr: Address<Virtual>,
+
+    /// The virtual address of the instruction that caused the fault.
+    pub instruction_addr: Address<Virtual>,
+}
+
+/// The possible outcomes of a fault handler invocation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FaultResolution {
+    /// The cause of the fault was fixed. Execution resumes by re-executing the faulting
+    /// instruction.
+    Resume,
+
+    /// Execution resumes at the instruction following the faulting instruction.
+    SkipInstruction,
+
+    /// The fault could not be resolved by the handler.
+    Unhandled,
+}
+
+/// Fault handler descriptor.
+#[derive(Copy, Clone)]
+pub struct FaultHandlerDescriptor {
+    /// Descriptive name.
+    pub name: &'static str,
+
+    /// The exception class the handler is interested in.
+    pub class: ExceptionClass,
+
+    /// The virtual address range the handler is responsible for.
+    pub virt_pages: PageSliceDescriptor<Virtual>,
+
+    /// Reference to handler trait object.
+    pub handler: &'static (dyn interface::FaultHandler + Sync),
+}
+
+/// Synchronous exception handling interfaces.
+pub mod interface {
+
+    /// Implemented by types that handle faults.
+    pub trait FaultHandler {
+        /// Called when a synchronous exception matching the handler's descriptor occurs.
+        ///
+        /// The handler is executed in exception context.
+        fn handle(&self, info: &super::FaultInfo) -> super::FaultResolution;
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// Stores registered fault handlers. Writable only during kernel init. RO afterwards.
+static FAULT_HANDLER_TABLE: InitStateLock<FaultHandlerTable> =
+    InitStateLock::new([None; NUM_FAULT_HANDLERS]);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::ReadWriteEx;
+
+impl FaultHandlerDescriptor {
+    /// Check if the handler is responsible for the given fault.
+    fn matches(&self, info: &FaultInfo) -> bool {
+        (self.class == info.class) && self.virt_pages.contains(info.fault_addr)
+    }
+}
+
+/// Register a fault handler.
+pub fn register_fault_handler(descriptor: FaultHandlerDescriptor) -> Result<(), &'static str> {
+    FAULT_HANDLER_TABLE.write(|table| {
+        if let Some(x) = table.iter_mut().find(|x| x.is_none()) {
+            *x = Some(descriptor);
+            return Ok(());
+        }
+
+        Err("Storage for fault handlers exhausted")
+    })
+}
+
+/// Offer a fault to the registered fault handlers.
+///
+/// Handlers are consulted in registration order. The first handler whose descriptor matches the
+/// fault and which does not return [`FaultResolution::Unhandled`] decides the outcome.
+pub fn handle_fault(info: &FaultInfo) -> FaultResolution {
+    FAULT_HANDLER_TABLE.read(|table| {
+        for descriptor in table.iter().flatten().filter(|x| x.matches(info)) {
+            let resolution = descriptor.handler.handle(info);
+
+            if resolution != FaultResolution::Unhandled {
+                return resolution;
+            }
+        }
+
+        FaultResolution::Unhandled
+    })
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Async executor.
+//!
+//! Runs futures to completion without a heap. The caller owns and pins the futures, and the
+//! executor polls each of them again only after its waker was invoked. Wakers are safe to invoke
+//! from IRQ handlers, which is how interrupt-driven drivers resume the tasks that await them.
+//!
+//! While no task is woken, the executor waits on a [`WaitQueue`], so the executing thread is
+//! parked, or the core waits for an event if it does not use threads.
+
+use crate::synchronization::WaitQueue;
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, RawWaker, RawWakerVTable, Waker},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The maximum number of tasks per executor.
+pub const MAX_TASKS: usize = 32;
+
+/// A task: a pinned future that does not return anything.
+pub type Task<'a> = Pin<&'a mut dyn Future<Output = ()>>;
+
+/// An executor. Must live in a static, because wakers refer to it.
+pub struct Executor {
+    /// Set by a task's waker. The task is polled again once its flag is set.
+    woken: [AtomicBool; MAX_TASKS],
+
+    running: AtomicBool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// Executors wait here until one of their tasks is woken.
+static WAKEUPS: WaitQueue = WaitQueue::new();
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+static WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake, waker_drop);
+
+/// The waker data points to the task's flag in a static executor, so cloning and dropping are
+/// no-ops.
+fn raw_waker(flag: &'static AtomicBool) -> RawWaker {
+    RawWaker::new(flag as *const AtomicBool as *const (), &WAKER_VTABLE)
+}
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    raw_waker(&*(data as *const AtomicBool))
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    let flag = &*(data as *const AtomicBool);
+
+    flag.store(true, Ordering::Release);
+    WAKEUPS.notify_all();
+}
+
+unsafe fn waker_drop(_data: *const ()) {}
+
+impl Executor {
+    /// Poll all tasks in `tasks` whose bit is set in `pending` and that were woken. Returns the
+    /// tasks that are still pending.
+    fn poll_woken(&'static self, tasks: &mut [Task], mut pending: u64) -> u64 {
+        for (i, task) in tasks.iter_mut().enumerate() {
+            if pending & (1 << i) == 0 || !self.woken[i].swap(false, Ordering::Acquire) {
+                continue;
+            }
+
+            let waker = unsafe { Waker::from_raw(raw_waker(&self.woken[i])) };
+            let mut cx = Context::from_waker(&waker);
+
+            if task.as_mut().poll(&mut cx).is_ready() {
+                pending &= !(1 << i);
+            }
+        }
+
+        pending
+    }
+
+    /// Check if one of the pending tasks was woken.
+    fn any_woken(&self, pending: u64) -> bool {
+        self.woken
+            .iter()
+            .enumerate()
+            .any(|(i, flag)| pending & (1 << i) != 0 && flag.load(Ordering::Acquire))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Executor {
+    /// Create an instance.
+    pub const fn new() -> Self {
+        // Only used to initialize the array, so each element is a distinct atomic.
+        #[allow(clippy::declare_interior_mutable_const)]
+        const NOT_WOKEN: AtomicBool = AtomicBool::new(false);
+
+        Self {
+            woken: [NOT_WOKEN; MAX_TASKS],
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// Run the given tasks until all of them have completed.
+    ///
+    /// Must not be called from IRQ handlers.
+    pub fn run(&'static self, tasks: &mut [Task]) -> Result<(), &'static str> {
+        if tasks.len() > MAX_TASKS {
+            return Err("Too many tasks for the executor");
+        }
+
+        if self.running.swap(true, Ordering::Acquire) {
+            return Err("Executor already running");
+        }
+
+        // Every task is polled once to get it started.
+        for flag in self.woken[..tasks.len()].iter() {
+            flag.store(true, Ordering::Relaxed);
+        }
+
+        let mut pending = (1u64 << tasks.len()) - 1;
+        loop {
+            pending = self.poll_woken(tasks, pending);
+            if pending == 0 {
+                break;
+            }
+
+            WAKEUPS.wait_until(|| self.any_woken(pending));
+        }
+
+        self.running.store(false, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Run a single future to completion and return its output.
+    ///
+    /// Must not be called from IRQ handlers.
+    pub fn block_on<F: Future>(&'static self, future: F) -> Result<F::Output, &'static str> {
+        let mut output = None;
+
+        {
+            let mut task = async {
+                output = Some(future.await);
+            };
+            // The task is shadowed, so it can not be moved anymore.
+            let task: Task = unsafe { Pin::new_unchecked(&mut task) };
+
+            self.run(&mut [task])?;
+        }
+
+        Ok(output.unwrap())
+    }
+}
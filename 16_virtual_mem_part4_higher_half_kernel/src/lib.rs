@@ -131,7 +131,6 @@
 
 mod panic_wait;
 mod runtime_init;
-mod synchronization;
 
 pub mod bsp;
 pub mod common;
@@ -139,10 +138,14 @@ pub mod console;
 pub mod cpu;
 pub mod driver;
 pub mod exception;
+pub mod executor;
 pub mod memory;
 pub mod print;
 pub mod state;
+pub mod synchronization;
+pub mod thread;
 pub mod time;
+pub mod watchdog;
 
 //--------------------------------------------------------------------------------------------------
 // Public Code
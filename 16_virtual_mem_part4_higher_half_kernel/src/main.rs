@@ -11,7 +11,50 @@
 #![no_main]
 #![no_std]
 
-use libkernel::{bsp, cpu, driver, exception, info, memory, state, time, warn};
+use core::time::Duration;
+use libkernel::{
+    bsp, console, cpu, driver, exception, executor::Executor, info, memory, print, state, thread,
+    time, warn,
+};
+
+/// The period of the kernel tick.
+const TICK_PERIOD: Duration = Duration::from_millis(10);
+
+/// The scheduler's time slice.
+const TIME_SLICE: Duration = Duration::from_millis(10);
+
+/// The board is reset if the watchdog is not petted within this timeout.
+#[cfg(feature = "watchdog")]
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Runs the console command loop.
+static EXECUTOR: Executor = Executor::new();
+
+/// Echo console input, and set the wall-clock time from lines of the form `date <ISO-8601>`.
+async fn console_commands() {
+    use time::interface::RealTimeClock;
+
+    let mut buf = [0; 64];
+
+    loop {
+        let len = console::read_line(bsp::console::console(), &mut buf).await;
+        let line = core::str::from_utf8(&buf[..len]).unwrap_or_default();
+
+        let timestamp = match line.trim().strip_prefix("date ") {
+            None => continue,
+            Some(x) => x,
+        };
+
+        match time::rtc::DateTime::parse_iso8601(timestamp) {
+            Err(msg) => warn!("{}", msg),
+            Ok(date_time) => {
+                time::rtc::real_time_clock().set_unix_time(date_time.to_unix_time());
+                print::set_wall_clock_timestamps(true);
+                info!("Wall-clock time set to {}", date_time);
+            }
+        }
+    }
+}
 
 /// Early init code.
 ///
@@ -24,6 +67,7 @@ use libkernel::{bsp, cpu, driver, exception, info, memory, state, time, warn};
 #[no_mangle]
 unsafe fn kernel_init() -> ! {
     use driver::interface::DriverManager;
+    use time::interface::TimeManager;
 
     exception::handling_init();
 
@@ -52,6 +96,12 @@ unsafe fn kernel_init() -> ! {
         }
     }
 
+    // The secondary cores are released through the spin table, which must be mapped while the
+    // kernel tables are still writable.
+    if let Err(msg) = bsp::cpu::map_spin_table() {
+        warn!("Error mapping the spin table: {}", msg);
+    }
+
     // Let device drivers register and enable their handlers with the interrupt controller.
     for i in bsp::driver::driver_manager().all_device_drivers() {
         if let Err(msg) = i.register_and_enable_irq_handler() {
@@ -59,8 +109,35 @@ unsafe fn kernel_init() -> ! {
         }
     }
 
+    if let Err(msg) = exception::asynchronous::ipi::register_and_enable_handler() {
+        warn!("Error registering IPI handler: {}", msg);
+    }
+
+    // Start the kernel tick. It is driven by the timer IRQ, so it needs the IRQ handler.
+    match time::time_manager().register_and_enable_irq_handler() {
+        Err(msg) => warn!("Error registering timer IRQ handler: {}", msg),
+        Ok(()) => {
+            if let Err(msg) = time::start_periodic_tick(TICK_PERIOD) {
+                warn!("Error starting the kernel tick: {}", msg);
+            }
+
+            // The watchdog is petted from the timer IRQ, so a panic ends in a reset as well.
+            #[cfg(feature = "watchdog")]
+            {
+                match libkernel::watchdog::start_with_periodic_pet(WATCHDOG_TIMEOUT) {
+                    Err(msg) => warn!("Error starting the watchdog: {}", msg),
+                    Ok(_) => libkernel::watchdog::set_reboot_on_panic(true),
+                }
+            }
+        }
+    }
+    if let Err(msg) = thread::start_preemption(TIME_SLICE) {
+        warn!("Error starting preemption: {}", msg);
+    }
+
     // Unmask interrupts on the boot CPU core.
     exception::asynchronous::local_irq_unmask();
+    exception::asynchronous::local_fiq_unmask();
 
     // Announce conclusion of the kernel_init() phase.
     state::state_manager().transition_to_single_core_main();
@@ -73,10 +150,19 @@ unsafe fn kernel_init() -> ! {
 fn kernel_main() -> ! {
     use driver::interface::DriverManager;
     use exception::asynchronous::interface::IRQManager;
+    use time::interface::TimeManager;
 
     info!("{}", libkernel::version());
     info!("Booting on: {}", bsp::board_name());
 
+    match cpu::smp::start_secondary_cores() {
+        Ok(num_cores) => {
+            state::state_manager().transition_to_multi_core_main();
+            info!("Cores online: {}", num_cores);
+        }
+        Err(msg) => warn!("Error starting the secondary cores: {}", msg),
+    }
+
     info!("MMU online:");
     memory::mmu::kernel_print_mappings();
 
@@ -88,7 +174,15 @@ fn kernel_main() -> ! {
 
     info!(
         "Architectural timer resolution: {} ns",
-        time::time_manager().resolution().as_nanos()
+        time::arch_time_manager().resolution().as_nanos()
+    );
+
+    info!("Kernel tick period: {} ms", time::tick_period().as_millis());
+
+    info!(
+        "Uptime cross-check: architectural timer {:?}, system timer {:?}",
+        time::arch_time_manager().uptime(),
+        bsp::system_timer().uptime()
     );
 
     info!("Drivers loaded:");
@@ -102,7 +196,19 @@ fn kernel_main() -> ! {
 
     info!("Registered IRQ handlers:");
     bsp::exception::asynchronous::irq_manager().print_handler();
+    bsp::exception::asynchronous::aux_irq_manager().print_handler();
+
+    info!("Scheduler statistics:");
+    thread::print_stats();
+
+    info!("IRQ statistics:");
+    bsp::exception::asynchronous::irq_manager().print_stats();
+    bsp::exception::asynchronous::aux_irq_manager().print_stats();
+
+    info!("Echoing input now. Set the wall-clock time with: date YYYY-MM-DDTHH:MM:SSZ");
+    if let Err(msg) = EXECUTOR.block_on(console_commands()) {
+        warn!("Error running the console commands: {}", msg);
+    }
 
-    info!("Echoing input now");
     cpu::wait_forever();
 }
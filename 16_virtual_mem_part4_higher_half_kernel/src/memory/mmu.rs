@@ -13,7 +13,7 @@ mod translation_table;
 mod types;
 
 use crate::{
-    bsp,
+    bsp, exception,
     memory::{Address, Physical, Virtual},
     synchronization, warn,
 };
@@ -67,6 +67,9 @@ pub mod interface {
             &self,
             virt: Address<Virtual>,
         ) -> Result<Address<Physical>, TranslationError>;
+
+        /// Invalidate all cached translations of the executing core.
+        fn invalidate_local_tlb(&self);
     }
 }
 
@@ -118,6 +121,11 @@ unsafe fn kernel_map_pages_at_unchecked(
     Ok(())
 }
 
+/// Cross-core call target for [`kernel_tlb_shootdown()`].
+fn invalidate_local_tlb(_arg: usize) {
+    arch_mmu::mmu().invalidate_local_tlb();
+}
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
@@ -252,6 +260,16 @@ pub fn try_virt_to_phys(virt: Address<Virtual>) -> Result<Address<Physical>, Tra
     arch_mmu::mmu().try_virt_to_phys(virt)
 }
 
+/// Invalidate the cached kernel translations on all online cores.
+///
+/// Must be called after existing kernel mappings were changed or removed. Returns after every
+/// online core has invalidated its TLB.
+pub fn kernel_tlb_shootdown() -> Result<(), &'static str> {
+    arch_mmu::mmu().invalidate_local_tlb();
+
+    exception::asynchronous::ipi::call_on_others(invalidate_local_tlb, 0)
+}
+
 /// Enable the MMU and data + instruction caching.
 ///
 /// # Safety
@@ -2,10 +2,34 @@
 //
 // Copyright (c) 2018-2021 Andre Richter <andre.o.richter@gmail.com>
 
-//! A panic handler that infinitely waits.
+//! A panic handler that halts all cores and infinitely waits, or resets the board if configured so.
+//!
+//! Only the first core that panics prints. Panics on other cores park them silently, because the
+//! panic console is not synchronized.
 
-use crate::{bsp, cpu, exception};
-use core::{fmt, panic::PanicInfo};
+use crate::{bsp, cpu, exception, state, watchdog};
+use core::{
+    fmt,
+    panic::PanicInfo,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Value of `PANICKING_CORE` while no core has panicked.
+const NO_CORE: usize = usize::MAX;
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// The core that panicked first.
+static PANICKING_CORE: AtomicUsize = AtomicUsize::new(NO_CORE);
+
+/// The number of nested panics on the panicking core.
+static PANIC_DEPTH: AtomicUsize = AtomicUsize::new(0);
 
 //--------------------------------------------------------------------------------------------------
 // Private Code
@@ -25,6 +49,10 @@ fn _panic_print(args: fmt::Arguments) {
 fn _panic_exit() -> ! {
     #[cfg(not(test_build))]
     {
+        if watchdog::reboot_on_panic() {
+            watchdog::reboot()
+        }
+
         cpu::wait_forever()
     }
 
@@ -44,14 +72,52 @@ macro_rules! panic_println {
     })
 }
 
+/// Let only the first panicking core through. Returns `false` if the executing core panicked
+/// while already handling a panic.
+fn claim_panic(core: usize) -> bool {
+    match PANICKING_CORE.compare_exchange(NO_CORE, core, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => {
+            PANIC_DEPTH.store(1, Ordering::Relaxed);
+            true
+        }
+        Err(first) if first == core => {
+            PANIC_DEPTH.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+        // Another core is already handling a panic and will stop this one.
+        Err(_) => cpu::wait_forever(),
+    }
+}
+
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     unsafe { exception::asynchronous::local_irq_mask() };
 
+    let core = cpu::smp::core_id::<usize>();
+
+    if !claim_panic(core) {
+        match PANIC_DEPTH.load(Ordering::Relaxed) {
+            2 => {
+                panic_println!("\nKernel panic on core {} while panicking", core);
+                _panic_exit()
+            }
+            // Printing might be what panicked, so exit without it.
+            3 => _panic_exit(),
+            // Exiting panicked as well.
+            _ => cpu::wait_forever(),
+        }
+    }
+
+    // Stop the other cores, so they can not interleave their output or keep the system running.
+    if cpu::smp::num_cores_online() > 1 {
+        exception::asynchronous::ipi::stop_other_cores();
+    }
+
+    let state = state::state_manager();
     if let Some(args) = info.message() {
-        panic_println!("\nKernel panic: {}", args);
+        panic_println!("\nKernel panic on core {} [{}]: {}", core, state, args);
     } else {
-        panic_println!("\nKernel panic!");
+        panic_println!("\nKernel panic on core {} [{}]!", core, state);
     }
 
     _panic_exit()
@@ -4,13 +4,61 @@
 
 //! Printing.
 
-use crate::{bsp, console};
-use core::fmt;
+use crate::{bsp, console, time};
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The timestamp of a log message.
+#[doc(hidden)]
+pub struct LogTimestamp;
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static WALL_CLOCK_TIMESTAMPS: AtomicBool = AtomicBool::new(false);
 
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
 
+/// Renders the wall-clock time if enabled and available, and the uptime otherwise.
+impl fmt::Display for LogTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use time::interface::{RealTimeClock, TimeManager};
+
+        if WALL_CLOCK_TIMESTAMPS.load(Ordering::Relaxed) {
+            if let Some(unix_time) = time::rtc::real_time_clock().unix_time() {
+                return write!(f, "{}", time::rtc::DateTime::from_unix_time(unix_time));
+            }
+        }
+
+        let timestamp = time::time_manager().uptime();
+        let timestamp_subsec_us = timestamp.subsec_micros();
+
+        write!(
+            f,
+            "{:>3}.{:03}{:03}",
+            timestamp.as_secs(),
+            timestamp_subsec_us / 1_000,
+            timestamp_subsec_us % 1_000
+        )
+    }
+}
+
+/// Select whether log messages carry ISO-8601 wall-clock timestamps instead of the uptime.
+///
+/// Wall-clock timestamps are only used once the real-time clock has been set.
+pub fn set_wall_clock_timestamps(enable: bool) {
+    WALL_CLOCK_TIMESTAMPS.store(enable, Ordering::Relaxed);
+}
+
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
     use console::interface::Write;
@@ -41,31 +89,15 @@ macro_rules! println {
 #[macro_export]
 macro_rules! info {
     ($string:expr) => ({
-        #[allow(unused_imports)]
-        use crate::time::interface::TimeManager;
-
-        let timestamp = $crate::time::time_manager().uptime();
-        let timestamp_subsec_us = timestamp.subsec_micros();
-
         $crate::print::_print(format_args_nl!(
-            concat!("[  {:>3}.{:03}{:03}] ", $string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000
+            concat!("[  {}] ", $string),
+            $crate::print::LogTimestamp
         ));
     });
     ($format_string:expr, $($arg:tt)*) => ({
-        #[allow(unused_imports)]
-        use crate::time::interface::TimeManager;
-
-        let timestamp = $crate::time::time_manager().uptime();
-        let timestamp_subsec_us = timestamp.subsec_micros();
-
         $crate::print::_print(format_args_nl!(
-            concat!("[  {:>3}.{:03}{:03}] ", $format_string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000,
+            concat!("[  {}] ", $format_string),
+            $crate::print::LogTimestamp,
             $($arg)*
         ));
     })
@@ -75,31 +107,15 @@ macro_rules! info {
 #[macro_export]
 macro_rules! warn {
     ($string:expr) => ({
-        #[allow(unused_imports)]
-        use crate::time::interface::TimeManager;
-
-        let timestamp = $crate::time::time_manager().uptime();
-        let timestamp_subsec_us = timestamp.subsec_micros();
-
         $crate::print::_print(format_args_nl!(
-            concat!("[W {:>3}.{:03}{:03}] ", $string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000
+            concat!("[W {}] ", $string),
+            $crate::print::LogTimestamp
         ));
     });
     ($format_string:expr, $($arg:tt)*) => ({
-        #[allow(unused_imports)]
-        use crate::time::interface::TimeManager;
-
-        let timestamp = $crate::time::time_manager().uptime();
-        let timestamp_subsec_us = timestamp.subsec_micros();
-
         $crate::print::_print(format_args_nl!(
-            concat!("[W {:>3}.{:03}{:03}] ", $format_string),
-            timestamp.as_secs(),
-            timestamp_subsec_us / 1_000,
-            timestamp_subsec_us % 1_000,
+            concat!("[W {}] ", $format_string),
+            $crate::print::LogTimestamp,
             $($arg)*
         ));
     })
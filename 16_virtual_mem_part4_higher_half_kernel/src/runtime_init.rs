@@ -4,7 +4,7 @@
 
 //! Rust runtime initialization code.
 
-use crate::{bsp, memory};
+use crate::{bsp, cpu, memory};
 
 //--------------------------------------------------------------------------------------------------
 // Private Code
@@ -24,8 +24,8 @@ unsafe fn zero_bss() {
 // Public Code
 //--------------------------------------------------------------------------------------------------
 
-/// Equivalent to `crt0` or `c0` code in C/C++ world. Clears the `bss` section, then jumps to kernel
-/// init code.
+/// Equivalent to `crt0` or `c0` code in C/C++ world. Clears the `bss` section and sets up the boot
+/// core's per-core data, then jumps to kernel init code.
 ///
 /// # Safety
 ///
@@ -37,5 +37,18 @@ pub unsafe fn runtime_init() -> ! {
     }
 
     zero_bss();
+    cpu::percpu::init();
     kernel_init()
 }
+
+/// The counterpart of `runtime_init()` for the secondary cores. The `bss` section has already been
+/// zeroed by the boot core, so only the per-core data needs to be set up.
+///
+/// # Safety
+///
+/// - Must only be called by the secondary cores' boot code.
+#[no_mangle]
+pub unsafe fn secondary_runtime_init() -> ! {
+    cpu::percpu::init();
+    cpu::smp::secondary_core_init()
+}
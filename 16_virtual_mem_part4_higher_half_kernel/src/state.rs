@@ -4,7 +4,10 @@
 
 //! State information about the kernel itself.
 
-use core::sync::atomic::{AtomicU8, Ordering};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
 
 //--------------------------------------------------------------------------------------------------
 // Private Definitions
@@ -89,4 +92,32 @@ impl StateManager {
             panic!("transition_to_single_core_main() called while state != Init");
         }
     }
+
+    /// Transition from SingleCoreMain to MultiCoreMain.
+    pub fn transition_to_multi_core_main(&self) {
+        if self
+            .0
+            .compare_exchange(
+                Self::SINGLE_CORE_MAIN,
+                Self::MULTI_CORE_MAIN,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            panic!("transition_to_multi_core_main() called while state != SingleCoreMain");
+        }
+    }
+}
+
+impl fmt::Display for StateManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.state() {
+            State::Init => "Init",
+            State::SingleCoreMain => "SingleCoreMain",
+            State::MultiCoreMain => "MultiCoreMain",
+        };
+
+        write!(f, "{}", name)
+    }
 }
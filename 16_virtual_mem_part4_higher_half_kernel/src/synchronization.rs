@@ -4,18 +4,41 @@
 
 //! Synchronization primitives.
 //!
+//! The locks spin while they are contended. Waiting for something that might take longer is done
+//! with the blocking primitives [`WaitQueue`], [`Semaphore`], [`Condvar`] and [`Event`].
+//!
 //! # Resources
 //!
 //!   - <https://doc.rust-lang.org/book/ch16-04-extensible-concurrency-sync-and-send.html>
 //!   - <https://stackoverflow.com/questions/59428096/understanding-the-send-trait>
 //!   - <https://doc.rust-lang.org/std/cell/index.html>
+//!   - <https://mirrors.edge.kernel.org/pub/linux/kernel/people/paulmck/perfbook/perfbook.html>
+
+mod blocking;
+#[cfg(any(feature = "lock_order", test))]
+mod lock_order;
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
 
-use core::cell::UnsafeCell;
+/// A ticket lock without data. Cores are granted the lock in the order in which they asked for it.
+struct TicketLock {
+    next_ticket: AtomicU32,
+    now_serving: AtomicU32,
+}
 
 //--------------------------------------------------------------------------------------------------
 // Public Definitions
 //--------------------------------------------------------------------------------------------------
 
+pub use blocking::{Condvar, Event, Semaphore, WaitQueue};
+
 /// Synchronization interfaces.
 pub mod interface {
 
@@ -45,17 +68,35 @@ pub mod interface {
     }
 }
 
-/// A pseudo-lock for teaching purposes.
+/// A spinlock that is safe to share between cores.
 ///
-/// In contrast to a real Mutex implementation, does not protect against concurrent access from
-/// other cores to the contained data. This part is preserved for later lessons.
+/// Does not mask IRQs, so it must not be used for data that is also accessed from IRQ handlers.
+/// Use [`IRQSafeSpinLock`] for that.
+pub struct SpinLock<T>
+where
+    T: ?Sized,
+{
+    lock: TicketLock,
+    data: UnsafeCell<T>,
+}
+
+/// A spinlock that additionally masks IRQs on the executing core while it is held.
+pub struct IRQSafeSpinLock<T>
+where
+    T: ?Sized,
+{
+    inner: SpinLock<T>,
+}
+
+/// A reader-writer spinlock that masks IRQs on the executing core while it is held.
 ///
-/// The lock will only be used as long as it is safe to do so, i.e. as long as the kernel is
-/// executing on a single core.
-pub struct IRQSafeNullLock<T>
+/// In contrast to [`InitStateLock`], writing is allowed at any time.
+pub struct IRQSafeRwLock<T>
 where
     T: ?Sized,
 {
+    /// The number of readers, or [`IRQSafeRwLock::WRITER`] if a writer holds the lock.
+    state: AtomicU32,
     data: UnsafeCell<T>,
 }
 
@@ -69,17 +110,94 @@ where
     data: UnsafeCell<T>,
 }
 
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl TicketLock {
+    const fn new() -> Self {
+        Self {
+            next_ticket: AtomicU32::new(0),
+            now_serving: AtomicU32::new(0),
+        }
+    }
+
+    fn acquire(&self) {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+
+        while self.now_serving.load(Ordering::Acquire) != ticket {
+            core::hint::spin_loop();
+        }
+    }
+
+    fn release(&self) {
+        // Only the lock holder writes `now_serving`, so there is no need for an atomic increment.
+        let next = self.now_serving.load(Ordering::Relaxed).wrapping_add(1);
+
+        self.now_serving.store(next, Ordering::Release);
+    }
+}
+
+/// Notify the lock order checker that `lock` is about to be acquired.
+#[inline(always)]
+fn lock_order_acquire<T: ?Sized>(_lock: &T) {
+    #[cfg(feature = "lock_order")]
+    lock_order::acquire(_lock as *const T as *const () as usize);
+}
+
+/// Notify the lock order checker that `lock` has been released.
+#[inline(always)]
+fn lock_order_release<T: ?Sized>(_lock: &T) {
+    #[cfg(feature = "lock_order")]
+    lock_order::release(_lock as *const T as *const () as usize);
+}
+
 //--------------------------------------------------------------------------------------------------
 // Public Code
 //--------------------------------------------------------------------------------------------------
 
-unsafe impl<T> Send for IRQSafeNullLock<T> where T: ?Sized + Send {}
-unsafe impl<T> Sync for IRQSafeNullLock<T> where T: ?Sized + Send {}
+unsafe impl<T> Send for SpinLock<T> where T: ?Sized + Send {}
+unsafe impl<T> Sync for SpinLock<T> where T: ?Sized + Send {}
+
+impl<T> SpinLock<T> {
+    /// Create an instance.
+    pub const fn new(data: T) -> Self {
+        Self {
+            lock: TicketLock::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T> IRQSafeSpinLock<T> {
+    /// Create an instance.
+    pub const fn new(data: T) -> Self {
+        Self {
+            inner: SpinLock::new(data),
+        }
+    }
+
+    /// Grants immutable access to the encapsulated data without taking the lock.
+    ///
+    /// # Safety
+    ///
+    /// - Intended for the panic path only, where the lock might be held by a core that never
+    ///   releases it. The lock holder can modify the data concurrently.
+    pub unsafe fn get_unlocked(&self) -> &T {
+        &*self.inner.data.get()
+    }
+}
+
+unsafe impl<T> Send for IRQSafeRwLock<T> where T: ?Sized + Send {}
+unsafe impl<T> Sync for IRQSafeRwLock<T> where T: ?Sized + Send + Sync {}
+
+impl<T> IRQSafeRwLock<T> {
+    const WRITER: u32 = 1 << 31;
 
-impl<T> IRQSafeNullLock<T> {
     /// Create an instance.
     pub const fn new(data: T) -> Self {
         Self {
+            state: AtomicU32::new(0),
             data: UnsafeCell::new(data),
         }
     }
@@ -102,16 +220,87 @@ impl<T> InitStateLock<T> {
 //------------------------------------------------------------------------------
 use crate::{exception, state};
 
-impl<T> interface::Mutex for IRQSafeNullLock<T> {
+impl<T> interface::Mutex for SpinLock<T> {
     type Data = T;
 
     fn lock<R>(&self, f: impl FnOnce(&mut Self::Data) -> R) -> R {
-        // In a real lock, there would be code encapsulating this line that ensures that this
-        // mutable reference will ever only be given out once at a time.
+        lock_order_acquire(self);
+        self.lock.acquire();
+
         let data = unsafe { &mut *self.data.get() };
+        let ret = f(data);
+
+        self.lock.release();
+        lock_order_release(self);
+
+        ret
+    }
+}
+
+impl<T> interface::Mutex for IRQSafeSpinLock<T> {
+    type Data = T;
 
-        // Execute the closure while IRQs are masked.
-        exception::asynchronous::exec_with_irq_masked(|| f(data))
+    fn lock<R>(&self, f: impl FnOnce(&mut Self::Data) -> R) -> R {
+        // Mask IRQs before taking the lock, so that a handler on this core can not spin on it.
+        exception::asynchronous::exec_with_irq_masked(|| self.inner.lock(f))
+    }
+}
+
+impl<T> interface::ReadWriteEx for IRQSafeRwLock<T> {
+    type Data = T;
+
+    fn write<R>(&self, f: impl FnOnce(&mut Self::Data) -> R) -> R {
+        exception::asynchronous::exec_with_irq_masked(|| {
+            lock_order_acquire(self);
+            while self
+                .state
+                .compare_exchange_weak(0, Self::WRITER, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                core::hint::spin_loop();
+            }
+
+            let data = unsafe { &mut *self.data.get() };
+            let ret = f(data);
+
+            self.state.store(0, Ordering::Release);
+            lock_order_release(self);
+
+            ret
+        })
+    }
+
+    fn read<R>(&self, f: impl FnOnce(&Self::Data) -> R) -> R {
+        exception::asynchronous::exec_with_irq_masked(|| {
+            lock_order_acquire(self);
+            loop {
+                let state = self.state.load(Ordering::Relaxed);
+
+                if (state & Self::WRITER) == 0
+                    && self
+                        .state
+                        .compare_exchange_weak(
+                            state,
+                            state + 1,
+                            Ordering::Acquire,
+                            Ordering::Relaxed,
+                        )
+                        .is_ok()
+                {
+                    break;
+                }
+
+                core::hint::spin_loop();
+            }
+
+            let data = unsafe { &*self.data.get() };
+            let ret = f(data);
+
+            self.state.fetch_sub(1, Ordering::Release);
+            lock_order_release(self);
+
+            ret
+        })
     }
 }
 
@@ -156,4 +345,32 @@ mod tests {
 
         assert_eq!(size_of::<InitStateLock<u64>>(), size_of::<u64>());
     }
+
+    /// The spinlocks must be released after use, so that they can be taken again.
+    #[kernel_test]
+    fn spin_locks_are_released() {
+        use interface::Mutex;
+
+        let lock = SpinLock::new(0);
+        lock.lock(|x| *x += 1);
+        lock.lock(|x| *x += 1);
+        assert_eq!(lock.lock(|x| *x), 2);
+
+        let irq_safe_lock = IRQSafeSpinLock::new(0);
+        irq_safe_lock.lock(|x| *x += 1);
+        assert_eq!(irq_safe_lock.lock(|x| *x), 1);
+    }
+
+    /// The reader-writer lock must be writable and readable after init.
+    #[kernel_test]
+    fn rw_lock_is_usable() {
+        use interface::ReadWriteEx;
+
+        let lock = IRQSafeRwLock::new(0);
+        lock.write(|x| *x = 42);
+        assert_eq!(lock.read(|x| *x), 42);
+
+        lock.write(|x| *x += 1);
+        assert_eq!(lock.read(|x| *x), 43);
+    }
 }
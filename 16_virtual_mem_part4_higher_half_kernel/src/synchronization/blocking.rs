@@ -0,0 +1,310 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Blocking synchronization primitives.
+//!
+//! Waiting threads are parked, so that other threads can run meanwhile. On cores that do not use
+//! threads, waiting falls back to `wfe`, which is why signaling always sends an event as well.
+//!
+//! Signaling is safe from IRQ handlers. Waiting is not.
+
+use super::{interface::Mutex, IRQSafeSpinLock};
+use crate::{cpu, thread};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The threads waiting on a queue, in the order in which they started waiting.
+#[derive(Copy, Clone)]
+struct WaiterList {
+    entries: [Option<thread::ThreadId>; thread::MAX_THREADS],
+    len: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A queue of threads that wait for a condition to become true.
+pub struct WaitQueue {
+    waiters: IRQSafeSpinLock<WaiterList>,
+}
+
+/// A counting semaphore.
+pub struct Semaphore {
+    count: AtomicUsize,
+    queue: WaitQueue,
+}
+
+/// A condition variable for data protected by a [`Mutex`].
+pub struct Condvar {
+    /// Incremented on each notification.
+    sequence: AtomicUsize,
+    queue: WaitQueue,
+}
+
+/// A flag that threads can wait for. Stays set until it is reset.
+pub struct Event {
+    is_set: AtomicBool,
+    queue: WaitQueue,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl WaiterList {
+    const fn new() -> Self {
+        Self {
+            entries: [None; thread::MAX_THREADS],
+            len: 0,
+        }
+    }
+
+    fn position(&self, id: thread::ThreadId) -> Option<usize> {
+        self.entries[..self.len].iter().position(|x| *x == Some(id))
+    }
+
+    /// Append a waiter, unless it is already waiting.
+    fn push_back(&mut self, id: thread::ThreadId) {
+        if self.position(id).is_some() {
+            return;
+        }
+
+        // Each thread waits on at most one queue at a time, so the list can not overflow.
+        self.entries[self.len] = Some(id);
+        self.len += 1;
+    }
+
+    fn remove(&mut self, id: thread::ThreadId) {
+        if let Some(i) = self.position(id) {
+            self.entries.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+            self.entries[self.len] = None;
+        }
+    }
+
+    fn pop_front(&mut self) -> Option<thread::ThreadId> {
+        let id = self.entries[..self.len].first().copied().flatten()?;
+        self.remove(id);
+
+        Some(id)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl WaitQueue {
+    /// Create an instance.
+    pub const fn new() -> Self {
+        Self {
+            waiters: IRQSafeSpinLock::new(WaiterList::new()),
+        }
+    }
+
+    /// Block until `condition` returns true.
+    ///
+    /// `condition` is evaluated once more after the executing thread was queued, so a notification
+    /// can not get lost between evaluating it and parking. It must not block, and it might be
+    /// evaluated any number of times.
+    pub fn wait_until(&self, mut condition: impl FnMut() -> bool) {
+        loop {
+            if condition() {
+                return;
+            }
+
+            let id = match thread::current() {
+                Some(x) => x,
+                None => {
+                    cpu::wait_for_event();
+                    continue;
+                }
+            };
+
+            self.waiters.lock(|w| w.push_back(id));
+
+            if condition() {
+                self.waiters.lock(|w| w.remove(id));
+                return;
+            }
+
+            thread::park();
+
+            // Not popped if the wake-up was spurious.
+            self.waiters.lock(|w| w.remove(id));
+        }
+    }
+
+    /// Wake the longest waiting thread. Returns `false` if no thread was waiting.
+    pub fn notify_one(&self) -> bool {
+        let waiter = self.waiters.lock(|w| w.pop_front());
+
+        if let Some(id) = waiter {
+            thread::unpark(id);
+        }
+        cpu::smp::send_event();
+
+        waiter.is_some()
+    }
+
+    /// Wake all waiting threads. Returns how many threads were waiting.
+    pub fn notify_all(&self) -> usize {
+        let waiters = self
+            .waiters
+            .lock(|w| core::mem::replace(w, WaiterList::new()));
+
+        for id in waiters.entries[..waiters.len].iter().flatten() {
+            thread::unpark(*id);
+        }
+        cpu::smp::send_event();
+
+        waiters.len
+    }
+}
+
+impl Semaphore {
+    /// Create an instance with the given number of permits.
+    pub const fn new(count: usize) -> Self {
+        Self {
+            count: AtomicUsize::new(count),
+            queue: WaitQueue::new(),
+        }
+    }
+
+    /// Take a permit if one is available.
+    pub fn try_acquire(&self) -> bool {
+        let mut count = self.count.load(Ordering::Relaxed);
+
+        loop {
+            if count == 0 {
+                return false;
+            }
+
+            match self.count.compare_exchange_weak(
+                count,
+                count - 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(x) => count = x,
+            }
+        }
+    }
+
+    /// Take a permit, blocking until one is available.
+    pub fn acquire(&self) {
+        self.queue.wait_until(|| self.try_acquire())
+    }
+
+    /// Add a permit and wake a waiting thread.
+    pub fn release(&self) {
+        self.count.fetch_add(1, Ordering::Release);
+        self.queue.notify_one();
+    }
+
+    /// The number of available permits.
+    pub fn available(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl Condvar {
+    /// Create an instance.
+    pub const fn new() -> Self {
+        Self {
+            sequence: AtomicUsize::new(0),
+            queue: WaitQueue::new(),
+        }
+    }
+
+    /// Lock `mutex` and call `f` on its data until `f` returns `Some`, and return its value.
+    ///
+    /// Blocks between the calls until the condition variable is notified. Notifications must be
+    /// sent after the data was changed, and the mutex was unlocked again.
+    pub fn wait_until<M, R>(&self, mutex: &M, mut f: impl FnMut(&mut M::Data) -> Option<R>) -> R
+    where
+        M: Mutex,
+    {
+        loop {
+            let sequence = self.sequence.load(Ordering::Acquire);
+
+            if let Some(x) = mutex.lock(&mut f) {
+                return x;
+            }
+
+            self.queue
+                .wait_until(|| self.sequence.load(Ordering::Acquire) != sequence);
+        }
+    }
+
+    /// Wake one waiting thread.
+    pub fn notify_one(&self) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        self.queue.notify_one();
+    }
+
+    /// Wake all waiting threads.
+    pub fn notify_all(&self) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        self.queue.notify_all();
+    }
+}
+
+impl Event {
+    /// Create an instance that is not set.
+    pub const fn new() -> Self {
+        Self {
+            is_set: AtomicBool::new(false),
+            queue: WaitQueue::new(),
+        }
+    }
+
+    /// Set the flag and wake all waiting threads.
+    pub fn set(&self) {
+        self.is_set.store(true, Ordering::Release);
+        self.queue.notify_all();
+    }
+
+    /// Clear the flag.
+    pub fn reset(&self) {
+        self.is_set.store(false, Ordering::Relaxed);
+    }
+
+    /// Check if the flag is set.
+    pub fn is_set(&self) -> bool {
+        self.is_set.load(Ordering::Acquire)
+    }
+
+    /// Block until the flag is set.
+    pub fn wait(&self) {
+        self.queue.wait_until(|| self.is_set())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Waiting for a condition that already holds must not block.
+    #[kernel_test]
+    fn waiting_for_a_true_condition_returns() {
+        let semaphore = Semaphore::new(1);
+        semaphore.acquire();
+        assert!(!semaphore.try_acquire());
+
+        let event = Event::new();
+        event.set();
+        event.wait();
+    }
+}
@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Lock order debugging.
+//!
+//! Tracks the locks that each core currently holds. Whenever a lock is acquired while other locks
+//! are held, the "held before" pairs are recorded. Acquiring two locks in both orders, or acquiring
+//! a lock that the core already holds, can deadlock and is reported with a panic.
+//!
+//! Locks are identified by their address, so only locks with a stable address, i.e. statics, are
+//! tracked reliably. Only compiled in with the `lock_order` feature.
+
+use super::TicketLock;
+use crate::{bsp, cpu, exception};
+use core::cell::UnsafeCell;
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The maximum number of locks a single core can hold at the same time.
+const MAX_HELD: usize = 8;
+
+/// The maximum number of recorded lock pairs. Pairs beyond are not checked.
+const MAX_PAIRS: usize = 64;
+
+/// The locks held by a single core, in acquisition order.
+#[derive(Copy, Clone)]
+struct HeldLocks {
+    locks: [usize; MAX_HELD],
+    len: usize,
+}
+
+/// Locks that have been observed to be held while another one was acquired.
+struct LockPairs {
+    lock: TicketLock,
+    pairs: UnsafeCell<([(usize, usize); MAX_PAIRS], usize)>,
+}
+
+/// Each core only ever accesses its own entry, and only with IRQs masked.
+struct PerCoreHeldLocks(UnsafeCell<[HeldLocks; bsp::cpu::NUM_CORES]>);
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static HELD_LOCKS: PerCoreHeldLocks = PerCoreHeldLocks(UnsafeCell::new(
+    [HeldLocks {
+        locks: [0; MAX_HELD],
+        len: 0,
+    }; bsp::cpu::NUM_CORES],
+));
+
+static LOCK_PAIRS: LockPairs = LockPairs::new();
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+unsafe impl Sync for PerCoreHeldLocks {}
+unsafe impl Sync for LockPairs {}
+
+impl LockPairs {
+    const fn new() -> Self {
+        Self {
+            lock: TicketLock::new(),
+            pairs: UnsafeCell::new(([(0, 0); MAX_PAIRS], 0)),
+        }
+    }
+
+    /// Record that `before` was held while `after` was acquired. Returns an error if the opposite
+    /// order has been observed earlier.
+    fn record(&self, before: usize, after: usize) -> Result<(), ()> {
+        self.lock.acquire();
+
+        let (pairs, len) = unsafe { &mut *self.pairs.get() };
+        let recorded = &pairs[..*len];
+
+        let result = if recorded.contains(&(after, before)) {
+            Err(())
+        } else {
+            if !recorded.contains(&(before, after)) && *len < MAX_PAIRS {
+                pairs[*len] = (before, after);
+                *len += 1;
+            }
+
+            Ok(())
+        };
+
+        self.lock.release();
+
+        result
+    }
+}
+
+/// The locks held by the executing core.
+///
+/// # Safety
+///
+/// - IRQs must be masked while the reference is alive.
+unsafe fn held_locks() -> &'static mut HeldLocks {
+    let all_cores = HELD_LOCKS.0.get() as *mut HeldLocks;
+
+    &mut *all_cores.add(cpu::smp::core_id::<usize>())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Check and record the acquisition of `lock` by the executing core.
+pub fn acquire(lock: usize) {
+    exception::asynchronous::exec_with_irq_masked(|| {
+        let held = unsafe { held_locks() };
+
+        for &other in &held.locks[..held.len] {
+            if other == lock {
+                panic!("Lock {:#x} acquired recursively", lock);
+            }
+
+            if LOCK_PAIRS.record(other, lock).is_err() {
+                panic!("Lock order inversion between {:#x} and {:#x}", other, lock);
+            }
+        }
+
+        if held.len == MAX_HELD {
+            panic!(
+                "Too many locks held by core {}",
+                cpu::smp::core_id::<usize>()
+            );
+        }
+
+        held.locks[held.len] = lock;
+        held.len += 1;
+    })
+}
+
+/// Record the release of `lock` by the executing core.
+pub fn release(lock: usize) {
+    exception::asynchronous::exec_with_irq_masked(|| {
+        let held = unsafe { held_locks() };
+
+        if let Some(i) = held.locks[..held.len].iter().rposition(|&x| x == lock) {
+            held.locks.copy_within(i + 1..held.len, i);
+            held.len -= 1;
+        }
+    })
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Acquiring two locks in both orders must be detected, no matter how often the first order
+    /// was seen.
+    #[kernel_test]
+    fn lock_order_inversion_is_detected() {
+        let pairs = LockPairs::new();
+
+        assert_eq!(pairs.record(0x1000, 0x2000), Ok(()));
+        assert_eq!(pairs.record(0x1000, 0x2000), Ok(()));
+        assert_eq!(pairs.record(0x2000, 0x3000), Ok(()));
+        assert_eq!(pairs.record(0x2000, 0x1000), Err(()));
+    }
+
+    /// Nested locks must be tracked until they are released, in any order.
+    #[kernel_test]
+    fn held_locks_are_tracked() {
+        let held = || exception::asynchronous::exec_with_irq_masked(|| unsafe { held_locks().len });
+        let before = held();
+
+        acquire(0x1000);
+        acquire(0x2000);
+        assert_eq!(held(), before + 2);
+
+        release(0x1000);
+        release(0x2000);
+        assert_eq!(held(), before);
+    }
+}
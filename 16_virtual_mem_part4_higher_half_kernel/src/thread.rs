@@ -0,0 +1,888 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Kernel threads.
+//!
+//! Threads are spawned on the executing core and stay there. Each core has a run queue per
+//! [`Priority`] and always runs the longest-waiting thread of the highest priority that is ready.
+//! Threads of the same priority share the core in round-robin order:
+//!
+//! - Cooperatively, by calling [`yield_now()`], [`sleep()`] or [`JoinHandle::join()`].
+//! - Preemptively, once [`start_preemption()`] was called. At the end of each time slice, the
+//!   running thread is switched out if another thread of the same or a higher priority is ready. A
+//!   thread that becomes ready also preempts running threads of a lower priority right away.
+//!
+//! Preemption happens on the IRQ exit path, see [`preempt_on_irq_exit()`].
+//!
+//! [`park()`] and [`unpark()`] are the building blocks for the blocking primitives in
+//! [`crate::synchronization`].
+//!
+//! The code that runs on a core when it first uses threads, e.g. `kernel_main()`, becomes that
+//! core's initial thread. It keeps the stack it already runs on and never exits. Each core that
+//! uses threads additionally gets an idle thread, which runs when no other thread is ready.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "_arch/aarch64/thread.rs"]
+mod arch_thread;
+
+mod stack;
+
+use crate::{
+    bsp, cpu, exception, info, synchronization,
+    synchronization::IRQSafeSpinLock,
+    time::{timer_queue, Instant},
+};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The number of [`Priority`] levels.
+const NUM_PRIORITIES: usize = 3;
+
+/// Thread states.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum State {
+    /// The slot is unused.
+    Free,
+
+    /// Waits to be scheduled.
+    Ready,
+
+    /// Executes on its core.
+    Running,
+
+    /// Waits for another thread to finish, or to be unparked.
+    Blocked,
+
+    /// Waits for its wake-up timer.
+    Sleeping,
+
+    /// Returned from its entry function, but still runs on its stack until it is switched out.
+    Exiting,
+
+    /// Returned from its entry function and switched out for good. Waits to be joined.
+    Finished,
+}
+
+/// A kernel thread.
+struct Thread {
+    state: State,
+    priority: Priority,
+
+    /// The core that the thread runs on.
+    core: usize,
+
+    /// The entry function and its argument. `None` for a core's initial thread.
+    entry: Option<fn(usize) -> usize>,
+    arg: usize,
+
+    /// The value returned from the entry function.
+    result: usize,
+
+    /// Set once the join handle is dropped. The slot is released when the thread has finished.
+    detached: bool,
+
+    /// The thread that waits for this thread to finish.
+    joiner: Option<usize>,
+
+    /// Set if the thread was unparked while it was not parked. Its next park returns right away.
+    unpark_token: bool,
+
+    /// The timer that ends the thread's sleep.
+    wake_timer: Option<timer_queue::TimerId>,
+
+    /// `None` for a core's initial thread.
+    stack: Option<stack::Stack>,
+
+    /// Accumulated time spent running.
+    runtime: Duration,
+
+    /// How often the thread was switched in.
+    switches: u64,
+
+    context: arch_thread::Context,
+}
+
+/// A FIFO of thread indices.
+#[derive(Copy, Clone)]
+struct RunQueue {
+    entries: [usize; MAX_THREADS],
+    head: usize,
+    len: usize,
+}
+
+/// The scheduling state of a core.
+#[derive(Copy, Clone)]
+struct CoreScheduler {
+    /// The thread that is running on the core.
+    current: Option<usize>,
+
+    idle: Option<usize>,
+    run_queues: [RunQueue; NUM_PRIORITIES],
+
+    /// Set if the running thread shall be switched out on the next IRQ exit.
+    need_resched: bool,
+
+    /// When the running thread was switched in.
+    switched_in_at: Option<Instant>,
+
+    context_switches: u64,
+}
+
+/// All threads of all cores.
+struct ThreadTable {
+    threads: [Thread; MAX_THREADS],
+    cores: [CoreScheduler; bsp::cpu::NUM_CORES],
+}
+
+/// A copy of a thread's statistics, taken for printing.
+#[derive(Copy, Clone)]
+struct ThreadStats {
+    index: usize,
+    core: usize,
+    kind: &'static str,
+    priority: Priority,
+    state: State,
+    runtime: Duration,
+    switches: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The maximum number of threads, including the initial and idle thread of each core.
+pub const MAX_THREADS: usize = stack::NUM_STACKS + bsp::cpu::NUM_CORES;
+
+/// Thread priorities.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Identifies a thread.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ThreadId(usize);
+
+/// An owned permission to join a thread. The thread is detached when the handle is dropped.
+pub struct JoinHandle {
+    index: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static THREAD_TABLE: IRQSafeSpinLock<ThreadTable> = IRQSafeSpinLock::new(ThreadTable::new());
+
+static PREEMPTION_STARTED: AtomicBool = AtomicBool::new(false);
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+impl State {
+    fn name(self) -> &'static str {
+        match self {
+            State::Free => "Free",
+            State::Ready => "Ready",
+            State::Running => "Running",
+            State::Blocked => "Blocked",
+            State::Sleeping => "Sleeping",
+            State::Exiting => "Exiting",
+            State::Finished => "Finished",
+        }
+    }
+}
+
+impl RunQueue {
+    const fn new() -> Self {
+        Self {
+            entries: [0; MAX_THREADS],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push_back(&mut self, index: usize) {
+        assert!(self.len < MAX_THREADS);
+
+        self.entries[(self.head + self.len) % MAX_THREADS] = index;
+        self.len += 1;
+    }
+
+    fn pop_front(&mut self) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let index = self.entries[self.head];
+        self.head = (self.head + 1) % MAX_THREADS;
+        self.len -= 1;
+
+        Some(index)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl CoreScheduler {
+    const fn new() -> Self {
+        Self {
+            current: None,
+            idle: None,
+            run_queues: [RunQueue::new(); NUM_PRIORITIES],
+            need_resched: false,
+            switched_in_at: None,
+            context_switches: 0,
+        }
+    }
+
+    /// The highest priority with a ready thread.
+    fn highest_ready_priority(&self) -> Option<usize> {
+        (0..NUM_PRIORITIES)
+            .rev()
+            .find(|&p| !self.run_queues[p].is_empty())
+    }
+
+    /// Take the next thread to run. The idle thread if no other thread is ready.
+    fn pick_next(&mut self) -> Option<usize> {
+        match self.highest_ready_priority() {
+            Some(p) => self.run_queues[p].pop_front(),
+            None => self.idle,
+        }
+    }
+}
+
+impl ThreadTable {
+    const fn new() -> Self {
+        const FREE: Thread = Thread {
+            state: State::Free,
+            priority: Priority::Normal,
+            core: 0,
+            entry: None,
+            arg: 0,
+            result: 0,
+            detached: false,
+            joiner: None,
+            unpark_token: false,
+            wake_timer: None,
+            stack: None,
+            runtime: Duration::from_secs(0),
+            switches: 0,
+            context: arch_thread::Context::new(),
+        };
+
+        Self {
+            threads: [FREE; MAX_THREADS],
+            cores: [CoreScheduler::new(); bsp::cpu::NUM_CORES],
+        }
+    }
+
+    /// Return the slot of a thread that is no longer needed to the pool.
+    fn release(&mut self, index: usize) {
+        let thread = &mut self.threads[index];
+
+        if let Some(stack) = thread.stack.take() {
+            stack::free(stack);
+        }
+        thread.state = State::Free;
+    }
+
+    /// Find an unused slot and reset it.
+    fn free_slot(&mut self) -> Option<usize> {
+        let index = self
+            .threads
+            .iter()
+            .position(|t| t.state == State::Free || (t.state == State::Finished && t.detached))?;
+
+        self.release(index);
+
+        let thread = &mut self.threads[index];
+        thread.priority = Priority::Normal;
+        thread.entry = None;
+        thread.arg = 0;
+        thread.result = 0;
+        thread.detached = false;
+        thread.joiner = None;
+        thread.unpark_token = false;
+        thread.wake_timer = None;
+        thread.runtime = Duration::from_secs(0);
+        thread.switches = 0;
+
+        Some(index)
+    }
+
+    /// Prepare a slot for a thread that starts at `entry(arg)` on a stack from the pool.
+    fn new_thread(
+        &mut self,
+        core: usize,
+        entry: fn(usize) -> usize,
+        arg: usize,
+        priority: Priority,
+    ) -> Result<usize, &'static str> {
+        let index = self.free_slot().ok_or("Thread table full")?;
+        let stack = stack::alloc().ok_or("No thread stack available")?;
+
+        let thread = &mut self.threads[index];
+        thread.state = State::Ready;
+        thread.priority = priority;
+        thread.core = core;
+        thread.entry = Some(entry);
+        thread.arg = arg;
+        thread.context = arch_thread::Context::new_thread(stack.end_exclusive(), thread_start);
+        thread.stack = Some(stack);
+
+        Ok(index)
+    }
+
+    /// The index of the executing core's running thread. The caller becomes the core's initial
+    /// thread if the core has none yet, and the core gets its idle thread.
+    fn current_or_register(&mut self, core: usize) -> Result<usize, &'static str> {
+        if let Some(index) = self.cores[core].current {
+            return Ok(index);
+        }
+
+        let idle = self.new_thread(core, idle_loop, 0, Priority::Low)?;
+        self.threads[idle].detached = true;
+
+        let index = match self.free_slot() {
+            Some(x) => x,
+            None => {
+                self.release(idle);
+                return Err("Thread table full");
+            }
+        };
+
+        let thread = &mut self.threads[index];
+        thread.state = State::Running;
+        thread.core = core;
+        thread.detached = true;
+
+        self.cores[core].idle = Some(idle);
+        self.cores[core].current = Some(index);
+        self.cores[core].switched_in_at = Some(Instant::now());
+
+        Ok(index)
+    }
+
+    /// Append a ready thread to its core's run queue.
+    fn enqueue(&mut self, index: usize) {
+        let thread = &self.threads[index];
+
+        self.cores[thread.core].run_queues[thread.priority as usize].push_back(index);
+    }
+
+    /// Let the given core reschedule on its next IRQ exit. Other cores are interrupted for that.
+    fn request_resched(&mut self, core: usize) {
+        self.cores[core].need_resched = true;
+
+        if core != cpu::smp::core_id::<usize>() {
+            exception::asynchronous::ipi::kick(core);
+        }
+    }
+
+    /// Check if a ready thread of the given priority shall preempt the running thread of `core`.
+    fn preempts(&self, core: usize, priority: Priority) -> bool {
+        let sched = &self.cores[core];
+
+        match sched.current {
+            None => false,
+            Some(current) if sched.idle == Some(current) => true,
+            Some(current) => priority > self.threads[current].priority,
+        }
+    }
+
+    /// Queue a ready thread and preempt its core's running thread if it has a lower priority.
+    fn make_ready(&mut self, index: usize) {
+        let (core, priority) = (self.threads[index].core, self.threads[index].priority);
+
+        self.enqueue(index);
+
+        if self.preempts(core, priority) {
+            self.request_resched(core);
+        }
+    }
+
+    /// Make a blocked or sleeping thread ready.
+    fn wake(&mut self, index: usize) {
+        let thread = &mut self.threads[index];
+
+        if !matches!(thread.state, State::Blocked | State::Sleeping) {
+            return;
+        }
+        thread.state = State::Ready;
+        thread.wake_timer = None;
+
+        self.make_ready(index);
+    }
+
+    /// Account the running time of the thread that is switched out.
+    fn account_switch(&mut self, core: usize, prev: usize, next: usize) {
+        let now = Instant::now();
+        let sched = &mut self.cores[core];
+
+        if let Some(switched_in_at) = sched.switched_in_at.replace(now) {
+            self.threads[prev].runtime += now.duration_since(switched_in_at);
+        }
+        sched.context_switches += 1;
+        self.threads[next].switches += 1;
+    }
+}
+
+/// The idle thread of each core. Waits for interrupts, which preempt it as soon as there is work.
+fn idle_loop(_arg: usize) -> usize {
+    loop {
+        cpu::wait_for_interrupt();
+    }
+}
+
+/// Complete a switch on the executing core.
+///
+/// A thread that exited can only be marked finished once it no longer runs on its stack, which is
+/// after the next thread has taken over.
+fn finish_switch() {
+    let core = cpu::smp::core_id::<usize>();
+
+    THREAD_TABLE.lock(|table| {
+        for index in 0..MAX_THREADS {
+            let thread = &mut table.threads[index];
+            if thread.state != State::Exiting || thread.core != core {
+                continue;
+            }
+
+            thread.state = State::Finished;
+            if thread.detached {
+                table.release(index);
+            }
+        }
+    });
+}
+
+/// Switch from the executing thread to the next thread of the executing core.
+///
+/// `outgoing` runs under the thread table lock and returns the state that the executing thread is
+/// left in. `State::Running` keeps it running. A ready thread continues right away if it has the
+/// highest priority of all ready threads.
+///
+/// Does nothing on cores that do not use threads.
+fn switch_away(outgoing: impl FnOnce(&mut ThreadTable, usize) -> State) {
+    exception::asynchronous::exec_with_irq_masked(|| {
+        let core = cpu::smp::core_id::<usize>();
+
+        let switch = THREAD_TABLE.lock(|table| {
+            let current = table.cores[core].current?;
+
+            let state = outgoing(table, current);
+            if state == State::Running {
+                return None;
+            }
+
+            table.cores[core].need_resched = false;
+            table.threads[current].state = state;
+            if state == State::Ready && table.cores[core].idle != Some(current) {
+                table.enqueue(current);
+            }
+
+            let next = table.cores[core]
+                .pick_next()
+                .expect("No thread left to run");
+            table.threads[next].state = State::Running;
+            if next == current {
+                return None;
+            }
+
+            table.cores[core].current = Some(next);
+            table.account_switch(core, current, next);
+
+            let prev_context = &mut table.threads[current].context as *mut arch_thread::Context;
+            let next_context = &table.threads[next].context as *const arch_thread::Context;
+
+            Some((prev_context, next_context))
+        });
+
+        if let Some((prev_context, next_context)) = switch {
+            // The contexts live in a static, and only the executing core touches them while its
+            // threads are switched.
+            unsafe { arch_thread::switch(prev_context, next_context) };
+
+            finish_switch();
+        }
+    })
+}
+
+/// Make the executing core use threads. Returns an error if there are no resources left for it.
+fn register_current() -> Result<(), &'static str> {
+    let core = cpu::smp::core_id::<usize>();
+
+    THREAD_TABLE.lock(|table| table.current_or_register(core).map(|_| ()))
+}
+
+/// Mark the executing thread as exited and switch away for good.
+fn exit(result: usize) -> ! {
+    switch_away(|table, current| {
+        table.threads[current].result = result;
+
+        if let Some(joiner) = table.threads[current].joiner.take() {
+            table.wake(joiner);
+        }
+
+        State::Exiting
+    });
+
+    panic!("Exited thread was scheduled again")
+}
+
+/// The first code that runs in a new thread.
+///
+/// Entered with IRQs masked, from within `switch_away()` of the previous thread.
+extern "C" fn thread_start() -> ! {
+    finish_switch();
+
+    let core = cpu::smp::core_id::<usize>();
+    let (entry, arg) = THREAD_TABLE.lock(|table| {
+        let thread = &table.threads[table.cores[core].current.unwrap()];
+
+        (thread.entry.unwrap(), thread.arg)
+    });
+
+    // Kernel threads run with IRQs unmasked.
+    unsafe { exception::asynchronous::local_irq_unmask() };
+
+    exit(entry(arg))
+}
+
+/// End of a time slice. Preempts running threads that have ready peers.
+fn time_slice_expired(_id: timer_queue::TimerId) {
+    THREAD_TABLE.lock(|table| {
+        for core in 0..bsp::cpu::NUM_CORES {
+            let current = match table.cores[core].current {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let priority = table.threads[current].priority as usize;
+            let has_peer = match table.cores[core].highest_ready_priority() {
+                Some(p) => p >= priority || table.cores[core].idle == Some(current),
+                None => false,
+            };
+
+            if has_peer {
+                table.request_resched(core);
+            }
+        }
+    });
+}
+
+/// Wake the thread whose sleep ended.
+fn sleep_expired(id: timer_queue::TimerId) {
+    THREAD_TABLE.lock(|table| {
+        let index = table
+            .threads
+            .iter()
+            .position(|t| t.state == State::Sleeping && t.wake_timer == Some(id));
+
+        if let Some(index) = index {
+            table.wake(index);
+        }
+    });
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Priority {
+    /// The priority's name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+}
+
+/// Spawn a thread of [`Priority::Normal`] on the executing core that runs `entry(arg)`.
+pub fn spawn(entry: fn(usize) -> usize, arg: usize) -> Result<JoinHandle, &'static str> {
+    spawn_with_priority(entry, arg, Priority::Normal)
+}
+
+/// Spawn a thread of the given priority on the executing core that runs `entry(arg)`.
+///
+/// A thread of a higher priority than the executing thread takes over on the next IRQ exit, or
+/// when the executing thread yields.
+pub fn spawn_with_priority(
+    entry: fn(usize) -> usize,
+    arg: usize,
+    priority: Priority,
+) -> Result<JoinHandle, &'static str> {
+    let core = cpu::smp::core_id::<usize>();
+
+    THREAD_TABLE.lock(|table| {
+        table.current_or_register(core)?;
+
+        let index = table.new_thread(core, entry, arg, priority)?;
+        table.make_ready(index);
+
+        Ok(JoinHandle { index })
+    })
+}
+
+/// Let the next ready thread of the same or a higher priority run.
+///
+/// Returns immediately if there is none.
+pub fn yield_now() {
+    switch_away(|_, _| State::Ready)
+}
+
+/// Put the executing thread to sleep for at least the given duration.
+///
+/// Waking up relies on the timer IRQ. Spins on cores that can not use threads.
+pub fn sleep(duration: Duration) {
+    use crate::time::interface::TimeManager;
+
+    if register_current().is_err() {
+        crate::time::time_manager().spin_for(duration);
+        return;
+    }
+
+    switch_away(|table, current| {
+        match timer_queue::add_oneshot(duration, sleep_expired) {
+            Ok(id) => {
+                table.threads[current].wake_timer = Some(id);
+                State::Sleeping
+            }
+            // No timer left, so just give the others a chance.
+            Err(_) => State::Ready,
+        }
+    })
+}
+
+/// The executing thread. `None` on cores that do not use threads.
+pub fn current() -> Option<ThreadId> {
+    let core = cpu::smp::core_id::<usize>();
+
+    THREAD_TABLE.lock(|table| table.cores[core].current.map(ThreadId))
+}
+
+/// Block the executing thread until it is unparked.
+///
+/// Returns right away if the thread was unparked since it last parked. Might also return
+/// spuriously, so callers must check their wake-up condition in a loop. Waits for an event on
+/// cores that do not use threads.
+///
+/// Must not be called from IRQ handlers.
+pub fn park() {
+    if current().is_none() {
+        cpu::wait_for_event();
+        return;
+    }
+
+    switch_away(|table, current| {
+        let thread = &mut table.threads[current];
+
+        if thread.unpark_token {
+            thread.unpark_token = false;
+            return State::Running;
+        }
+
+        State::Blocked
+    })
+}
+
+/// Make a parked thread ready, or let its next [`park()`] return right away.
+///
+/// Safe to call from IRQ handlers.
+pub fn unpark(id: ThreadId) {
+    THREAD_TABLE.lock(|table| {
+        if table.threads[id.0].state == State::Blocked {
+            table.wake(id.0);
+        } else {
+            table.threads[id.0].unpark_token = true;
+        }
+    })
+}
+
+/// Start time slicing.
+///
+/// Registers a periodic timer, so it needs the timer IRQ handler to be registered.
+pub fn start_preemption(time_slice: Duration) -> Result<(), &'static str> {
+    if PREEMPTION_STARTED.swap(true, Ordering::Relaxed) {
+        return Err("Preemption already started");
+    }
+
+    timer_queue::add_periodic(time_slice, time_slice_expired)?;
+
+    Ok(())
+}
+
+/// Switch to another thread if the interrupted thread shall be preempted.
+///
+/// Called last on the IRQ exit path, after the interrupt controller was told that handling is
+/// complete. The interrupted thread continues once it is switched back in, and then returns from
+/// the exception.
+///
+/// Nested IRQs never switch, because the preempted handler's IRQ is still active at the interrupt
+/// controller.
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub fn preempt_on_irq_exit(_ic: &exception::asynchronous::IRQContext) {
+    if exception::asynchronous::is_in_nestable_handler() {
+        return;
+    }
+
+    let core = cpu::smp::core_id::<usize>();
+
+    if THREAD_TABLE.lock(|table| table.cores[core].need_resched) {
+        yield_now();
+    }
+}
+
+/// Print the scheduler statistics of all cores.
+pub fn print_stats() {
+    const NO_STATS: Option<ThreadStats> = None;
+    let mut stats = [NO_STATS; MAX_THREADS];
+    let mut context_switches = [0; bsp::cpu::NUM_CORES];
+
+    // Copy the statistics first, so the table is not locked while printing.
+    THREAD_TABLE.lock(|table| {
+        for (index, thread) in table.threads.iter().enumerate() {
+            if thread.state == State::Free {
+                continue;
+            }
+
+            let sched = &table.cores[thread.core];
+            let kind = if sched.idle == Some(index) {
+                "idle"
+            } else if thread.entry.is_none() {
+                "initial"
+            } else {
+                "spawned"
+            };
+
+            stats[index] = Some(ThreadStats {
+                index,
+                core: thread.core,
+                kind,
+                priority: thread.priority,
+                state: thread.state,
+                runtime: thread.runtime,
+                switches: thread.switches,
+            });
+        }
+
+        for (core, sched) in table.cores.iter().enumerate() {
+            context_switches[core] = sched.context_switches;
+        }
+    });
+
+    for (core, switches) in context_switches.iter().enumerate() {
+        info!("      Core {}: {} context switches", core, switches);
+    }
+
+    for s in stats.iter().flatten() {
+        info!(
+            "      {: >2}. core {} {: <7} {: <6} {: <8} runtime {: >10} us, switched in {: >6}x",
+            s.index,
+            s.core,
+            s.kind,
+            s.priority.name(),
+            s.state.name(),
+            s.runtime.as_micros(),
+            s.switches
+        );
+    }
+}
+
+impl JoinHandle {
+    /// Wait until the thread has returned and hand out its entry function's return value.
+    ///
+    /// The executing thread blocks while waiting. Spins on cores that can not use threads.
+    pub fn join(self) -> usize {
+        let index = self.index;
+        core::mem::forget(self);
+
+        let _ = register_current();
+
+        loop {
+            switch_away(|table, current| {
+                // An exiting thread already woke its joiner. It finishes as soon as its core has
+                // switched away from it.
+                if matches!(table.threads[index].state, State::Exiting | State::Finished) {
+                    return State::Running;
+                }
+
+                table.threads[index].joiner = Some(current);
+                State::Blocked
+            });
+
+            let result = THREAD_TABLE.lock(|table| {
+                if table.threads[index].state != State::Finished {
+                    return None;
+                }
+
+                let result = table.threads[index].result;
+                table.release(index);
+
+                Some(result)
+            });
+
+            if let Some(x) = result {
+                return x;
+            }
+
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl Drop for JoinHandle {
+    fn drop(&mut self) {
+        THREAD_TABLE.lock(|table| {
+            if table.threads[self.index].state == State::Finished {
+                table.release(self.index);
+            } else {
+                table.threads[self.index].detached = true;
+            }
+        });
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Run queues must hand out threads in insertion order, also after wrapping around.
+    #[kernel_test]
+    fn run_queue_is_fifo() {
+        let mut queue = RunQueue::new();
+
+        for round in 0..3 {
+            for i in 0..MAX_THREADS {
+                queue.push_back(round + i);
+            }
+
+            for i in 0..MAX_THREADS {
+                assert_eq!(queue.pop_front(), Some(round + i));
+            }
+            assert_eq!(queue.pop_front(), None);
+        }
+    }
+}
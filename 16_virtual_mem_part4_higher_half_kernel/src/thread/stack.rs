@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Kernel thread stack allocator.
+//!
+//! Hands out fixed-size stacks from a pool that is reserved in `.bss`. The stacks have no guard
+//! pages, so threads must keep their stack usage well below [`STACK_SIZE`].
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The memory of a single stack. AArch64 requires the stack pointer to be 16 byte aligned.
+#[repr(align(16))]
+struct StackMemory([u8; STACK_SIZE]);
+
+/// The pool of stacks.
+struct StackPool(UnsafeCell<[StackMemory; NUM_STACKS]>);
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The size of each thread stack.
+pub const STACK_SIZE: usize = 64 * 1024;
+
+/// The number of stacks in the pool.
+pub const NUM_STACKS: usize = 16;
+
+/// An allocated stack. Must be given back with [`free()`].
+pub struct Stack {
+    index: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static STACK_POOL: StackPool = StackPool(UnsafeCell::new(new_stacks()));
+
+/// Bitmask of the stacks that are in use.
+static STACKS_IN_USE: AtomicUsize = AtomicUsize::new(0);
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+const fn new_stacks() -> [StackMemory; NUM_STACKS] {
+    const EMPTY: StackMemory = StackMemory([0; STACK_SIZE]);
+
+    [EMPTY; NUM_STACKS]
+}
+
+unsafe impl Sync for StackPool {}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Stack {
+    /// The exclusive end address of the stack, which is the initial stack pointer.
+    pub fn end_exclusive(&self) -> usize {
+        let pool = STACK_POOL.0.get() as *const StackMemory;
+        let start = unsafe { pool.add(self.index) } as usize;
+
+        start + STACK_SIZE
+    }
+}
+
+/// Allocate a stack. Returns `None` if all stacks are in use.
+pub fn alloc() -> Option<Stack> {
+    let mut in_use = STACKS_IN_USE.load(Ordering::Relaxed);
+
+    loop {
+        let index = (!in_use).trailing_zeros() as usize;
+        if index >= NUM_STACKS {
+            return None;
+        }
+
+        match STACKS_IN_USE.compare_exchange_weak(
+            in_use,
+            in_use | (1 << index),
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => return Some(Stack { index }),
+            Err(x) => in_use = x,
+        }
+    }
+}
+
+/// Give a stack back to the pool.
+///
+/// The stack must not be in use by any thread anymore.
+pub fn free(stack: Stack) {
+    STACKS_IN_USE.fetch_and(!(1 << stack.index), Ordering::Release);
+}
@@ -8,9 +8,24 @@
 #[path = "_arch/aarch64/time.rs"]
 mod arch_time;
 
+mod instant;
+mod timer;
+
+pub mod rtc;
+pub mod timer_queue;
+
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+pub use instant::{Deadline, Instant};
+pub use timer::Timer;
+
 //--------------------------------------------------------------------------------------------------
 // Architectural Public Reexports
 //--------------------------------------------------------------------------------------------------
+#[cfg(not(feature = "system_timer_backend"))]
 pub use arch_time::time_manager;
 
 //--------------------------------------------------------------------------------------------------
@@ -19,6 +34,7 @@ pub use arch_time::time_manager;
 
 /// Timekeeping interfaces.
 pub mod interface {
+    use super::Instant;
     use core::time::Duration;
 
     /// Time management functions.
@@ -31,7 +47,98 @@ pub mod interface {
         /// This includes time consumed by firmware and bootloaders.
         fn uptime(&self) -> Duration;
 
+        /// The current value of the monotonic counter.
+        fn now(&self) -> Instant;
+
+        /// Convert a number of counter ticks to a duration.
+        fn ticks_to_duration(&self, ticks: u64) -> Duration;
+
+        /// Convert a duration to a number of counter ticks, rounding up. Saturates on overflow.
+        fn duration_to_ticks(&self, duration: Duration) -> u64;
+
         /// Spin for a given duration.
+        ///
+        /// Durations shorter than the timer's resolution spin for a single counter tick.
         fn spin_for(&self, duration: Duration);
+
+        /// Wait in a low-power state for at least the given duration.
+        ///
+        /// Less precise than [`spin_for()`](TimeManager::spin_for), because the core is only woken
+        /// up periodically to check the time.
+        fn wait_for(&self, duration: Duration);
+
+        /// Register and enable the timer's IRQ handler.
+        ///
+        /// Must be called during kernel init.
+        fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str>;
+
+        /// Raise the timer IRQ once `deadline` is reached. `None` disarms the timer.
+        fn set_alarm(&self, deadline: Option<Instant>);
+    }
+
+    /// Wall-clock time functions.
+    pub trait RealTimeClock {
+        /// Set the current time, as duration since the Unix epoch (1970-01-01T00:00:00Z).
+        fn set_unix_time(&self, unix_time: Duration);
+
+        /// The current time as duration since the Unix epoch. `None` if the time was never set.
+        fn unix_time(&self) -> Option<Duration>;
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static TICK_PERIOD_NS: AtomicU64 = AtomicU64::new(0);
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+fn tick(_id: timer_queue::TimerId) {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the time manager.
+///
+/// The BSP's system timer backs all timekeeping and the software timer queue instead of the
+/// architectural timer.
+#[cfg(feature = "system_timer_backend")]
+pub fn time_manager() -> &'static impl interface::TimeManager {
+    crate::bsp::system_timer()
+}
+
+/// Return a reference to the architectural time manager, regardless of the selected backend.
+pub fn arch_time_manager() -> &'static impl interface::TimeManager {
+    arch_time::time_manager()
+}
+
+/// Start the periodic kernel tick.
+///
+/// The tick is a periodic software timer, so it needs the timer IRQ handler to be registered.
+pub fn start_periodic_tick(period: Duration) -> Result<(), &'static str> {
+    if TICK_PERIOD_NS.load(Ordering::Relaxed) != 0 {
+        return Err("Periodic tick already started");
+    }
+
+    timer_queue::add_periodic(period, tick)?;
+    TICK_PERIOD_NS.store(period.as_nanos() as u64, Ordering::Relaxed);
+
+    Ok(())
+}
+
+/// The number of ticks since the periodic tick was started.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// The period of the kernel tick. Zero if the tick was not started.
+pub fn tick_period() -> Duration {
+    Duration::from_nanos(TICK_PERIOD_NS.load(Ordering::Relaxed))
+}
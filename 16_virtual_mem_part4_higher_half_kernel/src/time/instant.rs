@@ -0,0 +1,207 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Monotonic points in time and deadlines.
+//!
+//! Timeouts are expressed as a [`Deadline`], so that drivers can uniformly write
+//! `while !deadline.has_expired() { ... }` instead of comparing uptimes by hand.
+
+use crate::time::{interface::TimeManager, time_manager};
+use core::{
+    ops::{Add, AddAssign, Sub},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A point in time, as an absolute value of the monotonic system counter.
+///
+/// The counter is 64 bits wide and starts at zero on power-on, so an `Instant` does not overflow
+/// within the lifetime of the system. Arithmetic saturates instead of wrapping.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Instant {
+    counter_ticks: u64,
+}
+
+/// A point in time after which an operation is considered timed out.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Deadline {
+    instant: Instant,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Instant {
+    /// Create an instance from an absolute counter value.
+    pub const fn from_counter_ticks(counter_ticks: u64) -> Self {
+        Self { counter_ticks }
+    }
+
+    /// Create an instance from the time since power-on. Saturates on overflow.
+    pub fn from_duration_since_boot(duration: Duration) -> Self {
+        Self::from_counter_ticks(time_manager().duration_to_ticks(duration))
+    }
+
+    /// The absolute counter value.
+    pub const fn counter_ticks(&self) -> u64 {
+        self.counter_ticks
+    }
+
+    /// The time since power-on.
+    pub fn as_duration_since_boot(&self) -> Duration {
+        time_manager().ticks_to_duration(self.counter_ticks)
+    }
+
+    /// The current point in time.
+    pub fn now() -> Self {
+        time_manager().now()
+    }
+
+    /// The duration that passed from `earlier` to `self`. Zero if `earlier` is later than `self`.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        time_manager().ticks_to_duration(self.counter_ticks.saturating_sub(earlier.counter_ticks))
+    }
+
+    /// The duration that passed since `self`.
+    pub fn elapsed(&self) -> Duration {
+        Self::now().duration_since(*self)
+    }
+
+    /// `self + duration`, or `None` on overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        let ticks = time_manager().duration_to_ticks(duration);
+
+        // The conversion saturates, so the maximum value might be the result of an overflow.
+        if ticks == u64::MAX {
+            return None;
+        }
+
+        self.counter_ticks
+            .checked_add(ticks)
+            .map(Instant::from_counter_ticks)
+    }
+
+    /// `self - duration`, or `None` if the result would be before power-on.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        let ticks = time_manager().duration_to_ticks(duration);
+
+        self.counter_ticks
+            .checked_sub(ticks)
+            .map(Instant::from_counter_ticks)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        let ticks = time_manager().duration_to_ticks(rhs);
+
+        Instant::from_counter_ticks(self.counter_ticks.saturating_add(ticks))
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        let ticks = time_manager().duration_to_ticks(rhs);
+
+        Instant::from_counter_ticks(self.counter_ticks.saturating_sub(ticks))
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Self::Output {
+        self.duration_since(rhs)
+    }
+}
+
+impl Deadline {
+    /// A deadline `timeout` from now.
+    pub fn after(timeout: Duration) -> Self {
+        Self::at(Instant::now() + timeout)
+    }
+
+    /// A deadline at the given point in time.
+    pub const fn at(instant: Instant) -> Self {
+        Self { instant }
+    }
+
+    /// The point in time of the deadline.
+    pub const fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// Check if the deadline has passed.
+    pub fn has_expired(&self) -> bool {
+        Instant::now() >= self.instant
+    }
+
+    /// The time left until the deadline. Zero if it has expired.
+    pub fn remaining(&self) -> Duration {
+        self.instant.duration_since(Instant::now())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Arithmetic must saturate instead of wrapping.
+    #[kernel_test]
+    fn instant_arithmetic_saturates() {
+        let early = Instant::from_counter_ticks(10);
+        let late = Instant::from_counter_ticks(20);
+
+        assert_eq!(early - late, Duration::from_secs(0));
+        assert_eq!(
+            early - Duration::from_secs(1),
+            Instant::from_counter_ticks(0)
+        );
+        assert_eq!(
+            Instant::from_counter_ticks(u64::MAX) + Duration::from_secs(1),
+            Instant::from_counter_ticks(u64::MAX)
+        );
+    }
+
+    /// Checked arithmetic must detect overflows.
+    #[kernel_test]
+    fn instant_checked_arithmetic_detects_overflow() {
+        let instant = Instant::from_counter_ticks(10);
+
+        assert!(Instant::from_counter_ticks(u64::MAX)
+            .checked_add(Duration::from_secs(1))
+            .is_none());
+        assert!(instant.checked_sub(Duration::from_secs(1)).is_none());
+        assert!(instant.checked_add(Duration::from_secs(1)).unwrap() > instant);
+    }
+
+    /// Deadlines in the past must be expired, deadlines in the future must not.
+    #[kernel_test]
+    fn deadline_expiry() {
+        assert!(Deadline::at(Instant::from_counter_ticks(0)).has_expired());
+
+        let deadline = Deadline::after(Duration::from_secs(3600));
+        assert!(!deadline.has_expired());
+        assert!(deadline.remaining() > Duration::from_secs(3599));
+    }
+}
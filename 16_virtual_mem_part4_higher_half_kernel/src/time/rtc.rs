@@ -0,0 +1,308 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Wall-clock time.
+//!
+//! The board has no battery-backed clock, so calendar time is derived from the monotonic uptime
+//! and an epoch that is set at runtime. Any source that knows the current time can set it, for
+//! example a command on the console, the host side of a chainloader, or an RTC chip driver.
+
+use crate::time;
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Days from 0000-03-01 to 1970-01-01 in the proleptic Gregorian calendar.
+const DAYS_TO_UNIX_EPOCH: u64 = 719_468;
+
+/// Days in a 400 year cycle.
+const DAYS_PER_ERA: u64 = 146_097;
+
+/// A real-time clock that adds the uptime to the time that was set last.
+struct UptimeClock {
+    /// Unix time at power-on, in nanoseconds. Zero if the clock was never set.
+    unix_time_at_boot_ns: AtomicU64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A calendar date and time in UTC.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DateTime {
+    pub year: u32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static REAL_TIME_CLOCK: UptimeClock = UptimeClock {
+    unix_time_at_boot_ns: AtomicU64::new(0),
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Parse a fixed-width decimal number.
+fn parse_decimal(s: &[u8]) -> Option<u32> {
+    if s.is_empty() {
+        return None;
+    }
+
+    s.iter().try_fold(0u32, |acc, c| match c {
+        b'0'..=b'9' => Some(acc * 10 + u32::from(c - b'0')),
+        _ => None,
+    })
+}
+
+fn is_leap_year(year: u32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// The number of days in the given month, 1 to 12.
+fn days_in_month(year: u32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl DateTime {
+    /// Convert the time since the Unix epoch to a calendar date and time.
+    pub fn from_unix_time(unix_time: Duration) -> Self {
+        let secs = unix_time.as_secs();
+        let secs_of_day = secs % SECS_PER_DAY;
+
+        // Civil-from-days, counting eras of 400 years that start on March 1st.
+        let days = secs / SECS_PER_DAY + DAYS_TO_UNIX_EPOCH;
+        let era = days / DAYS_PER_ERA;
+        let day_of_era = days - era * DAYS_PER_ERA;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as u32,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: ((secs_of_day / 60) % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+            nanosecond: unix_time.subsec_nanos(),
+        }
+    }
+
+    /// Convert the calendar date and time to the time since the Unix epoch.
+    pub fn to_unix_time(self) -> Duration {
+        let month = u64::from(self.month);
+        let year = u64::from(self.year) - if month <= 2 { 1 } else { 0 };
+
+        // Days-from-civil, the inverse of the conversion above.
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * shifted_month + 2) / 5 + u64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * DAYS_PER_ERA + day_of_era - DAYS_TO_UNIX_EPOCH;
+
+        let secs = days * SECS_PER_DAY
+            + u64::from(self.hour) * 3600
+            + u64::from(self.minute) * 60
+            + u64::from(self.second);
+
+        Duration::new(secs, self.nanosecond)
+    }
+
+    /// Parse an ISO-8601 timestamp of the form `YYYY-MM-DDTHH:MM:SS[.fff]Z` in UTC.
+    ///
+    /// The trailing `Z` is optional. Dates before the Unix epoch are rejected.
+    pub fn parse_iso8601(s: &str) -> Result<Self, &'static str> {
+        const ERR: &str = "Invalid ISO-8601 timestamp";
+
+        let s = s.trim().trim_end_matches('Z').as_bytes();
+        if s.len() < 19 || s[4] != b'-' || s[7] != b'-' || s[10] != b'T' {
+            return Err(ERR);
+        }
+        if s[13] != b':' || s[16] != b':' {
+            return Err(ERR);
+        }
+
+        let field = |from: usize, to: usize| parse_decimal(&s[from..to]).ok_or(ERR);
+
+        let nanosecond = match &s[19..] {
+            [] => 0,
+            [b'.', fraction @ ..] if fraction.len() <= 9 => {
+                field(20, s.len())? * 10u32.pow(9 - fraction.len() as u32)
+            }
+            _ => return Err(ERR),
+        };
+
+        let date_time = Self {
+            year: field(0, 4)?,
+            month: field(5, 7)? as u8,
+            day: field(8, 10)? as u8,
+            hour: field(11, 13)? as u8,
+            minute: field(14, 16)? as u8,
+            second: field(17, 19)? as u8,
+            nanosecond,
+        };
+
+        if date_time.year < 1970
+            || !(1..=12).contains(&date_time.month)
+            || !(1..=days_in_month(date_time.year, date_time.month)).contains(&date_time.day)
+            || date_time.hour > 23
+            || date_time.minute > 59
+            || date_time.second > 59
+        {
+            return Err(ERR);
+        }
+
+        Ok(date_time)
+    }
+}
+
+/// Renders ISO-8601 with millisecond precision, e.g. `2021-05-03T12:34:56.789Z`.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.nanosecond / 1_000_000
+        )
+    }
+}
+
+/// Return a reference to the real-time clock.
+pub fn real_time_clock() -> &'static impl time::interface::RealTimeClock {
+    &REAL_TIME_CLOCK
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl time::interface::RealTimeClock for UptimeClock {
+    fn set_unix_time(&self, unix_time: Duration) {
+        use time::interface::TimeManager;
+
+        let uptime = time::time_manager().uptime();
+        let at_boot = unix_time.checked_sub(uptime).unwrap_or_default();
+
+        // Keep zero reserved for "never set".
+        let at_boot_ns = (at_boot.as_nanos() as u64).max(1);
+
+        self.unix_time_at_boot_ns
+            .store(at_boot_ns, Ordering::Relaxed);
+    }
+
+    fn unix_time(&self) -> Option<Duration> {
+        use time::interface::TimeManager;
+
+        let at_boot_ns = self.unix_time_at_boot_ns.load(Ordering::Relaxed);
+        if at_boot_ns == 0 {
+            return None;
+        }
+
+        Some(Duration::from_nanos(at_boot_ns) + time::time_manager().uptime())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_macros::kernel_test;
+
+    /// Conversions from Unix time must handle the epoch, leap days and the end of a century.
+    #[kernel_test]
+    fn date_time_from_unix_time() {
+        let dt = |secs| DateTime::from_unix_time(Duration::from_secs(secs));
+
+        assert_eq!(dt(0).year, 1970);
+        assert_eq!((dt(951_782_400).month, dt(951_782_400).day), (2, 29));
+
+        let end_of_century = dt(4_102_444_799);
+        assert_eq!(
+            (
+                end_of_century.year,
+                end_of_century.month,
+                end_of_century.day
+            ),
+            (2099, 12, 31)
+        );
+        assert_eq!(
+            end_of_century.to_unix_time(),
+            Duration::from_secs(4_102_444_799)
+        );
+    }
+
+    /// Parsing must accept valid ISO-8601 timestamps and round-trip through Unix time.
+    #[kernel_test]
+    fn date_time_parse_iso8601() {
+        let parsed = DateTime::parse_iso8601("2021-05-03T00:00:00.5Z").unwrap();
+
+        assert_eq!(
+            parsed.to_unix_time(),
+            Duration::from_millis(1_620_000_000_500)
+        );
+        assert!(DateTime::parse_iso8601("2021-13-03T00:00:00Z").is_err());
+        assert!(DateTime::parse_iso8601("2021-05-03 00:00:00").is_err());
+    }
+
+    /// Parsing must reject days that do not exist in the given month.
+    #[kernel_test]
+    fn date_time_parse_iso8601_days_in_month() {
+        assert!(DateTime::parse_iso8601("2020-02-29T00:00:00Z").is_ok());
+        assert!(DateTime::parse_iso8601("2000-02-29T00:00:00Z").is_ok());
+        assert!(DateTime::parse_iso8601("2021-02-29T00:00:00Z").is_err());
+        assert!(DateTime::parse_iso8601("2100-02-29T00:00:00Z").is_err());
+
+        assert!(DateTime::parse_iso8601("2021-03-31T00:00:00Z").is_ok());
+        assert!(DateTime::parse_iso8601("2021-04-31T00:00:00Z").is_err());
+        assert!(DateTime::parse_iso8601("2021-06-31T00:00:00Z").is_err());
+        assert!(DateTime::parse_iso8601("2021-09-31T00:00:00Z").is_err());
+        assert!(DateTime::parse_iso8601("2021-11-31T00:00:00Z").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Timer future.
+//!
+//! A [`Timer`] registers a one-shot software timer when it is first polled, and the timer's IRQ
+//! callback wakes the task that awaits it.
+
+use crate::{
+    synchronization,
+    synchronization::IRQSafeSpinLock,
+    time::{timer_queue, Deadline, Instant},
+};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The maximum number of timer futures that can wait at the same time.
+const NUM_WAITING_TIMERS: usize = 16;
+
+/// A waiting timer future's software timer and the waker of the task that awaits it.
+struct WaitingTimer {
+    id: timer_queue::TimerId,
+    waker: Waker,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A future that completes once its deadline has passed.
+pub struct Timer {
+    deadline: Deadline,
+
+    /// The software timer, once the future was polled before its deadline.
+    id: Option<timer_queue::TimerId>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static WAITING_TIMERS: IRQSafeSpinLock<[Option<WaitingTimer>; NUM_WAITING_TIMERS]> = {
+    const NONE: Option<WaitingTimer> = None;
+
+    IRQSafeSpinLock::new([NONE; NUM_WAITING_TIMERS])
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+/// Wake the task that awaits the expired timer.
+fn timer_expired(id: timer_queue::TimerId) {
+    let waiting = WAITING_TIMERS.lock(|timers| {
+        timers
+            .iter_mut()
+            .find(|x| x.as_ref().map_or(false, |t| t.id == id))
+            .and_then(|x| x.take())
+    });
+
+    if let Some(timer) = waiting {
+        timer.waker.wake();
+    }
+}
+
+impl Timer {
+    /// Register the software timer, or update the waker of an already registered one.
+    ///
+    /// The software timer is added under the lock, so its callback can not run before the waker
+    /// was stored.
+    fn register(&mut self, waker: &Waker) -> Result<(), &'static str> {
+        WAITING_TIMERS.lock(|timers| {
+            if let Some(id) = self.id {
+                return match timers.iter_mut().flatten().find(|t| t.id == id) {
+                    Some(timer) => {
+                        if !timer.waker.will_wake(waker) {
+                            timer.waker = waker.clone();
+                        }
+                        Ok(())
+                    }
+                    // Expired meanwhile, the task is about to be polled again.
+                    None => Ok(()),
+                };
+            }
+
+            let slot = timers
+                .iter_mut()
+                .find(|x| x.is_none())
+                .ok_or("Too many waiting timers")?;
+
+            let id = timer_queue::add_oneshot(self.deadline.remaining(), timer_expired)?;
+            *slot = Some(WaitingTimer {
+                id,
+                waker: waker.clone(),
+            });
+            self.id = Some(id);
+
+            Ok(())
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Timer {
+    /// A timer that completes `duration` from now.
+    pub fn after(duration: Duration) -> Self {
+        Self::at(Instant::now() + duration)
+    }
+
+    /// A timer that completes at the given point in time.
+    pub fn at(instant: Instant) -> Self {
+        Self {
+            deadline: Deadline::at(instant),
+            id: None,
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+
+        if this.deadline.has_expired() {
+            return Poll::Ready(());
+        }
+
+        // Without a software timer, ask to be polled again right away.
+        if this.register(cx.waker()).is_err() {
+            cx.waker().wake_by_ref();
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let id = match self.id.take() {
+            Some(x) => x,
+            None => return,
+        };
+
+        WAITING_TIMERS.lock(|timers| {
+            if let Some(x) = timers
+                .iter_mut()
+                .find(|x| x.as_ref().map_or(false, |t| t.id == id))
+            {
+                *x = None;
+                let _ = timer_queue::cancel(id);
+            }
+        });
+    }
+}
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Software timer queue.
+//!
+//! Kernel code can register one-shot and periodic callbacks, which are kept in a queue sorted by
+//! deadline. The hardware timer is programmed to fire only at the nearest deadline, so there are no
+//! timer interrupts while no software timer is due. Callbacks are invoked from the timer IRQ.
+
+use crate::{
+    bsp, exception::asynchronous::ipi, synchronization, synchronization::IRQSafeSpinLock, time,
+    time::Instant,
+};
+use core::time::Duration;
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+const NUM_TIMERS: usize = 32;
+
+#[derive(Copy, Clone)]
+struct Timer {
+    id: TimerId,
+
+    /// The point in time at which the timer expires.
+    deadline: Instant,
+
+    /// The period in counter ticks. Zero for one-shot timers.
+    period: u64,
+
+    callback: TimerCallback,
+}
+
+struct TimerQueueInner {
+    /// Sorted by deadline. All occupied entries come before the free ones.
+    timers: [Option<Timer>; NUM_TIMERS],
+    next_id: u64,
+}
+
+/// A queue of software timers, sorted by deadline.
+struct TimerQueue {
+    inner: IRQSafeSpinLock<TimerQueueInner>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Identifies a registered timer.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TimerId(u64);
+
+/// A function that is called from the timer IRQ when a timer expires.
+pub type TimerCallback = fn(TimerId);
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static TIMER_QUEUE: TimerQueue = TimerQueue::new();
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+use synchronization::interface::Mutex;
+
+impl TimerQueueInner {
+    /// Insert a timer, keeping the queue sorted. Timers with equal deadlines keep their insertion
+    /// order.
+    fn insert(&mut self, timer: Timer) -> Result<(), &'static str> {
+        let len = self.timers.iter().take_while(|x| x.is_some()).count();
+        if len == NUM_TIMERS {
+            return Err("Storage for software timers exhausted");
+        }
+
+        let pos = self.timers[..len]
+            .iter()
+            .position(|x| x.map_or(false, |t| t.deadline > timer.deadline))
+            .unwrap_or(len);
+
+        self.timers.copy_within(pos..len, pos + 1);
+        self.timers[pos] = Some(timer);
+
+        Ok(())
+    }
+
+    /// Remove the timer at the given position, keeping the queue sorted.
+    fn remove(&mut self, pos: usize) -> Timer {
+        let timer = self.timers[pos].take().unwrap();
+
+        self.timers.copy_within(pos + 1.., pos);
+        self.timers[NUM_TIMERS - 1] = None;
+
+        timer
+    }
+}
+
+impl TimerQueue {
+    const fn new() -> Self {
+        Self {
+            inner: IRQSafeSpinLock::new(TimerQueueInner {
+                timers: [None; NUM_TIMERS],
+                next_id: 0,
+            }),
+        }
+    }
+
+    fn add(
+        &self,
+        deadline: Instant,
+        period: u64,
+        callback: TimerCallback,
+    ) -> Result<TimerId, &'static str> {
+        self.inner.lock(|inner| {
+            let id = TimerId(inner.next_id);
+
+            inner.insert(Timer {
+                id,
+                deadline,
+                period,
+                callback,
+            })?;
+            inner.next_id += 1;
+
+            Ok(id)
+        })
+    }
+
+    fn cancel(&self, id: TimerId) -> Result<(), &'static str> {
+        self.inner.lock(|inner| {
+            let pos = inner
+                .timers
+                .iter()
+                .position(|x| x.map_or(false, |t| t.id == id))
+                .ok_or("Timer not found")?;
+
+            inner.remove(pos);
+
+            Ok(())
+        })
+    }
+
+    /// The deadline of the timer that expires next.
+    fn next_deadline(&self) -> Option<Instant> {
+        self.inner.lock(|inner| inner.timers[0].map(|t| t.deadline))
+    }
+
+    /// Dequeue the next timer that has expired at `now`.
+    ///
+    /// Periodic timers are queued again with their next deadline. If the next deadline was already
+    /// missed, it is pushed out to one period from `now` to avoid bursts of callbacks.
+    fn pop_expired(&self, now: Instant) -> Option<(TimerId, TimerCallback)> {
+        self.inner.lock(|inner| {
+            match inner.timers[0] {
+                Some(t) if t.deadline <= now => (),
+                _ => return None,
+            }
+
+            let mut timer = inner.remove(0);
+
+            if timer.period != 0 {
+                let next = timer.deadline.counter_ticks().saturating_add(timer.period);
+                timer.deadline = Instant::from_counter_ticks(next);
+
+                if timer.deadline <= now {
+                    let next = now.counter_ticks().saturating_add(timer.period);
+                    timer.deadline = Instant::from_counter_ticks(next);
+                }
+
+                // Cannot fail, the entry was just freed.
+                let _ = inner.insert(timer);
+            }
+
+            Some((timer.id, timer.callback))
+        })
+    }
+}
+
+/// Program the hardware timer of the executing core for the nearest deadline.
+///
+/// Has the signature of a cross-core call function. The argument is unused.
+fn rearm_local(_: usize) {
+    use time::interface::TimeManager;
+
+    time::time_manager().set_alarm(TIMER_QUEUE.next_deadline());
+}
+
+/// Program the hardware timer for the nearest deadline.
+///
+/// Only the boot core has the timer IRQ enabled, so the alarm is always programmed there.
+fn rearm() -> Result<(), &'static str> {
+    ipi::call_on(bsp::cpu::BOOT_CORE_ID as usize, rearm_local, 0)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Call `callback` once, after `delay` has passed.
+pub fn add_oneshot(delay: Duration, callback: TimerCallback) -> Result<TimerId, &'static str> {
+    let deadline = Instant::now() + delay;
+    let id = TIMER_QUEUE.add(deadline, 0, callback)?;
+    rearm()?;
+
+    Ok(id)
+}
+
+/// Call `callback` every `period`, starting one period from now.
+pub fn add_periodic(period: Duration, callback: TimerCallback) -> Result<TimerId, &'static str> {
+    use time::interface::TimeManager;
+
+    let period_ticks = time::time_manager().duration_to_ticks(period);
+    if period_ticks == 0 {
+        return Err("Period of a periodic timer must not be zero");
+    }
+
+    let deadline = Instant::now() + period;
+    let id = TIMER_QUEUE.add(deadline, period_ticks, callback)?;
+    rearm()?;
+
+    Ok(id)
+}
+
+/// Cancel a timer. Returns an error if the timer does not exist (anymore).
+pub fn cancel(id: TimerId) -> Result<(), &'static str> {
+    TIMER_QUEUE.cancel(id)?;
+    rearm()
+}
+
+/// Invoke the callbacks of all expired timers and program the hardware timer for the next
+/// deadline.
+///
+/// Called from the timer IRQ handler.
+pub fn handle_expired() {
+    while let Some((id, callback)) = TIMER_QUEUE.pop_expired(Instant::now()) {
+        // The queue is unlocked here, so callbacks are free to add or cancel timers.
+        callback(id);
+    }
+
+    // The timer IRQ is only taken on the boot core, so the alarm is programmed locally.
+    rearm_local(0);
+}
+
+//--------------------------------------------------------------------------------------------------
+// Testing
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use test_macros::kernel_test;
+
+    static CALLED: AtomicU64 = AtomicU64::new(0);
+
+    fn record(id: TimerId) {
+        // Shift in the ID, so that the call order can be checked.
+        let old = CALLED.load(Ordering::Relaxed);
+        CALLED.store((old << 8) | (id.0 + 1), Ordering::Relaxed);
+    }
+
+    fn drain(queue: &TimerQueue, now: Instant) {
+        while let Some((id, callback)) = queue.pop_expired(now) {
+            callback(id);
+        }
+    }
+
+    /// Timers must expire in deadline order, independent of registration order.
+    #[kernel_test]
+    fn timers_expire_in_deadline_order() {
+        let queue = TimerQueue::new();
+        CALLED.store(0, Ordering::Relaxed);
+
+        let at = Instant::from_counter_ticks;
+        let t0 = queue.add(at(30), 0, record).unwrap();
+        let t1 = queue.add(at(10), 0, record).unwrap();
+        let t2 = queue.add(at(20), 0, record).unwrap();
+
+        assert_eq!(queue.next_deadline(), Some(at(10)));
+
+        drain(&queue, at(15));
+        assert_eq!(CALLED.load(Ordering::Relaxed), t1.0 + 1);
+
+        drain(&queue, at(30));
+        let expected = (((t1.0 + 1) << 8 | (t2.0 + 1)) << 8) | (t0.0 + 1);
+        assert_eq!(CALLED.load(Ordering::Relaxed), expected);
+        assert_eq!(queue.next_deadline(), None);
+    }
+
+    /// Cancelled timers must not expire.
+    #[kernel_test]
+    fn cancelled_timers_do_not_expire() {
+        let queue = TimerQueue::new();
+        CALLED.store(0, Ordering::Relaxed);
+
+        let at = Instant::from_counter_ticks;
+        let t0 = queue.add(at(10), 0, record).unwrap();
+        let t1 = queue.add(at(20), 0, record).unwrap();
+
+        queue.cancel(t0).unwrap();
+        assert!(queue.cancel(t0).is_err());
+
+        drain(&queue, at(20));
+        assert_eq!(CALLED.load(Ordering::Relaxed), t1.0 + 1);
+    }
+
+    /// Periodic timers must be requeued with their next deadline until cancelled.
+    #[kernel_test]
+    fn periodic_timers_are_requeued() {
+        let queue = TimerQueue::new();
+        CALLED.store(0, Ordering::Relaxed);
+
+        let at = Instant::from_counter_ticks;
+        let t0 = queue.add(at(10), 10, record).unwrap();
+
+        drain(&queue, at(10));
+        assert_eq!(queue.next_deadline(), Some(at(20)));
+
+        drain(&queue, at(20));
+        assert_eq!(
+            CALLED.load(Ordering::Relaxed),
+            ((t0.0 + 1) << 8) | (t0.0 + 1)
+        );
+
+        queue.cancel(t0).unwrap();
+        assert_eq!(queue.next_deadline(), None);
+    }
+}
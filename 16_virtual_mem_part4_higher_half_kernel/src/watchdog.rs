@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Watchdog.
+
+use crate::{bsp, time::timer_queue};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use interface::Watchdog;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Watchdog interfaces.
+pub mod interface {
+    use core::time::Duration;
+
+    /// Watchdog functions.
+    pub trait Watchdog {
+        /// Start the watchdog. The board is reset unless it is petted within `timeout`.
+        ///
+        /// Restarts the countdown if the watchdog is already running.
+        fn start(&self, timeout: Duration) -> Result<(), &'static str>;
+
+        /// Reload the countdown. Does nothing if the watchdog is not running.
+        fn pet(&self);
+
+        /// Stop the watchdog.
+        fn stop(&self);
+
+        /// Reset the board.
+        fn reboot(&self) -> !;
+
+        /// Reset the board and stay halted instead of booting again.
+        fn halt(&self) -> !;
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static REBOOT_ON_PANIC: AtomicBool = AtomicBool::new(false);
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+fn pet(_id: timer_queue::TimerId) {
+    bsp::watchdog().pet();
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Start the board's watchdog and pet it periodically from the timer IRQ.
+///
+/// The watchdog is petted at half the timeout, so it only fires if timer IRQs stop being served,
+/// e.g. because the kernel hangs with IRQs masked.
+pub fn start_with_periodic_pet(timeout: Duration) -> Result<timer_queue::TimerId, &'static str> {
+    bsp::watchdog().start(timeout)?;
+
+    match timer_queue::add_periodic(timeout / 2, pet) {
+        Ok(id) => Ok(id),
+        Err(x) => {
+            bsp::watchdog().stop();
+            Err(x)
+        }
+    }
+}
+
+/// Choose whether a kernel panic resets the board instead of waiting forever.
+pub fn set_reboot_on_panic(enable: bool) {
+    REBOOT_ON_PANIC.store(enable, Ordering::Relaxed);
+}
+
+/// Whether a kernel panic resets the board.
+pub fn reboot_on_panic() -> bool {
+    REBOOT_ON_PANIC.load(Ordering::Relaxed)
+}
+
+/// Reset the board.
+pub fn reboot() -> ! {
+    bsp::watchdog().reboot()
+}
+
+/// Reset the board and stay halted.
+pub fn halt() -> ! {
+    bsp::watchdog().halt()
+}
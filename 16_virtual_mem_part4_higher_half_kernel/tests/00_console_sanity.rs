@@ -37,6 +37,6 @@ unsafe fn kernel_init() -> ! {
     // wait_forever() is used. Calling qemu_exit_success() fixes this behavior. So for the time
     // being, the following lines are just a workaround to fix this compiler/linker weirdness.
     use libkernel::time::interface::TimeManager;
-    libkernel::time::time_manager().spin_for(core::time::Duration::from_secs(3600));
+    libkernel::time::time_manager().wait_for(core::time::Duration::from_secs(3600));
     cpu::qemu_exit_success()
 }
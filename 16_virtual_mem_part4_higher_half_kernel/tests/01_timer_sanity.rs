@@ -47,3 +47,36 @@ fn spin_accuracy_check_1_second() {
 
     assert_eq!((t2 - t1).as_secs(), 1)
 }
+
+/// Durations shorter than one counter tick must still wait.
+#[kernel_test]
+fn spin_for_sub_tick_duration_waits() {
+    let t1 = time::time_manager().now();
+    time::time_manager().spin_for(Duration::from_nanos(1));
+    let t2 = time::time_manager().now();
+
+    assert!(t2 > t1)
+}
+
+/// Converting large counter values must not overflow.
+#[kernel_test]
+fn uptime_conversion_does_not_overflow() {
+    const ONE_YEAR_SECS: u64 = 365 * 24 * 60 * 60;
+
+    let ticks = time::time_manager().duration_to_ticks(Duration::from_secs(ONE_YEAR_SECS));
+
+    assert_eq!(
+        time::time_manager().ticks_to_duration(ticks).as_secs(),
+        ONE_YEAR_SECS
+    )
+}
+
+/// The low-power wait must not return early.
+#[kernel_test]
+fn wait_for_does_not_return_early() {
+    let t1 = time::time_manager().now();
+    time::time_manager().wait_for(Duration::from_millis(100));
+    let t2 = time::time_manager().now();
+
+    assert!(t2.duration_since(t1) >= Duration::from_millis(100))
+}
@@ -3,6 +3,9 @@
 // Copyright (c) 2019-2021 Andre Richter <andre.o.richter@gmail.com>
 
 //! Page faults must result in synchronous exceptions.
+//!
+//! Page faults that are covered by a registered fault handler must be recoverable. Page faults that
+//! are not covered must end in a panic.
 
 #![feature(format_args_nl)]
 #![no_main]
@@ -17,18 +20,138 @@
 /// or indirectly.
 mod panic_exit_success;
 
-use libkernel::{bsp, cpu, exception, println};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use libkernel::{
+    bsp, cpu,
+    exception::{
+        self,
+        synchronous::{
+            interface::FaultHandler, ExceptionClass, FaultHandlerDescriptor, FaultInfo,
+            FaultResolution,
+        },
+    },
+    memory::{
+        self,
+        mmu::{AccessPermissions, AttributeFields, MemAttributes, PageSliceDescriptor},
+        Address,
+    },
+    println,
+};
+
+const ONE_GIB: usize = 1024 * 1024 * 1024;
+
+/// Skips the faulting instruction.
+struct SkipHandler {
+    count: AtomicUsize,
+}
+
+/// Maps the boot core's stack guard page on first access.
+struct LazyMapHandler {
+    count: AtomicUsize,
+}
+
+static SKIP_HANDLER: SkipHandler = SkipHandler {
+    count: AtomicUsize::new(0),
+};
+
+static LAZY_MAP_HANDLER: LazyMapHandler = LazyMapHandler {
+    count: AtomicUsize::new(0),
+};
+
+impl FaultHandler for SkipHandler {
+    fn handle(&self, _info: &FaultInfo) -> FaultResolution {
+        self.count.fetch_add(1, Ordering::Relaxed);
+
+        FaultResolution::SkipInstruction
+    }
+}
+
+impl FaultHandler for LazyMapHandler {
+    fn handle(&self, _info: &FaultInfo) -> FaultResolution {
+        self.count.fetch_add(1, Ordering::Relaxed);
+
+        let virt_pages = bsp::memory::mmu::virt_boot_core_stack_guard_page_desc();
+
+        // The guard page sits directly below the boot core's stack, physically as well.
+        let phys_stack_start = match memory::mmu::try_virt_to_phys(virt_pages.end_addr()) {
+            Err(_) => return FaultResolution::Unhandled,
+            Ok(addr) => addr,
+        };
+        let phys_pages = PageSliceDescriptor::from_addr(
+            phys_stack_start - virt_pages.size(),
+            virt_pages.num_pages(),
+        );
+
+        let attr = AttributeFields {
+            mem_attributes: MemAttributes::CacheableDRAM,
+            acc_perms: AccessPermissions::ReadWrite,
+            execute_never: true,
+        };
+
+        let result = unsafe {
+            memory::mmu::kernel_map_pages_at(
+                "Lazily mapped guard page",
+                &virt_pages,
+                &phys_pages,
+                &attr,
+            )
+        };
+
+        match result {
+            Err(_) => FaultResolution::Unhandled,
+            Ok(()) => FaultResolution::Resume,
+        }
+    }
+}
 
 #[no_mangle]
 unsafe fn kernel_init() -> ! {
     exception::handling_init();
     bsp::console::qemu_bring_up_console();
 
-    println!("Testing synchronous exception handling by causing a page fault");
+    println!("Testing synchronous exception handling by causing page faults");
     println!("-------------------------------------------------------------------\n");
 
-    println!("Writing to bottom of address space to address 1 GiB...");
-    let big_addr: u64 = 1 * 1024 * 1024 * 1024;
+    let descriptors = [
+        FaultHandlerDescriptor {
+            name: "Skip",
+            class: ExceptionClass::DataAbort,
+            virt_pages: PageSliceDescriptor::from_addr(Address::new(ONE_GIB), 1),
+            handler: &SKIP_HANDLER,
+        },
+        FaultHandlerDescriptor {
+            name: "Lazy map",
+            class: ExceptionClass::DataAbort,
+            virt_pages: bsp::memory::mmu::virt_boot_core_stack_guard_page_desc(),
+            handler: &LAZY_MAP_HANDLER,
+        },
+    ];
+    for i in descriptors.iter() {
+        if exception::synchronous::register_fault_handler(*i).is_err() {
+            cpu::qemu_exit_failure()
+        }
+    }
+
+    println!("Reading from address 1 GiB, which the fault handler skips...");
+    core::ptr::read_volatile(ONE_GIB as *mut u64);
+
+    if SKIP_HANDLER.count.load(Ordering::Relaxed) != 1 {
+        cpu::qemu_exit_failure()
+    }
+
+    println!("Reading from the stack guard page, which the fault handler maps...");
+    let guard_addr = bsp::memory::mmu::virt_boot_core_stack_guard_page_desc().start_addr();
+    core::ptr::read_volatile(guard_addr.into_usize() as *mut u64);
+
+    // The second access must not fault anymore.
+    core::ptr::read_volatile(guard_addr.into_usize() as *mut u64);
+
+    if LAZY_MAP_HANDLER.count.load(Ordering::Relaxed) != 1 {
+        cpu::qemu_exit_failure()
+    }
+
+    println!("Writing to bottom of address space to address 2 GiB...");
+    let big_addr: u64 = 2 * 1024 * 1024 * 1024;
     core::ptr::read_volatile(big_addr as *mut u64);
 
     // If execution reaches here, the memory access above did not cause a page fault exception.
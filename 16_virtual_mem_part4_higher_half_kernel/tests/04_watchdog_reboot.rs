@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! A watchdog reboot must reset the board.
+//!
+//! The test runner starts QEMU with `-no-reboot` for this test, so the reset makes it exit with a
+//! success code. Panicking or returning from the reboot ends in a failure.
+
+#![feature(format_args_nl)]
+#![no_main]
+#![no_std]
+
+use libkernel::{bsp, driver::interface::DriverManager, exception, println, watchdog};
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+
+    for i in bsp::driver::driver_manager()
+        .non_early_print_device_drivers()
+        .iter()
+    {
+        if let Err(x) = i.init() {
+            panic!("Error loading driver: {}: {}", i.compatible(), x);
+        }
+    }
+
+    println!("Testing watchdog reboot");
+    println!("-------------------------------------------------------------------\n");
+
+    watchdog::reboot();
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Secondary core boot tests.
+
+#![feature(custom_test_frameworks)]
+#![no_main]
+#![no_std]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(libkernel::test_runner)]
+
+use libkernel::{bsp, cpu, exception, memory, state};
+use test_macros::kernel_test;
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+    bsp::cpu::map_spin_table().unwrap();
+
+    state::state_manager().transition_to_single_core_main();
+    cpu::smp::start_secondary_cores().unwrap();
+    state::state_manager().transition_to_multi_core_main();
+
+    test_main();
+
+    cpu::qemu_exit_success()
+}
+
+/// Check that all cores came online.
+#[kernel_test]
+fn all_cores_online() {
+    assert_eq!(cpu::smp::num_cores_online(), bsp::cpu::NUM_CORES);
+}
+
+/// Check that the secondary cores' stack guard pages are unmapped.
+#[kernel_test]
+fn secondary_core_stack_guard_pages_unmapped() {
+    for core_id in 1..bsp::cpu::NUM_CORES {
+        let guard_page = bsp::memory::mmu::virt_secondary_core_stack_guard_page_desc(core_id);
+
+        assert!(memory::mmu::try_virt_to_phys(guard_page.start_addr()).is_err());
+        assert!(memory::mmu::try_virt_to_phys(guard_page.end_addr()).is_ok());
+    }
+}
@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Inter-processor interrupt tests.
+
+#![feature(custom_test_frameworks)]
+#![no_main]
+#![no_std]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(libkernel::test_runner)]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use libkernel::{bsp, cpu, driver::interface::DriverManager, exception, memory, state};
+use test_macros::kernel_test;
+
+static CALLED_ON: AtomicUsize = AtomicUsize::new(0);
+
+fn record_core(arg: usize) {
+    CALLED_ON.fetch_or(arg << cpu::smp::core_id::<usize>(), Ordering::Relaxed);
+}
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+
+    for i in bsp::driver::driver_manager()
+        .non_early_print_device_drivers()
+        .iter()
+    {
+        if let Err(x) = i.init() {
+            panic!("Error loading driver: {}: {}", i.compatible(), x);
+        }
+    }
+    bsp::cpu::map_spin_table().unwrap();
+    exception::asynchronous::ipi::register_and_enable_handler().unwrap();
+    exception::asynchronous::local_irq_unmask();
+
+    state::state_manager().transition_to_single_core_main();
+    cpu::smp::start_secondary_cores().unwrap();
+    state::state_manager().transition_to_multi_core_main();
+
+    test_main();
+
+    cpu::qemu_exit_success()
+}
+
+/// Check that a call reaches exactly the requested core.
+#[kernel_test]
+fn call_on_runs_on_target_core() {
+    for core in 0..bsp::cpu::NUM_CORES {
+        CALLED_ON.store(0, Ordering::Relaxed);
+
+        exception::asynchronous::ipi::call_on(core, record_core, 1).unwrap();
+        assert_eq!(CALLED_ON.load(Ordering::Relaxed), 1 << core);
+    }
+}
+
+/// Check that a broadcast call reaches all other cores, but not the caller.
+#[kernel_test]
+fn call_on_others_runs_everywhere_else() {
+    let all = (1 << bsp::cpu::NUM_CORES) - 1;
+    CALLED_ON.store(0, Ordering::Relaxed);
+
+    exception::asynchronous::ipi::call_on_others(record_core, 1).unwrap();
+    assert_eq!(
+        CALLED_ON.load(Ordering::Relaxed),
+        all & !(1 << cpu::smp::core_id::<usize>())
+    );
+}
+
+/// Check that a TLB shootdown completes.
+#[kernel_test]
+fn tlb_shootdown_completes() {
+    assert!(memory::mmu::kernel_tlb_shootdown().is_ok());
+}
@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Kernel thread tests.
+
+#![feature(custom_test_frameworks)]
+#![no_main]
+#![no_std]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(libkernel::test_runner)]
+
+use libkernel::{bsp, cpu, exception, synchronization, synchronization::SpinLock, thread};
+use test_macros::kernel_test;
+
+const NUM_THREADS: usize = 3;
+const NUM_ROUNDS: usize = 4;
+
+/// Records which thread ran in which order.
+struct Log {
+    entries: [usize; NUM_THREADS * NUM_ROUNDS],
+    len: usize,
+}
+
+static LOG: SpinLock<Log> = SpinLock::new(Log {
+    entries: [0; NUM_THREADS * NUM_ROUNDS],
+    len: 0,
+});
+
+fn log_and_yield(id: usize) -> usize {
+    use synchronization::interface::Mutex;
+
+    for _ in 0..NUM_ROUNDS {
+        LOG.lock(|log| {
+            log.entries[log.len] = id;
+            log.len += 1;
+        });
+
+        thread::yield_now();
+    }
+
+    id * 10
+}
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+
+    test_main();
+
+    cpu::qemu_exit_success()
+}
+
+/// Check that threads take turns in round-robin order and hand out their return values.
+#[kernel_test]
+fn threads_interleave() {
+    use synchronization::interface::Mutex;
+
+    let handles = [
+        thread::spawn(log_and_yield, 1).unwrap(),
+        thread::spawn(log_and_yield, 2).unwrap(),
+        thread::spawn(log_and_yield, 3).unwrap(),
+    ];
+
+    for (i, handle) in core::array::IntoIter::new(handles).enumerate() {
+        assert_eq!(handle.join(), (i + 1) * 10);
+    }
+
+    LOG.lock(|log| {
+        assert_eq!(log.len, NUM_THREADS * NUM_ROUNDS);
+
+        for (i, id) in log.entries.iter().enumerate() {
+            assert_eq!(*id, (i % NUM_THREADS) + 1);
+        }
+    });
+}
+
+/// Check that yielding without other threads returns right away.
+#[kernel_test]
+fn yield_without_threads_returns() {
+    thread::yield_now();
+}
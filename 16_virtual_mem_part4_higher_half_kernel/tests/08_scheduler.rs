@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Preemptive scheduler tests.
+
+#![feature(custom_test_frameworks)]
+#![no_main]
+#![no_std]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(libkernel::test_runner)]
+
+use core::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+use libkernel::{
+    bsp, cpu, driver::interface::DriverManager, exception, thread, time,
+    time::interface::TimeManager,
+};
+use test_macros::kernel_test;
+
+const NUM_SPINNERS: usize = 3;
+
+/// The time slice used by the tests.
+const TIME_SLICE: Duration = Duration::from_millis(5);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static LOW_RAN: AtomicBool = AtomicBool::new(false);
+
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+static COUNTS: [AtomicUsize; NUM_SPINNERS] = [ZERO; NUM_SPINNERS];
+
+/// Count until told to stop, without ever yielding.
+fn count(id: usize) -> usize {
+    while !STOP.load(Ordering::Relaxed) {
+        COUNTS[id].fetch_add(1, Ordering::Relaxed);
+    }
+
+    0
+}
+
+fn set_low_ran(_: usize) -> usize {
+    LOW_RAN.store(true, Ordering::Relaxed);
+
+    0
+}
+
+/// Spin without yielding and report whether a lower priority thread got to run meanwhile.
+fn spin_and_check_low(_: usize) -> usize {
+    time::time_manager().spin_for(Duration::from_millis(50));
+
+    LOW_RAN.load(Ordering::Relaxed) as usize
+}
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+
+    for i in bsp::driver::driver_manager()
+        .non_early_print_device_drivers()
+        .iter()
+    {
+        if let Err(x) = i.init() {
+            panic!("Error loading driver: {}: {}", i.compatible(), x);
+        }
+    }
+    exception::asynchronous::ipi::register_and_enable_handler().unwrap();
+    time::time_manager()
+        .register_and_enable_irq_handler()
+        .unwrap();
+    thread::start_preemption(TIME_SLICE).unwrap();
+    exception::asynchronous::local_irq_unmask();
+
+    test_main();
+
+    cpu::qemu_exit_success()
+}
+
+/// Check that threads of equal priority that never yield still share the core fairly.
+#[kernel_test]
+fn equal_priority_threads_share_the_core() {
+    let handles = [
+        thread::spawn(count, 0).unwrap(),
+        thread::spawn(count, 1).unwrap(),
+        thread::spawn(count, 2).unwrap(),
+    ];
+
+    thread::sleep(Duration::from_millis(200));
+    STOP.store(true, Ordering::Relaxed);
+
+    for handle in core::array::IntoIter::new(handles) {
+        handle.join();
+    }
+
+    let counts = [
+        COUNTS[0].load(Ordering::Relaxed),
+        COUNTS[1].load(Ordering::Relaxed),
+        COUNTS[2].load(Ordering::Relaxed),
+    ];
+    let min = *counts.iter().min().unwrap();
+    let max = *counts.iter().max().unwrap();
+
+    assert!(min > 0);
+    assert!(min * 2 >= max);
+}
+
+/// Check that a lower priority thread does not run while a higher priority one is ready.
+#[kernel_test]
+fn higher_priority_runs_first() {
+    let low = thread::spawn_with_priority(set_low_ran, 0, thread::Priority::Low).unwrap();
+    let high = thread::spawn_with_priority(spin_and_check_low, 0, thread::Priority::High).unwrap();
+
+    assert_eq!(high.join(), 0);
+    low.join();
+    assert!(LOW_RAN.load(Ordering::Relaxed));
+}
+
+/// Check that sleeping lasts at least the requested duration.
+#[kernel_test]
+fn sleep_lasts_long_enough() {
+    let duration = Duration::from_millis(20);
+    let start = time::time_manager().uptime();
+
+    thread::sleep(duration);
+
+    assert!(time::time_manager().uptime() - start >= duration);
+}
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Blocking synchronization primitive tests.
+
+#![feature(custom_test_frameworks)]
+#![no_main]
+#![no_std]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(libkernel::test_runner)]
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use libkernel::{
+    bsp, cpu,
+    driver::interface::DriverManager,
+    exception, synchronization,
+    synchronization::{Condvar, Event, IRQSafeSpinLock, Semaphore},
+    thread, time,
+    time::{interface::TimeManager, timer_queue},
+};
+use test_macros::kernel_test;
+
+const NUM_ITEMS: usize = 8;
+
+static ITEMS: Semaphore = Semaphore::new(0);
+static CONSUMED: AtomicUsize = AtomicUsize::new(0);
+
+static TIMER_EVENT: Event = Event::new();
+
+static COUNTER: IRQSafeSpinLock<usize> = IRQSafeSpinLock::new(0);
+static COUNTER_CHANGED: Condvar = Condvar::new();
+
+fn consume(_: usize) -> usize {
+    for _ in 0..NUM_ITEMS {
+        ITEMS.acquire();
+        CONSUMED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    0
+}
+
+fn set_timer_event(_: timer_queue::TimerId) {
+    TIMER_EVENT.set();
+}
+
+/// Wait until the counter reaches the given value.
+fn wait_for_counter(target: usize) -> usize {
+    COUNTER_CHANGED.wait_until(&COUNTER, |counter| {
+        if *counter >= target {
+            Some(*counter)
+        } else {
+            None
+        }
+    })
+}
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+
+    for i in bsp::driver::driver_manager()
+        .non_early_print_device_drivers()
+        .iter()
+    {
+        if let Err(x) = i.init() {
+            panic!("Error loading driver: {}: {}", i.compatible(), x);
+        }
+    }
+    exception::asynchronous::ipi::register_and_enable_handler().unwrap();
+    time::time_manager()
+        .register_and_enable_irq_handler()
+        .unwrap();
+    exception::asynchronous::local_irq_unmask();
+
+    test_main();
+
+    cpu::qemu_exit_success()
+}
+
+/// Check that an event set from IRQ context wakes a waiter on a core that does not use threads.
+///
+/// Runs first, before the other tests make the boot core use threads.
+#[kernel_test]
+fn event_wakes_without_threads() {
+    assert!(thread::current().is_none());
+
+    timer_queue::add_oneshot(Duration::from_millis(10), set_timer_event).unwrap();
+    TIMER_EVENT.wait();
+
+    assert!(TIMER_EVENT.is_set());
+    TIMER_EVENT.reset();
+}
+
+/// Check that a consumer blocks until the producer released a permit for each item.
+#[kernel_test]
+fn semaphore_hands_over_items() {
+    let consumer = thread::spawn(consume, 0).unwrap();
+
+    for i in 0..NUM_ITEMS {
+        // The consumer can only have taken what was released so far.
+        assert!(CONSUMED.load(Ordering::Relaxed) <= i);
+
+        ITEMS.release();
+        thread::yield_now();
+    }
+
+    consumer.join();
+    assert_eq!(CONSUMED.load(Ordering::Relaxed), NUM_ITEMS);
+    assert_eq!(ITEMS.available(), 0);
+}
+
+/// Check that an event set from IRQ context wakes a blocked thread.
+#[kernel_test]
+fn event_wakes_blocked_thread() {
+    let start = time::time_manager().uptime();
+    let delay = Duration::from_millis(20);
+
+    timer_queue::add_oneshot(delay, set_timer_event).unwrap();
+    TIMER_EVENT.wait();
+
+    assert!(time::time_manager().uptime() - start >= delay);
+    TIMER_EVENT.reset();
+}
+
+/// Check that waiters on a condition variable see the data they waited for.
+#[kernel_test]
+fn condvar_waits_for_condition() {
+    use synchronization::interface::Mutex;
+
+    let waiters = [
+        thread::spawn(wait_for_counter, 3).unwrap(),
+        thread::spawn(wait_for_counter, 5).unwrap(),
+    ];
+
+    for _ in 0..5 {
+        thread::yield_now();
+
+        COUNTER.lock(|counter| *counter += 1);
+        COUNTER_CHANGED.notify_all();
+    }
+
+    let [first, second] = waiters;
+    assert!(first.join() >= 3);
+    assert_eq!(second.join(), 5);
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+// Copyright (c) 2021 Andre Richter <andre.o.richter@gmail.com>
+
+//! Async executor tests.
+
+#![feature(custom_test_frameworks)]
+#![no_main]
+#![no_std]
+#![reexport_test_harness_main = "test_main"]
+#![test_runner(libkernel::test_runner)]
+
+use core::{
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use libkernel::{
+    bsp, cpu,
+    driver::interface::DriverManager,
+    exception,
+    executor::{Executor, Task},
+    time,
+    time::{interface::TimeManager, Timer},
+};
+use test_macros::kernel_test;
+
+static EXECUTOR: Executor = Executor::new();
+
+static TIMERS_DONE: AtomicUsize = AtomicUsize::new(0);
+
+async fn wait_and_count(duration: Duration) {
+    Timer::after(duration).await;
+
+    TIMERS_DONE.fetch_add(1, Ordering::Relaxed);
+}
+
+#[no_mangle]
+unsafe fn kernel_init() -> ! {
+    bsp::console::qemu_bring_up_console();
+
+    exception::handling_init();
+
+    for i in bsp::driver::driver_manager()
+        .non_early_print_device_drivers()
+        .iter()
+    {
+        if let Err(x) = i.init() {
+            panic!("Error loading driver: {}: {}", i.compatible(), x);
+        }
+    }
+    time::time_manager()
+        .register_and_enable_irq_handler()
+        .unwrap();
+    exception::asynchronous::local_irq_unmask();
+
+    test_main();
+
+    cpu::qemu_exit_success()
+}
+
+/// Check that awaiting a timer lasts at least its duration, and hands out the future's output.
+#[kernel_test]
+fn timer_future_completes_after_duration() {
+    let duration = Duration::from_millis(20);
+    let start = time::time_manager().uptime();
+
+    let output = EXECUTOR
+        .block_on(async {
+            Timer::after(duration).await;
+            42
+        })
+        .unwrap();
+
+    assert_eq!(output, 42);
+    assert!(time::time_manager().uptime() - start >= duration);
+}
+
+/// Check that tasks wait for their timers concurrently.
+#[kernel_test]
+fn tasks_run_concurrently() {
+    let duration = Duration::from_millis(50);
+    let start = time::time_manager().uptime();
+
+    let mut first = wait_and_count(duration);
+    let mut second = wait_and_count(duration);
+
+    // The futures are shadowed, so they can not be moved anymore.
+    let first: Task = unsafe { Pin::new_unchecked(&mut first) };
+    let second: Task = unsafe { Pin::new_unchecked(&mut second) };
+
+    EXECUTOR.run(&mut [first, second]).unwrap();
+
+    let elapsed = time::time_manager().uptime() - start;
+    assert_eq!(TIMERS_DONE.load(Ordering::Relaxed), 2);
+    assert!(elapsed >= duration);
+    assert!(elapsed < duration * 2);
+}
+
+/// Check that a timer whose deadline has passed completes on the first poll.
+#[kernel_test]
+fn expired_timer_is_ready() {
+    EXECUTOR
+        .block_on(Timer::after(Duration::from_secs(0)))
+        .unwrap();
+}
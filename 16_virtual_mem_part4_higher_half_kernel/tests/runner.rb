@@ -127,10 +127,16 @@ end
 ##--------------------------------------------------------------------------------------------------
 ## Script entry point
 ##--------------------------------------------------------------------------------------------------
+# QEMU arguments that only individual tests need.
+QEMU_TEST_SPECIFIC_ARGS = {
+    # Exit on the watchdog's reset instead of booting the test again.
+    '04_watchdog_reboot' => ['-no-reboot']
+}.freeze
+
 binary = ARGV.last
 test_name = binary.gsub(%r{.*deps/}, '').split('-')[0]
 console_test_file = "tests/#{test_name}.rb"
-qemu_cmd = ARGV.join(' ')
+qemu_cmd = (ARGV + QEMU_TEST_SPECIFIC_ARGS.fetch(test_name, [])).join(' ')
 
 test_runner = if File.exist?(console_test_file)
                   load console_test_file
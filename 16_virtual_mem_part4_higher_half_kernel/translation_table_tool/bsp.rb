@@ -19,6 +19,8 @@ class RaspberryPi
             boot_core_stack_start: /__boot_core_stack_start/,
             boot_core_stack_end_exclusive: /__boot_core_stack_end_exclusive/,
 
+            secondary_core_stacks_start: /__secondary_core_stacks_start/,
+
             rx_start: /__rx_start/,
             rx_end_exclusive: /__rx_end_exclusive/,
 
@@ -32,6 +34,9 @@ class RaspberryPi
         symbols = `#{NM_BINARY} --demangle #{kernel_elf}`.split("\n")
         @kernel_virt_addr_space_size = parse_from_symbols(symbols, /__kernel_virt_addr_space_size/)
         @kernel_virt_start_addr = parse_from_symbols(symbols, /__kernel_virt_start_addr/)
+        @secondary_core_stack_guard_page_size =
+            parse_from_symbols(symbols, /__secondary_core_stack_guard_page_size/)
+        @secondary_core_stack_size = parse_from_symbols(symbols, /__secondary_core_stack_size/)
         @virt_addresses = parse_from_symbols(symbols, @virt_addresses)
         @phys_addresses = virt_to_phys(@virt_addresses)
 
@@ -167,8 +172,24 @@ class RaspberryPi
                               boot_core_stack_attribues)
     end
 
+    def descriptor_secondary_core_stack(core_id)
+        name = "Core #{core_id} stack"
+
+        stride = @secondary_core_stack_guard_page_size + @secondary_core_stack_size
+        offset = ((core_id - 1) * stride) + @secondary_core_stack_guard_page_size
+
+        virt_stack_pages = PageArray.new(@virt_addresses[:secondary_core_stacks_start] + offset,
+                                         @secondary_core_stack_size, @kernel_granule::SIZE)
+        phys_stack_pages = PageArray.new(@phys_addresses[:secondary_core_stacks_start] + offset,
+                                         @secondary_core_stack_size, @kernel_granule::SIZE)
+        stack_attribues = AttributeFields.new(:CacheableDRAM, :ReadWrite, :XN)
+
+        MappingDescriptor.new(name, virt_stack_pages, phys_stack_pages, stack_attribues)
+    end
+
     def parse_descriptors
-        [descriptor_ro, descriptor_data, descriptor_boot_core_stack]
+        [descriptor_ro, descriptor_data, descriptor_boot_core_stack] +
+            (1..3).map { |core_id| descriptor_secondary_core_stack(core_id) }
     end
 
     def update_max_descriptor_name_length